@@ -0,0 +1,18 @@
+pub mod ply;
+pub mod stl;
+pub mod subdivision;
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Whether a triangle has any area, so that it's worth building
+fn has_area([p1, p2, p3]: [&[f64; 3]; 3]) -> bool {
+    let [a, b] = [p2, p3].map(|p| [0, 1, 2].map(|i| p[i] - p1[i]));
+    let cross = [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ];
+    cross.iter().any(|&c| c != 0.0)
+}
@@ -0,0 +1,542 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead},
+};
+
+use crate::has_area;
+
+/// A polygon mesh read from a PLY file, in ASCII or binary form
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ply {
+    pub vertices: Vec<[f64; 3]>,
+    /// Present when every vertex has `nx`, `ny` and `nz` properties
+    pub normals: Option<Vec<[f64; 3]>>,
+    /// Present when every vertex has `red`, `green` and `blue` properties,
+    /// scaled to run from 0 to 1
+    pub colors: Option<Vec<[f64; 3]>>,
+    /// Whether `colors` were stored as integers, which are sRGB by
+    /// convention, rather than as linear floats
+    pub colors_are_srgb: bool,
+    /// Indices into `vertices`, all of which are checked to be in range
+    pub faces: Vec<Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    InvalidHeader(String),
+    InvalidData(String),
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(e) => write!(f, "couldn't read PLY data: {}", e),
+            PlyError::InvalidHeader(msg) => write!(f, "invalid PLY header: {}", msg),
+            PlyError::InvalidData(msg) => write!(f, "invalid PLY data: {}", msg),
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlyError {
+    fn from(e: io::Error) -> Self {
+        PlyError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Property {
+    Scalar(String, Scalar),
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Ply {
+    /// Parses a PLY file. The `vertex` element's `x`, `y` and `z` properties
+    /// are required; `nx`, `ny` and `nz` normals and `red`, `green` and `blue`
+    /// colors are used when every vertex has them. Faces come from the
+    /// `vertex_indices` (or `vertex_index`) list of the `face` element, and
+    /// other elements and properties are skipped.
+    pub fn parse(mut reader: impl BufRead) -> Result<Self, PlyError> {
+        let (encoding, elements) = parse_header(&mut reader)?;
+        let mut values = ValueReader {
+            reader,
+            encoding,
+            tokens: Vec::new(),
+        };
+
+        let mut ply = Ply {
+            vertices: Vec::new(),
+            normals: None,
+            colors: None,
+            colors_are_srgb: false,
+            faces: Vec::new(),
+        };
+
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => ply.read_vertices(element, &mut values)?,
+                "face" => ply.read_faces(element, &mut values)?,
+                _ => {
+                    for _ in 0..element.count {
+                        for property in &element.properties {
+                            values.read_property(property)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(&i) = ply
+            .faces
+            .iter()
+            .flatten()
+            .find(|&&i| i >= ply.vertices.len())
+        {
+            return Err(PlyError::InvalidData(format!(
+                "face refers to vertex {}, but there are only {}",
+                i,
+                ply.vertices.len()
+            )));
+        }
+
+        Ok(ply)
+    }
+
+    fn read_vertices(
+        &mut self,
+        element: &Element,
+        values: &mut ValueReader<impl BufRead>,
+    ) -> Result<(), PlyError> {
+        let position = |name| {
+            element
+                .properties
+                .iter()
+                .position(|p| matches!(p, Property::Scalar(n, _) if n == name))
+        };
+        let [x, y, z] = ["x", "y", "z"].map(position);
+        let (Some(x), Some(y), Some(z)) = (x, y, z) else {
+            return Err(PlyError::InvalidHeader(String::from(
+                "vertex element needs x, y and z properties",
+            )));
+        };
+        let normal = match ["nx", "ny", "nz"].map(position) {
+            [Some(nx), Some(ny), Some(nz)] => Some([nx, ny, nz]),
+            _ => None,
+        };
+        let color = match ["red", "green", "blue"].map(position) {
+            [Some(r), Some(g), Some(b)] => Some([r, g, b]),
+            _ => None,
+        };
+
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        for _ in 0..element.count {
+            let mut row = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                row.push(values.read_property(property)?);
+            }
+            let scalar = |i: usize| row[i].first().copied().unwrap_or(0.0);
+
+            self.vertices.push([x, y, z].map(scalar));
+            if let Some(normal) = normal {
+                normals.push(normal.map(scalar));
+            }
+            if let Some(channels) = color {
+                // Integer colors run up to their type's maximum, and float colors up to 1
+                colors.push(channels.map(|i| match &element.properties[i] {
+                    Property::Scalar(_, Scalar::U8) => scalar(i) / 255.0,
+                    Property::Scalar(_, Scalar::U16) => scalar(i) / 65535.0,
+                    _ => scalar(i),
+                }));
+            }
+        }
+
+        self.normals = normal.map(|_| normals);
+        self.colors = color.map(|_| colors);
+        self.colors_are_srgb = color.is_some_and(|[r, ..]| {
+            matches!(
+                element.properties[r],
+                Property::Scalar(_, Scalar::U8 | Scalar::U16)
+            )
+        });
+        Ok(())
+    }
+
+    fn read_faces(
+        &mut self,
+        element: &Element,
+        values: &mut ValueReader<impl BufRead>,
+    ) -> Result<(), PlyError> {
+        let indices = element.properties.iter().position(|p| {
+            matches!(p, Property::List { name, .. } if name == "vertex_indices" || name == "vertex_index")
+        });
+        let Some(indices) = indices else {
+            return Err(PlyError::InvalidHeader(String::from(
+                "face element needs a vertex_indices list",
+            )));
+        };
+
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                let read = values.read_property(property)?;
+                if i == indices {
+                    self.faces
+                        .push(read.into_iter().map(index).collect::<Result<_, _>>()?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The vertex indices of each triangle, fan-triangulating faces with
+    /// more than three vertices and dropping triangles with no area
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.faces
+            .iter()
+            .flat_map(|face| (2..face.len()).map(|i| [face[0], face[i - 1], face[i]]))
+            .filter(|corners| has_area(corners.map(|i| &self.vertices[i])))
+    }
+}
+
+fn parse_header(reader: &mut impl BufRead) -> Result<(Encoding, Vec<Element>), PlyError> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> Result<(), PlyError> {
+        line.clear();
+        if reader.read_line(line)? == 0 {
+            return Err(PlyError::InvalidHeader(String::from("missing end_header")));
+        }
+        Ok(())
+    };
+
+    next_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err(PlyError::InvalidHeader(String::from(
+            "doesn't start with 'ply'",
+        )));
+    }
+
+    let mut encoding = None;
+    let mut elements = Vec::<Element>::new();
+    loop {
+        next_line(&mut line)?;
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[..] {
+            ["end_header"] => break,
+            [] | ["comment", ..] | ["obj_info", ..] => (),
+            ["format", format, _version] => {
+                encoding = Some(match format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    _ => {
+                        return Err(PlyError::InvalidHeader(format!(
+                            "unknown format '{}'",
+                            format
+                        )))
+                    }
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: String::from(name),
+                count: count.parse().map_err(|_| {
+                    PlyError::InvalidHeader(format!("invalid element count '{}'", count))
+                })?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property::List {
+                    name: String::from(name),
+                    count: parse_scalar(count)?,
+                    item: parse_scalar(item)?,
+                };
+                add_property(&mut elements, property)?;
+            }
+            ["property", ty, name] => {
+                let property = Property::Scalar(String::from(name), parse_scalar(ty)?);
+                add_property(&mut elements, property)?;
+            }
+            _ => {
+                return Err(PlyError::InvalidHeader(format!(
+                    "unexpected line '{}'",
+                    line.trim()
+                )))
+            }
+        }
+    }
+
+    let encoding = encoding.ok_or(PlyError::InvalidHeader(String::from("missing format")))?;
+    Ok((encoding, elements))
+}
+
+fn add_property(elements: &mut [Element], property: Property) -> Result<(), PlyError> {
+    match elements.last_mut() {
+        Some(element) => {
+            element.properties.push(property);
+            Ok(())
+        }
+        None => Err(PlyError::InvalidHeader(String::from(
+            "property before any element",
+        ))),
+    }
+}
+
+fn parse_scalar(name: &str) -> Result<Scalar, PlyError> {
+    match name {
+        "char" | "int8" => Ok(Scalar::I8),
+        "uchar" | "uint8" => Ok(Scalar::U8),
+        "short" | "int16" => Ok(Scalar::I16),
+        "ushort" | "uint16" => Ok(Scalar::U16),
+        "int" | "int32" => Ok(Scalar::I32),
+        "uint" | "uint32" => Ok(Scalar::U32),
+        "float" | "float32" => Ok(Scalar::F32),
+        "double" | "float64" => Ok(Scalar::F64),
+        _ => Err(PlyError::InvalidHeader(format!("unknown type '{}'", name))),
+    }
+}
+
+/// Checks that a list length or vertex index read as a number is a whole,
+/// non-negative one
+fn index(n: f64) -> Result<usize, PlyError> {
+    if n >= 0.0 && n.fract() == 0.0 {
+        Ok(n as usize)
+    } else {
+        Err(PlyError::InvalidData(format!(
+            "invalid count or index '{}'",
+            n
+        )))
+    }
+}
+
+/// Reads the values after the header, one at a time
+struct ValueReader<R> {
+    reader: R,
+    encoding: Encoding,
+    /// The rest of the current line, in reverse, for ASCII files
+    tokens: Vec<String>,
+}
+
+impl<R: BufRead> ValueReader<R> {
+    /// Reads one property, as a single value for scalars or all of a list's items
+    fn read_property(&mut self, property: &Property) -> Result<Vec<f64>, PlyError> {
+        match property {
+            Property::Scalar(_, ty) => Ok(vec![self.read(*ty)?]),
+            Property::List { count, item, .. } => {
+                let n = index(self.read(*count)?)?;
+                (0..n).map(|_| self.read(*item)).collect()
+            }
+        }
+    }
+
+    fn read(&mut self, ty: Scalar) -> Result<f64, PlyError> {
+        if self.encoding == Encoding::Ascii {
+            return self.read_ascii();
+        }
+
+        macro_rules! read_binary {
+            ($t:ty) => {{
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                self.reader.read_exact(&mut bytes)?;
+                let value = if self.encoding == Encoding::BinaryBigEndian {
+                    <$t>::from_be_bytes(bytes)
+                } else {
+                    <$t>::from_le_bytes(bytes)
+                };
+                value as f64
+            }};
+        }
+
+        Ok(match ty {
+            Scalar::I8 => read_binary!(i8),
+            Scalar::U8 => read_binary!(u8),
+            Scalar::I16 => read_binary!(i16),
+            Scalar::U16 => read_binary!(u16),
+            Scalar::I32 => read_binary!(i32),
+            Scalar::U32 => read_binary!(u32),
+            Scalar::F32 => read_binary!(f32),
+            Scalar::F64 => read_binary!(f64),
+        })
+    }
+
+    fn read_ascii(&mut self) -> Result<f64, PlyError> {
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(PlyError::InvalidData(String::from(
+                    "file ended before all elements were read",
+                )));
+            }
+            self.tokens = line.split_whitespace().rev().map(String::from).collect();
+        }
+
+        let token = self.tokens.pop().unwrap();
+        token
+            .parse()
+            .map_err(|_| PlyError::InvalidData(format!("invalid number '{}'", token)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment A unit square split in two
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3
+";
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+             property float z\nproperty float nx\nproperty float ny\nproperty float nz\n\
+             element face 1\nproperty list uchar uint vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+
+        let floats: [f32; 18] = [
+            0.0, 0.0, 0.0, 0.0, 0.0, -1.0, //
+            1.0, 0.0, 0.0, 0.0, 0.0, -1.0, //
+            0.0, 1.0, 0.0, 0.0, 0.0, -1.0,
+        ];
+        for f in floats {
+            data.extend(if big_endian {
+                f.to_be_bytes()
+            } else {
+                f.to_le_bytes()
+            });
+        }
+        data.push(3);
+        for i in [0u32, 1, 2] {
+            data.extend(if big_endian {
+                i.to_be_bytes()
+            } else {
+                i.to_le_bytes()
+            });
+        }
+        data
+    }
+
+    #[test]
+    fn parsing_an_ascii_file() {
+        let ply = Ply::parse(ASCII.as_bytes()).unwrap();
+
+        assert_eq!(ply.vertices.len(), 4);
+        assert_eq!(ply.vertices[2], [1.0, 1.0, 0.0]);
+        assert_eq!(ply.faces, [vec![0, 1, 2, 3]]);
+        assert!(ply.normals.is_none());
+        assert_eq!(ply.colors.unwrap()[3], [0.0, 0.0, 1.0]);
+        assert!(ply.colors_are_srgb);
+    }
+
+    #[test]
+    fn parsing_binary_files() {
+        for big_endian in [false, true] {
+            let ply = Ply::parse(&binary(big_endian)[..]).unwrap();
+
+            assert_eq!(
+                ply.vertices,
+                [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            );
+            assert_eq!(ply.normals.unwrap()[1], [0.0, 0.0, -1.0]);
+            assert_eq!(ply.faces, [vec![0, 1, 2]]);
+        }
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        let data = ASCII.replace("4 0 1 2 3", "3 0 1 4");
+
+        let error = Ply::parse(data.as_bytes()).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid PLY data: face refers to vertex 4, but there are only 4"
+        );
+    }
+
+    #[test]
+    fn fractional_or_negative_indices_are_errors() {
+        for (face, bad) in [("3 0 1 2.5", "2.5"), ("3 0 -1 2", "-1"), ("-3 0 1 2", "-3")] {
+            let data = ASCII
+                .replace("uchar int vertex_indices", "int float vertex_indices")
+                .replace("4 0 1 2 3", face);
+
+            let error = Ply::parse(data.as_bytes()).err().unwrap();
+
+            assert_eq!(
+                error.to_string(),
+                format!("invalid PLY data: invalid count or index '{}'", bad)
+            );
+        }
+    }
+
+    #[test]
+    fn faces_are_fanned_into_triangles_with_area() {
+        let data = ASCII
+            .replace("element face 1", "element face 2")
+            .replace("4 0 1 2 3", "4 0 1 2 3\n3 0 1 0");
+        let ply = Ply::parse(data.as_bytes()).unwrap();
+
+        assert_eq!(ply.triangles().collect::<Vec<_>>(), [[0, 1, 2], [0, 2, 3]]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Read},
+};
+
+use crate::has_area;
+
+/// The facets of an STL file, in ASCII or binary form
+pub struct Stl {
+    triangles: Vec<[[f64; 3]; 3]>,
+}
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    /// An ASCII file which couldn't be parsed, with the line number (counting
+    /// from 1) where the problem was found
+    InvalidAscii {
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(e) => write!(f, "couldn't read STL data: {}", e),
+            StlError::InvalidAscii { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for StlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StlError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StlError {
+    fn from(e: io::Error) -> Self {
+        StlError::Io(e)
+    }
+}
+
+impl Stl {
+    /// Parses an STL file. Binary files are told apart from ASCII ones by
+    /// their size matching the triangle count in their header, since binary
+    /// headers may start with `solid` too. The stored facet normals are
+    /// ignored in favor of the ones implied by the vertex order.
+    pub fn parse(mut reader: impl Read) -> Result<Self, StlError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        if data.len() >= 84 {
+            let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+            if data.len() == 84 + 50 * count {
+                return Ok(Stl::parse_binary(&data[84..]));
+            }
+        }
+        Stl::parse_ascii(&String::from_utf8_lossy(&data))
+    }
+
+    /// Each record is a normal, three vertices, then two bytes of attributes
+    fn parse_binary(records: &[u8]) -> Self {
+        let float = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let point = |bytes: &[u8]| [0, 4, 8].map(|i| float(&bytes[i..i + 4]).into());
+
+        let triangles = records
+            .chunks_exact(50)
+            .map(|r| [point(&r[12..24]), point(&r[24..36]), point(&r[36..48])])
+            .collect();
+        Stl { triangles }
+    }
+
+    fn parse_ascii(text: &str) -> Result<Self, StlError> {
+        let mut triangles = Vec::new();
+        let mut vertices = Vec::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let error = |message: String| StlError::InvalidAscii {
+                line: n + 1,
+                message,
+            };
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            match tokens[..] {
+                ["vertex", x, y, z] => {
+                    let [x, y, z] = [x, y, z].map(|s| {
+                        s.parse::<f64>()
+                            .map_err(|_| error(format!("invalid number '{}'", s)))
+                    });
+                    vertices.push([x?, y?, z?]);
+                }
+                ["endfacet"] => {
+                    let facet = std::mem::take(&mut vertices);
+                    let facet = <[[f64; 3]; 3]>::try_from(facet).map_err(|vs| {
+                        error(format!("facet has {} vertices, rather than 3", vs.len()))
+                    })?;
+                    triangles.push(facet);
+                }
+                ["vertex", ..] => {
+                    return Err(error(String::from("vertex needs 3 coordinates")));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Stl { triangles })
+    }
+
+    /// The facets' vertices, dropping facets with no area
+    pub fn triangles(&self) -> impl Iterator<Item = &[[f64; 3]; 3]> {
+        self.triangles
+            .iter()
+            .filter(|[p1, p2, p3]| has_area([p1, p2, p3]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "solid square
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    #[test]
+    fn parsing_an_ascii_file() {
+        let stl = Stl::parse(ASCII.as_bytes()).unwrap();
+
+        assert_eq!(stl.triangles.len(), 2);
+        assert_eq!(
+            stl.triangles[1],
+            [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn parsing_a_binary_file_whose_header_starts_with_solid() {
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(80, 0);
+        data.extend(1u32.to_le_bytes());
+        for f in [
+            0.0f32, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            data.extend(f.to_le_bytes());
+        }
+        data.extend([0, 0]);
+
+        let stl = Stl::parse(&data[..]).unwrap();
+
+        assert_eq!(
+            stl.triangles,
+            [[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]]
+        );
+    }
+
+    #[test]
+    fn facets_without_three_vertices_are_errors() {
+        let data = ASCII.replacen("      vertex 1 0 0\n", "", 1);
+
+        let error = Stl::parse(data.as_bytes()).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "line 7: facet has 2 vertices, rather than 3"
+        );
+    }
+
+    #[test]
+    fn facets_with_no_area_are_dropped() {
+        let data = ASCII.replacen("vertex 1 1 0", "vertex 2 0 0", 1);
+        let stl = Stl::parse(data.as_bytes()).unwrap();
+
+        assert_eq!(stl.triangles().count(), 1);
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::dot;
+
+/// The rules used to smooth a mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scheme {
+    /// Splits each triangle into four. Other faces are triangulated first.
+    Loop,
+    /// Splits each face into quads, one per corner
+    CatmullClark,
+}
+
+/// How to subdivide a mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Subdivision {
+    pub scheme: Scheme,
+    /// How many times to subdivide. Each level multiplies the face count by
+    /// about four.
+    pub levels: u32,
+    /// Edges where the faces meet at more than this angle, in radians, stay
+    /// sharp, as do the edges along the mesh's boundary. If `None`, only the
+    /// boundary is kept sharp.
+    pub crease_angle: Option<f64>,
+}
+
+/// A face of a mesh being subdivided, as indices into its positions and
+/// texture coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub vertices: Vec<usize>,
+    pub texture_coordinates: Option<Vec<usize>>,
+    /// Carried over to every face the polygon is split into, so that callers
+    /// can keep track of groups and materials
+    pub tag: usize,
+}
+
+/// A mesh of polygons with any number of sides, to be subdivided
+pub struct PolygonMesh {
+    pub positions: Vec<[f64; 3]>,
+    pub texture_coordinates: Vec<(f64, f64)>,
+    pub faces: Vec<Polygon>,
+}
+
+/// An edge, with its lower-numbered vertex first
+type Edge = (usize, usize);
+
+fn edge(a: usize, b: usize) -> Edge {
+    (a.min(b), a.max(b))
+}
+
+impl PolygonMesh {
+    pub fn subdivide(mut self, subdivision: &Subdivision) -> Self {
+        if subdivision.levels == 0 {
+            return self;
+        }
+        if subdivision.scheme == Scheme::Loop {
+            self.faces = self.faces.into_iter().flat_map(fan_triangulate).collect();
+        }
+        // Faces which repeat a vertex or have no area have no shape to smooth,
+        // and would leave Loop's edge points without an opposite corner
+        let positions = &self.positions;
+        self.faces.retain(|f| {
+            let distinct = f.vertices.iter().collect::<HashSet<_>>().len() == f.vertices.len();
+            distinct && normalize(newell_normal(&f.vertices, positions)).is_some()
+        });
+
+        let mut sharp = match subdivision.crease_angle {
+            Some(angle) => self.creases(angle.cos()),
+            None => HashSet::new(),
+        };
+        for _ in 0..subdivision.levels {
+            (self, sharp) = self.subdivide_once(subdivision.scheme, &sharp);
+        }
+        self
+    }
+
+    /// The edges between two faces which meet at more than the crease angle
+    fn creases(&self, cos_crease_angle: f64) -> HashSet<Edge> {
+        let normals = self
+            .faces
+            .iter()
+            .map(|f| normalize(newell_normal(&f.vertices, &self.positions)))
+            .collect::<Vec<_>>();
+
+        self.edge_faces()
+            .into_iter()
+            .filter(|(_, faces)| match faces[..] {
+                [f1, f2] => match (&normals[f1], &normals[f2]) {
+                    (Some(n1), Some(n2)) => dot(n1, n2) < cos_crease_angle,
+                    _ => false,
+                },
+                _ => false,
+            })
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    /// The faces on either side of each edge
+    fn edge_faces(&self) -> HashMap<Edge, Vec<usize>> {
+        let mut edge_faces = HashMap::<Edge, Vec<usize>>::new();
+        for (i, f) in self.faces.iter().enumerate() {
+            for (a, b) in corners_and_next(&f.vertices) {
+                edge_faces.entry(edge(a, b)).or_default().push(i);
+            }
+        }
+        edge_faces
+    }
+
+    fn subdivide_once(self, scheme: Scheme, sharp: &HashSet<Edge>) -> (Self, HashSet<Edge>) {
+        let edge_faces = self.edge_faces();
+        // Boundary and non-manifold edges are treated like creases
+        let is_hard = |e: &Edge| sharp.contains(e) || edge_faces[e].len() != 2;
+
+        let mut neighbors = vec![Vec::new(); self.positions.len()];
+        let mut hard_neighbors = vec![Vec::new(); self.positions.len()];
+        for e in edge_faces.keys() {
+            let (a, b) = *e;
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+            if is_hard(e) {
+                hard_neighbors[a].push(b);
+                hard_neighbors[b].push(a);
+            }
+        }
+        let mut vertex_faces = vec![Vec::new(); self.positions.len()];
+        for (i, f) in self.faces.iter().enumerate() {
+            for &v in &f.vertices {
+                vertex_faces[v].push(i);
+            }
+        }
+
+        let face_points = self
+            .faces
+            .iter()
+            .map(|f| centroid(f.vertices.iter().map(|&v| &self.positions[v])))
+            .collect::<Vec<_>>();
+
+        // New positions are the moved vertices, then the edge points, then
+        // (for Catmull-Clark) the face points
+        let mut positions = Vec::with_capacity(self.positions.len() + edge_faces.len());
+        for (v, p) in self.positions.iter().enumerate() {
+            let hard = &hard_neighbors[v];
+            let n = neighbors[v].len() as f64;
+            let moved = match hard.len() {
+                // A crease or boundary vertex only moves along the crease
+                2 => weighted_sum([
+                    (p, 0.75),
+                    (&self.positions[hard[0]], 0.125),
+                    (&self.positions[hard[1]], 0.125),
+                ]),
+                // A corner stays put, as do vertices no face uses
+                h if h > 2 || vertex_faces[v].is_empty() => *p,
+                _ if scheme == Scheme::Loop => {
+                    let beta = if n == 3.0 {
+                        3.0 / 16.0
+                    } else {
+                        3.0 / (8.0 * n)
+                    };
+                    let neighbors = neighbors[v].iter().map(|&u| (&self.positions[u], beta));
+                    weighted_sum([(p, 1.0 - n * beta)].into_iter().chain(neighbors))
+                }
+                // (Q + 2R + (n - 3)V) / n, where Q is the average of the face
+                // points and R of the edge midpoints, which average V with the
+                // neighbors
+                _ => {
+                    let q = centroid(vertex_faces[v].iter().map(|&f| &face_points[f]));
+                    let neighbors = centroid(neighbors[v].iter().map(|&u| &self.positions[u]));
+                    weighted_sum([(&q, 1.0 / n), (&neighbors, 1.0 / n), (p, (n - 2.0) / n)])
+                }
+            };
+            positions.push(moved);
+        }
+
+        let mut edge_points = HashMap::with_capacity(edge_faces.len());
+        for (e, faces) in &edge_faces {
+            let (a, b) = *e;
+            let (pa, pb) = (&self.positions[a], &self.positions[b]);
+            let point = if is_hard(e) {
+                weighted_sum([(pa, 0.5), (pb, 0.5)])
+            } else {
+                match scheme {
+                    Scheme::Loop => {
+                        let opposite = faces.iter().map(|&f| {
+                            let v = &self.faces[f].vertices;
+                            let other = v.iter().find(|&&u| u != a && u != b).unwrap();
+                            (&self.positions[*other], 0.125)
+                        });
+                        weighted_sum([(pa, 0.375), (pb, 0.375)].into_iter().chain(opposite))
+                    }
+                    Scheme::CatmullClark => weighted_sum([
+                        (pa, 0.25),
+                        (pb, 0.25),
+                        (&face_points[faces[0]], 0.25),
+                        (&face_points[faces[1]], 0.25),
+                    ]),
+                }
+            };
+            edge_points.insert(*e, positions.len());
+            positions.push(point);
+        }
+
+        let mut texture_coordinates = self.texture_coordinates;
+        let mut edge_texture_coordinates = HashMap::new();
+        let mut faces = Vec::with_capacity(self.faces.len() * 4);
+        for (i, f) in self.faces.iter().enumerate() {
+            let n = f.vertices.len();
+            let edge_vertex = |c: usize| edge_points[&edge(f.vertices[c], f.vertices[(c + 1) % n])];
+            // Texture coordinates are interpolated linearly within each face,
+            // and shared between faces which shared them before
+            let uvs = f.texture_coordinates.as_ref().map(|ts| {
+                let edges = (0..n)
+                    .map(|c| {
+                        let (ta, tb) = (ts[c], ts[(c + 1) % n]);
+                        *edge_texture_coordinates
+                            .entry(edge(ta, tb))
+                            .or_insert_with(|| {
+                                let (ua, va) = texture_coordinates[ta];
+                                let (ub, vb) = texture_coordinates[tb];
+                                texture_coordinates.push(((ua + ub) / 2.0, (va + vb) / 2.0));
+                                texture_coordinates.len() - 1
+                            })
+                    })
+                    .collect::<Vec<_>>();
+                let center = ts.iter().fold((0.0, 0.0), |(u, v), &t| {
+                    (u + texture_coordinates[t].0, v + texture_coordinates[t].1)
+                });
+                texture_coordinates.push((center.0 / n as f64, center.1 / n as f64));
+                (ts, edges, texture_coordinates.len() - 1)
+            });
+            let child = |vertices: Vec<usize>, texture_coordinates: Option<Vec<usize>>| Polygon {
+                vertices,
+                texture_coordinates,
+                tag: f.tag,
+            };
+
+            match scheme {
+                Scheme::Loop => {
+                    let [e0, e1, e2] = [0, 1, 2].map(edge_vertex);
+                    let [v0, v1, v2] = [0, 1, 2].map(|c| f.vertices[c]);
+                    let uv = |pick: fn(&[usize], &[usize]) -> Vec<usize>| {
+                        uvs.as_ref().map(|(ts, es, _)| pick(ts, es))
+                    };
+                    faces.push(child(vec![v0, e0, e2], uv(|t, e| vec![t[0], e[0], e[2]])));
+                    faces.push(child(vec![e0, v1, e1], uv(|t, e| vec![e[0], t[1], e[1]])));
+                    faces.push(child(vec![e2, e1, v2], uv(|t, e| vec![e[2], e[1], t[2]])));
+                    faces.push(child(vec![e0, e1, e2], uv(|_, e| vec![e[0], e[1], e[2]])));
+                }
+                Scheme::CatmullClark => {
+                    let face_vertex = positions.len();
+                    positions.push(face_points[i]);
+                    for c in 0..n {
+                        let previous = (c + n - 1) % n;
+                        let vertices = vec![
+                            f.vertices[c],
+                            edge_vertex(c),
+                            face_vertex,
+                            edge_vertex(previous),
+                        ];
+                        let uv = uvs
+                            .as_ref()
+                            .map(|(ts, es, center)| vec![ts[c], es[c], *center, es[previous]]);
+                        faces.push(child(vertices, uv));
+                    }
+                }
+            }
+        }
+
+        // Each half of a sharp edge stays sharp
+        let sharp = sharp
+            .iter()
+            .flat_map(|&(a, b)| {
+                let middle = edge_points[&(a, b)];
+                [edge(a, middle), edge(middle, b)]
+            })
+            .collect();
+
+        let mesh = PolygonMesh {
+            positions,
+            texture_coordinates,
+            faces,
+        };
+        (mesh, sharp)
+    }
+}
+
+/// Each corner of a polygon paired with the next one around
+fn corners_and_next(vertices: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let n = vertices.len();
+    (0..n).map(move |i| (vertices[i], vertices[(i + 1) % n]))
+}
+
+/// Adds up points scaled by weights, which should sum to 1
+fn weighted_sum<'a>(terms: impl IntoIterator<Item = (&'a [f64; 3], f64)>) -> [f64; 3] {
+    terms
+        .into_iter()
+        .fold([0.0; 3], |sum, (p, w)| [0, 1, 2].map(|i| sum[i] + p[i] * w))
+}
+
+fn centroid<'a>(points: impl Iterator<Item = &'a [f64; 3]>) -> [f64; 3] {
+    let points = points.collect::<Vec<_>>();
+    let weight = 1.0 / points.len() as f64;
+    weighted_sum(points.into_iter().map(|p| (p, weight)))
+}
+
+/// The normal of a polygon which may not be quite flat, with its length
+/// twice the polygon's area
+fn newell_normal(vertices: &[usize], positions: &[[f64; 3]]) -> [f64; 3] {
+    corners_and_next(vertices).fold([0.0; 3], |[x, y, z], (a, b)| {
+        let ([px, py, pz], [qx, qy, qz]) = (positions[a], positions[b]);
+        [
+            x + (py - qy) * (pz + qz),
+            y + (pz - qz) * (px + qx),
+            z + (px - qx) * (py + qy),
+        ]
+    })
+}
+
+fn normalize(v: [f64; 3]) -> Option<[f64; 3]> {
+    let length = dot(&v, &v).sqrt();
+    (length > 0.0).then(|| v.map(|c| c / length))
+}
+
+fn fan_triangulate(polygon: Polygon) -> Vec<Polygon> {
+    let corners = |ids: &[usize], i: usize| vec![ids[0], ids[i - 1], ids[i]];
+    (2..polygon.vertices.len())
+        .map(|i| Polygon {
+            vertices: corners(&polygon.vertices, i),
+            texture_coordinates: polygon
+                .texture_coordinates
+                .as_ref()
+                .map(|ts| corners(ts, i)),
+            tag: polygon.tag,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts;
+
+    use super::*;
+
+    /// A cube from -1 to 1, with each face as a quad wound the same way
+    fn cube() -> PolygonMesh {
+        let positions = (0..8)
+            .map(|i| {
+                let coordinate = |bit| if i & bit == 0 { -1.0 } else { 1.0 };
+                [coordinate(1), coordinate(2), coordinate(4)]
+            })
+            .collect();
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ]
+        .into_iter()
+        .map(|vertices| Polygon {
+            vertices: vertices.to_vec(),
+            texture_coordinates: None,
+            tag: 0,
+        })
+        .collect();
+        PolygonMesh {
+            positions,
+            texture_coordinates: Vec::new(),
+            faces,
+        }
+    }
+
+    fn subdivide(
+        mesh: PolygonMesh,
+        scheme: Scheme,
+        levels: u32,
+        crease_angle: Option<f64>,
+    ) -> PolygonMesh {
+        mesh.subdivide(&Subdivision {
+            scheme,
+            levels,
+            crease_angle,
+        })
+    }
+
+    fn assert_point_approx_equals(a: &[f64; 3], b: &[f64; 3]) {
+        assert!(
+            (0..3).all(|i| (a[i] - b[i]).abs() < 1e-9),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn catmull_clark_splits_faces_into_quads() {
+        let mesh = subdivide(cube(), Scheme::CatmullClark, 2, None);
+
+        assert_eq!(mesh.faces.len(), 6 * 4 * 4);
+        assert!(mesh.faces.iter().all(|f| f.vertices.len() == 4));
+        // Each level adds a point per edge and per face
+        assert_eq!(mesh.positions.len(), 98);
+    }
+
+    #[test]
+    fn catmull_clark_moves_cube_corners_inwards() {
+        let mesh = subdivide(cube(), Scheme::CatmullClark, 1, None);
+
+        // A corner with three faces moves to (Q + 2R + 0V) / 3
+        let c = 5.0 / 9.0;
+        assert_point_approx_equals(&mesh.positions[7], &[c, c, c]);
+    }
+
+    #[test]
+    fn faces_repeating_a_vertex_are_dropped() {
+        for (scheme, face_count) in [(Scheme::Loop, 48), (Scheme::CatmullClark, 24)] {
+            let mut mesh = cube();
+            mesh.positions.push([3.0, 3.0, 3.0]);
+            mesh.faces.push(Polygon {
+                vertices: vec![8, 0, 0],
+                texture_coordinates: None,
+                tag: 0,
+            });
+
+            let mesh = subdivide(mesh, scheme, 1, None);
+
+            assert_eq!(mesh.faces.len(), face_count);
+            assert!(mesh.faces.iter().all(|f| !f.vertices.contains(&8)));
+        }
+    }
+
+    #[test]
+    fn loop_splits_triangles_into_four() {
+        let mesh = subdivide(cube(), Scheme::Loop, 1, None);
+
+        // The cube's quads are split into 12 triangles first
+        assert_eq!(mesh.faces.len(), 48);
+        assert!(mesh.faces.iter().all(|f| f.vertices.len() == 3));
+        assert_eq!(mesh.positions.len(), 8 + 18);
+    }
+
+    #[test]
+    fn sharp_creases_keep_the_cube_flat() {
+        for scheme in [Scheme::Loop, Scheme::CatmullClark] {
+            let mesh = subdivide(cube(), scheme, 2, Some(consts::FRAC_PI_4));
+
+            // Every corner has three sharp edges, so stays put, and every
+            // point on an edge or face stays on the cube's surface
+            assert_eq!(mesh.positions[7], [1.0, 1.0, 1.0]);
+            for p in &mesh.positions {
+                let on_surface = p.iter().any(|c| (c.abs() - 1.0).abs() < 1e-9);
+                assert!(on_surface, "{:?} is off the cube", p);
+            }
+        }
+    }
+
+    #[test]
+    fn boundary_vertices_stay_on_the_boundary() {
+        // A flat square of four quads around the origin
+        let positions = (0..9)
+            .map(|i| [(i % 3) as f64 - 1.0, (i / 3) as f64 - 1.0, 0.0])
+            .collect();
+        let faces = [[0, 1, 4, 3], [1, 2, 5, 4], [3, 4, 7, 6], [4, 5, 8, 7]]
+            .into_iter()
+            .map(|vertices| Polygon {
+                vertices: vertices.to_vec(),
+                texture_coordinates: None,
+                tag: 0,
+            })
+            .collect();
+        let grid = PolygonMesh {
+            positions,
+            texture_coordinates: Vec::new(),
+            faces,
+        };
+
+        let mesh = subdivide(grid, Scheme::CatmullClark, 1, None);
+
+        // The middle of an edge moves along the boundary, not inwards
+        assert_point_approx_equals(&mesh.positions[1], &[0.0, -1.0, 0.0]);
+        // A boundary corner has two boundary edges, so follows the crease rule
+        assert_point_approx_equals(&mesh.positions[0], &[-0.875, -0.875, 0.0]);
+        assert_point_approx_equals(&mesh.positions[4], &[0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn texture_coordinates_are_interpolated_and_tags_kept() {
+        let mesh = PolygonMesh {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            texture_coordinates: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            faces: vec![Polygon {
+                vertices: vec![0, 1, 2, 3],
+                texture_coordinates: Some(vec![0, 1, 2, 3]),
+                tag: 7,
+            }],
+        };
+
+        let mesh = subdivide(mesh, Scheme::CatmullClark, 1, None);
+
+        assert!(mesh.faces.iter().all(|f| f.tag == 7));
+        let uvs = mesh.faces[0]
+            .texture_coordinates
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|&t| mesh.texture_coordinates[t])
+            .collect::<Vec<_>>();
+        assert_eq!(uvs, [(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.5)]);
+    }
+}
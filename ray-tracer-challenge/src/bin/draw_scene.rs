@@ -1,10 +1,13 @@
 #![allow(dead_code)]
 
-use std::{f64::consts, fs::File, io::BufReader};
+use std::{f64::consts, fs::File, io::BufReader, path::Path, thread, time::Duration};
 
 use ray_tracer_challenge::{
     draw::color::{self, Color},
-    io::wavefront_obj::WavefrontObj,
+    io::{
+        mtl::MtlLibrary,
+        wavefront_obj::{NormalWeighting, WavefrontObj},
+    },
     math::{matrix::InvertibleMatrix, point::Point3d, vector::Vec3d},
     scene::{
         camera::{Camera, RenderOpts},
@@ -22,13 +25,26 @@ use ray_tracer_challenge::{
             Object,
         },
         pattern::{checker3d::Checker3d, stripe::Stripe},
+        progress::{CancellationToken, Progress},
         transformation,
         world::World,
         Scene,
     },
-    util,
+    stats, util,
 };
 
+/// Prints the percentage done and the time left, each time the percentage changes
+fn show_progress(progress: &Progress) {
+    let percent = |pixels: usize| pixels * 100 / progress.pixels_total;
+    if percent(progress.pixels_done) != percent(progress.pixels_done - 1) {
+        eprint!(
+            "\r{:3}% done, {:.0?} left ",
+            percent(progress.pixels_done),
+            progress.eta().unwrap_or_default()
+        );
+    }
+}
+
 const RES_X: usize = 600;
 const RES_Y: usize = 400;
 
@@ -37,17 +53,78 @@ fn main() {
     let now = Instant::now();
 
     let scene = test_obj_world();
+    let opts = RenderOpts {
+        anti_aliasing_samples: 2,
+        ..Default::default()
+    };
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // Passing --debug-pixel X Y writes out how that pixel's color came about,
+    // instead of rendering
+    if let Some(n) = args.iter().position(|a| a == "--debug-pixel") {
+        let coord = |i: usize| -> usize {
+            args.get(n + i)
+                .and_then(|a| a.parse().ok())
+                .expect("usage: --debug-pixel X Y")
+        };
+        let (x, y) = (coord(1), coord(2));
+        let trace = scene.debug_pixel(x, y, &opts);
+        let filename = format!("output/scene-pixel-{}-{}.json", x, y);
+        std::fs::write(&filename, serde_json::to_string_pretty(&trace).unwrap())
+            .expect("unable to write file");
+        println!("Wrote {}", filename);
+        return;
+    }
+
+    // Passing --stats counts rays and intersection tests while rendering
+    let show_stats = args.iter().any(|a| a == "--stats");
+    if show_stats {
+        stats::enable();
+    }
+
+    // Passing --time-limit SECONDS stops rendering after that long, keeping
+    // the pixels finished so far
+    let cancel = CancellationToken::new();
+    if let Some(n) = args.iter().position(|a| a == "--time-limit") {
+        let seconds: f64 = args
+            .get(n + 1)
+            .and_then(|a| a.parse().ok())
+            .expect("usage: --time-limit SECONDS");
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs_f64(seconds));
+            cancel.cancel();
+        });
+    }
 
     println!("Rendering scene...");
-    let canvas = scene.render(&RenderOpts {
-        anti_aliasing_samples: 2,
-    });
-    println!("Scene rendered.");
+    let canvas = scene.render_with_progress(&opts, &show_progress, &cancel);
+    if cancel.is_cancelled() {
+        println!("\nRendering stopped early.");
+    } else {
+        println!("\nScene rendered.");
+    }
 
     let elapsed = now.elapsed();
     println!("Elapsed: {:.2?}", elapsed);
+    if show_stats {
+        print!("{}", stats::snapshot().summary(elapsed));
+    }
 
     util::write_to_file(&canvas, "output/scene");
+
+    // Passing --passes also writes the ambient, diffuse, specular, reflection,
+    // refraction and per-light passes
+    if args.iter().any(|a| a == "--passes") {
+        println!("Rendering lighting passes...");
+        util::write_passes_to_files(&scene.render_passes(&opts), "output/scene");
+    }
+
+    // Passing --aovs also writes depth, normal, albedo, ID and motion passes
+    if args.iter().any(|a| a == "--aovs") {
+        println!("Rendering AOVs...");
+        util::write_aovs_to_files(&scene.render_aovs(), "output/scene");
+    }
 }
 
 fn hexagon_corner() -> impl Object {
@@ -319,10 +396,19 @@ fn test_mirror_world() -> Scene {
 
 fn test_obj_world() -> Scene {
     let obj_file = File::open("objs/spot_triangulated.obj").unwrap();
-    let reader = BufReader::new(obj_file);
+    let mut parsed = WavefrontObj::parse(BufReader::new(obj_file)).unwrap();
+    // The model has no normals of its own, so smooth it
+    parsed.generate_normals(NormalWeighting::Angle, consts::FRAC_PI_3);
+
+    // Material libraries are named relative to the OBJ file
+    let mut library = MtlLibrary::default();
+    for name in parsed.material_libraries() {
+        let mtl_file = File::open(Path::new("objs").join(name)).unwrap();
+        library.extend(MtlLibrary::parse(BufReader::new(mtl_file)).unwrap());
+    }
 
     let obj = Transformed::new(
-        WavefrontObj::parse(reader).to_object(),
+        parsed.to_object_with_materials(&library),
         InvertibleMatrix::try_from(transformation::sequence(&[
             transformation::rotation_y(std::f64::consts::FRAC_PI_4),
             transformation::scaling(10.0, 10.0, 10.0),
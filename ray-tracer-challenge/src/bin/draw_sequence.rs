@@ -0,0 +1,110 @@
+use std::f64::consts;
+
+use ray_tracer_challenge::{
+    draw::color::{self, Color},
+    math::{matrix::InvertibleMatrix, point::Point3d, vector::Vec3d},
+    scene::{
+        animation::CameraPath,
+        camera::RenderOpts,
+        light::PointLight,
+        material::{Material, Surface},
+        object::{cube::Cube, plane::Plane, sphere::Sphere, transformed::Transformed},
+        pattern::checker3d::Checker3d,
+        transformation,
+        world::World,
+    },
+    util,
+};
+
+const RES_X: usize = 400;
+const RES_Y: usize = 300;
+const FRAMES: usize = 48;
+const FRAMES_PER_SECOND: f64 = 24.0;
+
+fn main() {
+    use std::time::Instant;
+    let now = Instant::now();
+
+    let world = turntable_world();
+
+    // One full revolution over the length of the sequence
+    let path = CameraPath::fixed(
+        RES_X,
+        RES_Y,
+        consts::FRAC_PI_3,
+        Point3d::new(0.0, 3.0, -8.0),
+        Point3d::new(0.0, 1.0, 0.0),
+        Vec3d::new(0.0, 1.0, 0.0),
+    )
+    .orbit(FRAMES as f64 / FRAMES_PER_SECOND);
+
+    let opts = RenderOpts {
+        anti_aliasing_samples: 2,
+        ..Default::default()
+    };
+
+    for (frame, canvas) in path
+        .render_sequence(&world, &opts, FRAMES, FRAMES_PER_SECOND)
+        .enumerate()
+    {
+        println!("Rendered frame {} of {}", frame + 1, FRAMES);
+        util::write_frame_to_file(&canvas, "output/turntable", frame);
+    }
+
+    let elapsed = now.elapsed();
+    println!("Elapsed: {:.2?}", elapsed);
+}
+
+fn turntable_world() -> World {
+    let floor = Plane {
+        material: Material {
+            surface: Surface::Pattern(Box::new(Checker3d {
+                a: color::white(),
+                b: Color::new(0.3, 0.3, 0.3),
+                transform: InvertibleMatrix::try_from(transformation::translation(0.0, -0.01, 0.0))
+                    .unwrap(),
+            })),
+            specular: 0.0,
+            reflectivity: 0.1,
+            ..Default::default()
+        },
+    };
+
+    let ball = Transformed::new(
+        Sphere {
+            material: Material {
+                surface: Surface::Color(Color::new(0.059, 0.322, 0.729)),
+                specular: 1.0,
+                reflectivity: 0.3,
+                ..Default::default()
+            },
+        },
+        InvertibleMatrix::try_from(transformation::translation(-1.5, 1.0, 0.0)).unwrap(),
+    );
+
+    let block = Transformed::new(
+        Cube {
+            material: Material {
+                surface: Surface::Color(Color::new(0.8, 0.3, 0.1)),
+                ..Default::default()
+            },
+        },
+        InvertibleMatrix::try_from(transformation::sequence(&[
+            transformation::scaling(0.75, 0.75, 0.75),
+            transformation::rotation_y(consts::FRAC_PI_6),
+            transformation::translation(1.5, 0.75, 0.5),
+        ]))
+        .unwrap(),
+    );
+
+    let light_source = PointLight {
+        position: Point3d::new(-10.0, 10.0, -10.0),
+        intensity: color::white(),
+    };
+
+    World {
+        objects: vec![Box::new(floor), Box::new(ball), Box::new(block)],
+        lights: vec![light_source],
+        ..Default::default()
+    }
+}
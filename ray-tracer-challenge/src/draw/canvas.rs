@@ -1,4 +1,4 @@
-use super::color::Color;
+use super::{color::Color, tone_map::OutputOpts};
 
 pub struct Canvas {
     width: usize,
@@ -48,6 +48,12 @@ impl Canvas {
     }
 
     pub fn ppm(&self) -> String {
+        self.ppm_with_opts(&OutputOpts::default())
+    }
+
+    /// Encodes the canvas as a PPM image, with exposure, tone mapping and
+    /// sRGB encoding applied to each pixel
+    pub fn ppm_with_opts(&self, opts: &OutputOpts) -> String {
         const MAX_COLOR_VAL: i32 = 255;
 
         fn translate(color: f64) -> i32 {
@@ -68,6 +74,7 @@ impl Canvas {
             .map(|row| {
                 row.iter()
                     .flat_map(|color| {
+                        let color = opts.apply(color);
                         let r = translate(color.r());
                         let g = translate(color.g());
                         let b = translate(color.b());
@@ -95,11 +102,26 @@ impl Canvas {
 
         s
     }
+
+    /// Encodes the canvas as a little-endian PFM (portable float map) image,
+    /// which keeps the full range of each pixel. Rows are written bottom to top,
+    /// as the format requires.
+    pub fn pfm(&self) -> Vec<u8> {
+        let mut bytes = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+        for row in self.data.chunks(self.width).rev() {
+            for color in row {
+                for v in [color.r(), color.g(), color.b()] {
+                    bytes.extend_from_slice(&(v as f32).to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::draw::color::Color;
+    use crate::draw::{color::Color, tone_map::ToneMap};
 
     use super::*;
 
@@ -125,6 +147,24 @@ mod tests {
         assert_eq!(c.at(2, 3).expect("pixel is defined"), &r);
     }
 
+    #[test]
+    fn constructing_pfm() {
+        let mut c = Canvas::new(2, 2);
+        c.write((0, 1), Color::new(1.5, -2.0, f64::INFINITY));
+
+        let pfm = c.pfm();
+        let header = "PF\n2 2\n-1.0\n";
+
+        assert!(pfm.starts_with(header.as_bytes()));
+        assert_eq!(pfm.len(), header.len() + 2 * 2 * 3 * 4);
+        // The bottom row comes first
+        let first_pixel = pfm[header.len()..header.len() + 12]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(first_pixel, vec![1.5, -2.0, f32::INFINITY]);
+    }
+
     mod ppm {
         use super::*;
 
@@ -182,6 +222,23 @@ mod tests {
             );
         }
 
+        #[test]
+        fn tone_mapping_ppm_pixel_data() {
+            let c = Canvas::new_with_color(2, 1, &Color::new(3.0, 1.0, 0.0));
+            let opts = OutputOpts {
+                exposure: -1.0,
+                tone_map: ToneMap::Reinhard,
+                srgb: false,
+            };
+
+            let ppm = c.ppm_with_opts(&opts);
+            let lines = ppm.lines();
+
+            let line4 = lines.skip(3).take(1).collect::<Vec<_>>().join("\n");
+
+            assert_eq!(line4, "153 85 0 153 85 0");
+        }
+
         #[test]
         fn ppm_files_terminated_by_newline() {
             let c = Canvas::new(5, 3);
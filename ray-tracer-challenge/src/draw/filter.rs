@@ -0,0 +1,137 @@
+/// A separable reconstruction filter, which decides how much each sample
+/// contributes to the pixels around it. A pixel's color is the weighted average
+/// of the samples within the filter's radius of its center. Distances are
+/// measured in pixels.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Filter {
+    /// Every sample counts only towards the pixel it was taken in, with equal weight
+    #[default]
+    Box,
+    /// Weight falls off linearly to zero at `radius`. A radius of 1 is typical.
+    Tent { radius: f64 },
+    /// A Gaussian with falloff `alpha`, shifted to reach zero at `radius`.
+    /// A radius of 1.5 and alpha of 2 are typical.
+    Gaussian { radius: f64, alpha: f64 },
+    /// The Mitchell-Netravali cubic, stretched to reach zero at `radius`. Can
+    /// weight samples negatively, which sharpens edges. A radius of 2 with
+    /// `b` = `c` = 1/3 is typical.
+    MitchellNetravali { radius: f64, b: f64, c: f64 },
+}
+
+impl Filter {
+    /// The distance beyond which samples have no weight
+    pub fn radius(&self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::MitchellNetravali { radius, .. } => *radius,
+        }
+    }
+
+    /// The weight of a sample offset by (dx, dy) from a pixel's center
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate(dx) * self.evaluate(dy)
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        match *self {
+            // Half-open so that a sample on the edge between pixels counts towards one only
+            Filter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent { radius } => f64::max(0.0, 1.0 - x.abs() / radius),
+            Filter::Gaussian { radius, alpha } => f64::max(
+                0.0,
+                (-alpha * x * x).exp() - (-alpha * radius * radius).exp(),
+            ),
+            Filter::MitchellNetravali { radius, b, c } => {
+                let x = 2.0 * x.abs() / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::util;
+
+    use super::*;
+
+    const MITCHELL: Filter = Filter::MitchellNetravali {
+        radius: 2.0,
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+
+    #[test]
+    fn box_filter_covers_one_pixel() {
+        assert_eq!(Filter::Box.weight(0.0, 0.0), 1.0);
+        assert_eq!(Filter::Box.weight(-0.5, 0.49), 1.0);
+        assert_eq!(Filter::Box.weight(0.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn tent_filter_falls_off_linearly() {
+        let f = Filter::Tent { radius: 2.0 };
+
+        assert_eq!(f.weight(0.0, 0.0), 1.0);
+        assert_eq!(f.weight(1.0, 0.0), 0.5);
+        assert_eq!(f.weight(1.0, -1.0), 0.25);
+        assert_eq!(f.weight(2.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn gaussian_filter_reaches_zero_at_its_radius() {
+        let f = Filter::Gaussian {
+            radius: 1.5,
+            alpha: 2.0,
+        };
+
+        assert!(f.weight(0.0, 0.0) > f.weight(0.5, 0.0));
+        assert!(util::are_equal(f.weight(1.5, 0.0), 0.0));
+        assert_eq!(f.weight(2.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn mitchell_filter_has_negative_lobes() {
+        assert!(util::are_equal(
+            MITCHELL.weight(0.0, 0.0),
+            (8.0 / 9.0) * (8.0 / 9.0)
+        ));
+        assert!(MITCHELL.weight(1.5, 0.0) < 0.0);
+        assert_eq!(MITCHELL.weight(2.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn filters_are_symmetric() {
+        for f in [
+            Filter::Tent { radius: 1.0 },
+            Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.0,
+            },
+            MITCHELL,
+        ] {
+            assert_eq!(f.weight(0.3, -0.7), f.weight(-0.3, 0.7));
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod canvas;
 pub mod color;
+pub mod filter;
+pub mod tone_map;
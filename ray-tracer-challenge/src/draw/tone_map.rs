@@ -0,0 +1,155 @@
+use super::color::Color;
+
+/// How `Canvas::ppm_with_opts` brings colors above 1.0 into range. Without
+/// one, lights and bright highlights are clamped to flat white.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ToneMap {
+    /// Clamps each channel, as `Canvas::ppm` always has
+    #[default]
+    Clamp,
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces,
+    /// Hable's filmic curve, normalized so that `white` maps to 1.0
+    Filmic {
+        white: f64,
+    },
+}
+
+impl ToneMap {
+    pub fn apply(&self, color: &Color) -> Color {
+        let map_channel = |c: f64| -> f64 {
+            let mapped = match *self {
+                ToneMap::Clamp => c,
+                ToneMap::Reinhard => c / (1.0 + c),
+                ToneMap::Aces => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+                ToneMap::Filmic { white } => hable(c) / hable(white),
+            };
+            mapped.min(1.0)
+        };
+        match self {
+            ToneMap::Clamp => color.clone(),
+            _ => Color::new(
+                map_channel(color.r().max(0.0)),
+                map_channel(color.g().max(0.0)),
+                map_channel(color.b().max(0.0)),
+            ),
+        }
+    }
+}
+
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+fn linear_to_srgb(v: f64) -> f64 {
+    if v <= 0.0031308 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Adjustments made to each pixel as a canvas is exported, leaving the
+/// canvas itself untouched
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutputOpts {
+    /// In stops, so +1 doubles every color
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    /// Encodes the mapped colors as sRGB, which image viewers expect, rather
+    /// than writing them linearly as the book does
+    pub srgb: bool,
+}
+
+impl OutputOpts {
+    pub fn apply(&self, color: &Color) -> Color {
+        let mapped = self.tone_map.apply(&(color * 2f64.powf(self.exposure)));
+        if self.srgb {
+            Color::new(
+                linear_to_srgb(mapped.r()),
+                linear_to_srgb(mapped.g()),
+                linear_to_srgb(mapped.b()),
+            )
+        } else {
+            mapped
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::draw::color::{self, test_utils::assert_colors_approx_equal};
+
+    use super::*;
+
+    const TONE_MAPS: [ToneMap; 3] = [
+        ToneMap::Reinhard,
+        ToneMap::Aces,
+        ToneMap::Filmic { white: 11.2 },
+    ];
+
+    #[test]
+    fn default_output_leaves_colors_unchanged() {
+        let c = Color::new(-0.5, 0.25, 4.0);
+
+        assert_eq!(OutputOpts::default().apply(&c), c);
+    }
+
+    #[test]
+    fn exposure_is_measured_in_stops() {
+        let opts = OutputOpts {
+            exposure: -2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            opts.apply(&Color::new(4.0, 2.0, 1.0)),
+            Color::new(1.0, 0.5, 0.25)
+        );
+    }
+
+    #[test]
+    fn tone_maps_keep_black_black() {
+        for t in TONE_MAPS {
+            assert_colors_approx_equal(&t.apply(&color::black()), &color::black());
+        }
+    }
+
+    #[test]
+    fn tone_maps_compress_bright_colors_into_range() {
+        for t in TONE_MAPS {
+            let mapped = t.apply(&Color::new(0.5, 4.0, 100.0));
+
+            assert!(mapped.r() < mapped.g() && mapped.g() < mapped.b());
+            assert!(mapped.b() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn filmic_maps_white_point_to_white() {
+        let mapped = ToneMap::Filmic { white: 11.2 }.apply(&Color::new(11.2, 11.2, 11.2));
+
+        assert_colors_approx_equal(&mapped, &color::white());
+    }
+
+    #[test]
+    fn srgb_encoding_brightens_midtones() {
+        let opts = OutputOpts {
+            srgb: true,
+            ..Default::default()
+        };
+
+        assert_colors_approx_equal(
+            &opts.apply(&Color::new(0.0, 0.5, 1.0)),
+            &Color::new(0.0, 0.735357, 1.0),
+        );
+    }
+}
@@ -1 +1,5 @@
+pub mod mtl;
+pub mod ply;
+pub mod stl;
+pub mod subdivision;
 pub mod wavefront_obj;
@@ -0,0 +1,269 @@
+use std::{collections::HashMap, io::BufRead};
+
+use crate::{
+    draw::color::Color,
+    scene::material::{Material, Surface},
+};
+
+use super::wavefront_obj::{ParseError, ParseErrorKind};
+
+/// A material from an MTL file, with only the properties the file sets
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MtlMaterial {
+    /// `Kd`
+    pub diffuse: Option<Color>,
+    /// `Ks`
+    pub specular: Option<Color>,
+    /// `Ns`
+    pub shininess: Option<f64>,
+    /// `Ni`
+    pub refractive_index: Option<f64>,
+    /// `d`, or one minus `Tr`. One is opaque.
+    pub dissolve: Option<f64>,
+    /// `Ke`
+    pub emission: Option<Color>,
+    /// The file named by `map_Kd`
+    pub diffuse_map: Option<String>,
+}
+
+impl MtlMaterial {
+    /// Maps the properties onto a Phong material, keeping the defaults for
+    /// anything unset. The diffuse color becomes the surface color, the
+    /// specular color is averaged into a single strength, and the dissolve
+    /// becomes transparency. There's no emission in the Phong model, so
+    /// emissive materials are drawn in their emission color by ambient light
+    /// alone. Diffuse maps aren't supported, since patterns can't look up
+    /// texture coordinates.
+    pub fn to_material(&self) -> Material {
+        let defaults = Material::default();
+        let average = |c: &Color| (c.r() + c.g() + c.b()) / 3.0;
+
+        let emission = self
+            .emission
+            .as_ref()
+            .filter(|e| e.r() > 0.0 || e.g() > 0.0 || e.b() > 0.0);
+        let (surface, ambient, diffuse, specular) = match (emission, &self.diffuse) {
+            (Some(e), _) => (Surface::Color(e.clone()), 1.0, 0.0, 0.0),
+            (None, Some(d)) => (
+                Surface::Color(d.clone()),
+                defaults.ambient,
+                1.0,
+                self.specular.as_ref().map_or(defaults.specular, average),
+            ),
+            (None, None) => (
+                defaults.surface,
+                defaults.ambient,
+                defaults.diffuse,
+                self.specular.as_ref().map_or(defaults.specular, average),
+            ),
+        };
+
+        Material {
+            surface,
+            ambient,
+            diffuse,
+            specular,
+            shininess: self.shininess.unwrap_or(defaults.shininess),
+            transparency: self
+                .dissolve
+                .map_or(defaults.transparency, |d| 1.0 - d.clamp(0.0, 1.0)),
+            refractive_index: self.refractive_index.unwrap_or(defaults.refractive_index),
+            ..defaults
+        }
+    }
+}
+
+/// The materials defined in one or more MTL files, by name
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MtlLibrary {
+    ignored: u32,
+    materials: HashMap<String, MtlMaterial>,
+}
+
+impl MtlLibrary {
+    /// Parses an MTL file. Properties other than those in `MtlMaterial` are
+    /// counted and skipped.
+    pub fn parse(reader: impl BufRead) -> Result<Self, ParseError> {
+        let mut library = MtlLibrary::default();
+        let mut current: Option<(String, MtlMaterial)> = None;
+
+        for (n, line) in reader.lines().enumerate() {
+            let error = |kind| ParseError { line: n + 1, kind };
+            let line = line.map_err(|e| error(ParseErrorKind::Io(e)))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (head, tail) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let tail = tail.trim();
+            if head == "newmtl" {
+                if tail.is_empty() {
+                    return Err(error(ParseErrorKind::MissingName("newmtl")));
+                }
+                if let Some((name, material)) = current.take() {
+                    library.materials.insert(name, material);
+                }
+                current = Some((String::from(tail), MtlMaterial::default()));
+                continue;
+            }
+
+            // Properties before the first `newmtl` don't belong to anything
+            let Some((_, material)) = current.as_mut() else {
+                library.ignored += 1;
+                continue;
+            };
+            match head {
+                "Kd" => material.diffuse = Some(parse_color(tail, "Kd").map_err(error)?),
+                "Ks" => material.specular = Some(parse_color(tail, "Ks").map_err(error)?),
+                "Ke" => material.emission = Some(parse_color(tail, "Ke").map_err(error)?),
+                "Ns" => material.shininess = Some(parse_number(tail, "Ns").map_err(error)?),
+                "Ni" => material.refractive_index = Some(parse_number(tail, "Ni").map_err(error)?),
+                "d" => material.dissolve = Some(parse_number(tail, "d").map_err(error)?),
+                "Tr" => material.dissolve = Some(1.0 - parse_number(tail, "Tr").map_err(error)?),
+                "map_Kd" => {
+                    // Options such as `-s 1 1 1` come before the file name
+                    let file = tail
+                        .split_whitespace()
+                        .last()
+                        .ok_or(error(ParseErrorKind::MissingName("map_Kd")))?;
+                    material.diffuse_map = Some(String::from(file));
+                }
+                _ => library.ignored += 1,
+            }
+        }
+
+        if let Some((name, material)) = current {
+            library.materials.insert(name, material);
+        }
+        Ok(library)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MtlMaterial> {
+        self.materials.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    /// Adds the materials from another library, replacing any with the same names
+    pub fn extend(&mut self, other: MtlLibrary) {
+        self.ignored += other.ignored;
+        self.materials.extend(other.materials);
+    }
+}
+
+fn parse_numbers(tail: &str) -> Result<Vec<f64>, ParseErrorKind> {
+    tail.split_whitespace()
+        .map(|s| {
+            s.parse::<f64>()
+                .map_err(|_| ParseErrorKind::InvalidNumber(String::from(s)))
+        })
+        .collect()
+}
+
+fn parse_number(tail: &str, record: &'static str) -> Result<f64, ParseErrorKind> {
+    match parse_numbers(tail)?[..] {
+        [n] => Ok(n),
+        ref ns => Err(ParseErrorKind::WrongValueCount {
+            record,
+            found: ns.len(),
+        }),
+    }
+}
+
+/// Parses an RGB color. A single value is used for all three channels.
+fn parse_color(tail: &str, record: &'static str) -> Result<Color, ParseErrorKind> {
+    match parse_numbers(tail)?[..] {
+        [v] => Ok(Color::new(v, v, v)),
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        ref ns => Err(ParseErrorKind::WrongValueCount {
+            record,
+            found: ns.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::point::Point3d;
+
+    use super::*;
+
+    const LIBRARY: &str = "
+# Three materials
+newmtl Red
+Ka 0 0 0
+Kd 0.8 0.1 0.1
+Ks 0.5
+Ns 50
+illum 2
+
+newmtl Glass
+Kd 1 1 1
+Ni 1.5
+d 0.25
+
+newmtl Lamp
+Ke 4 4 3
+map_Kd -s 2 2 1 textures/lamp.png
+";
+
+    #[test]
+    fn parsing_materials() {
+        let library = MtlLibrary::parse(LIBRARY.as_bytes()).unwrap();
+
+        let mut names = library.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["Glass", "Lamp", "Red"]);
+        assert_eq!(library.ignored, 2);
+        assert_eq!(
+            library.get("Red"),
+            Some(&MtlMaterial {
+                diffuse: Some(Color::new(0.8, 0.1, 0.1)),
+                specular: Some(Color::new(0.5, 0.5, 0.5)),
+                shininess: Some(50.0),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            library.get("Lamp").unwrap().diffuse_map.as_deref(),
+            Some("textures/lamp.png")
+        );
+    }
+
+    #[test]
+    fn malformed_properties_are_errors() {
+        let error = MtlLibrary::parse("newmtl A\nKd 1 2\n".as_bytes())
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "line 2: wrong number of values for 'Kd': 2"
+        );
+    }
+
+    #[test]
+    fn mapping_onto_phong_materials() {
+        let library = MtlLibrary::parse(LIBRARY.as_bytes()).unwrap();
+
+        let red = library.get("Red").unwrap().to_material();
+        assert_eq!(
+            red.surface.color_at(&Point3d::new(0.0, 0.0, 0.0)),
+            Color::new(0.8, 0.1, 0.1)
+        );
+        assert_eq!((red.diffuse, red.specular, red.shininess), (1.0, 0.5, 50.0));
+
+        let glass = library.get("Glass").unwrap().to_material();
+        assert_eq!((glass.transparency, glass.refractive_index), (0.75, 1.5));
+
+        let lamp = library.get("Lamp").unwrap().to_material();
+        assert_eq!(
+            lamp.surface.color_at(&Point3d::new(0.0, 0.0, 0.0)),
+            Color::new(4.0, 4.0, 3.0)
+        );
+        assert_eq!((lamp.ambient, lamp.diffuse, lamp.specular), (1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::BufRead;
+
+use mesh_formats::ply;
+pub use mesh_formats::ply::PlyError;
+
+use crate::{
+    draw::color::Color,
+    math::{point::Point3d, vector::Vec3d},
+    scene::{
+        material::{Material, Surface},
+        object::{
+            bounded::Bounded,
+            group::Group,
+            mesh::{Mesh, MeshFace},
+            triangle::Triangle,
+            Object,
+        },
+        pattern::vertex_colors::VertexColors,
+    },
+};
+
+/// A triangle mesh read from a PLY file, in ASCII or binary form
+pub struct Ply(ply::Ply);
+
+impl Ply {
+    /// Parses a PLY file, reading vertex normals and colors when every
+    /// vertex has them
+    pub fn parse(reader: impl BufRead) -> Result<Self, PlyError> {
+        ply::Ply::parse(reader).map(Ply)
+    }
+
+    /// Builds the mesh, fan-triangulating faces with more than three
+    /// vertices and dropping faces with no area. Vertex normals make the
+    /// triangles smooth, and vertex colors are blended across each triangle.
+    pub fn to_object(self) -> impl Object {
+        let Ply(ply) = self;
+        let triangles = ply
+            .triangles()
+            .map(|corners| {
+                let points = corners.map(|i| point(ply.vertices[i]));
+                let material = match &ply.colors {
+                    Some(colors) => Material {
+                        surface: Surface::Pattern(Box::new(VertexColors::new(
+                            points.clone(),
+                            corners.map(|i| {
+                                let [r, g, b] = colors[i];
+                                Color::new(r, g, b)
+                            }),
+                        ))),
+                        ..Default::default()
+                    },
+                    None => Default::default(),
+                };
+
+                match &ply.normals {
+                    Some(normals) => {
+                        let [p1, p2, p3] = points;
+                        let [n1, n2, n3] = corners.map(|i| vector(normals[i]));
+                        Triangle::smooth([(p1, n1), (p2, n2), (p3, n3)], material)
+                    }
+                    None => Triangle::flat(points, material),
+                }
+            })
+            .collect::<Vec<_>>();
+        Bounded::new(Group::new(triangles))
+    }
+
+    /// Like `to_object`, but as a `Mesh` whose triangles share the vertex
+    /// data, to save memory for big scans. Since a mesh has one material,
+    /// vertex colors are ignored.
+    pub fn to_mesh(self, material: Material) -> Mesh {
+        let Ply(ply) = self;
+        let faces = ply
+            .triangles()
+            .map(|corners| {
+                let vertices = corners.map(|i| i as u32);
+                MeshFace {
+                    vertices,
+                    normals: ply.normals.as_ref().map(|_| vertices),
+                    texture_coordinates: None,
+                }
+            })
+            .collect();
+        Mesh::new(
+            ply.vertices.into_iter().map(point).collect(),
+            ply.normals
+                .unwrap_or_default()
+                .into_iter()
+                .map(vector)
+                .collect(),
+            Vec::new(),
+            faces,
+            material,
+        )
+        .expect("PLY faces are checked when parsing")
+    }
+}
+
+fn point([x, y, z]: [f64; 3]) -> Point3d {
+    Point3d::new(x, y, z)
+}
+
+fn vector([x, y, z]: [f64; 3]) -> Vec3d {
+    Vec3d::new(x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::ray::Ray;
+
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment A unit square split in two
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3
+";
+
+    #[test]
+    fn vertex_colors_are_blended_across_faces() {
+        let object = Ply::parse(ASCII.as_bytes()).unwrap().to_object();
+
+        let color_at = |x, y| {
+            let ray = Ray::new(Point3d::new(x, y, -1.0), Vec3d::new(0.0, 0.0, 1.0));
+            object.intersect(&ray)[0].color.clone()
+        };
+        assert_eq!(color_at(0.5, 0.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(color_at(0.75, 0.25), Color::new(0.75, 0.0, 0.25));
+        assert_eq!(color_at(0.25, 0.75), Color::new(0.25, 0.0, 0.75));
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Read;
+
+use mesh_formats::stl;
+pub use mesh_formats::stl::StlError;
+
+use crate::{
+    math::point::Point3d,
+    scene::object::{bounded::Bounded, group::Group, triangle::Triangle, Object},
+};
+
+/// A triangle mesh read from an STL file, in ASCII or binary form
+pub struct Stl(stl::Stl);
+
+impl Stl {
+    /// Parses an STL file. The stored facet normals are ignored in favor of
+    /// the ones implied by the vertex order.
+    pub fn parse(reader: impl Read) -> Result<Self, StlError> {
+        stl::Stl::parse(reader).map(Stl)
+    }
+
+    /// Builds the mesh, dropping triangles with no area
+    pub fn to_object(self) -> impl Object {
+        let triangles = self
+            .0
+            .triangles()
+            .map(|facet| {
+                Triangle::flat(
+                    facet.map(|[x, y, z]| Point3d::new(x, y, z)),
+                    Default::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        Bounded::new(Group::new(triangles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{math::vector::Vec3d, scene::ray::Ray};
+
+    use super::*;
+
+    const ASCII: &str = "solid square
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    #[test]
+    fn rays_hit_either_half_of_the_square() {
+        let object = Stl::parse(ASCII.as_bytes()).unwrap().to_object();
+
+        for (x, y) in [(0.75, 0.25), (0.25, 0.75)] {
+            let ray = Ray::new(Point3d::new(x, y, -1.0), Vec3d::new(0.0, 0.0, 1.0));
+            let intersections = object.intersect(&ray);
+
+            assert_eq!(intersections.len(), 1);
+            assert_eq!(intersections[0].t(), 1.0);
+        }
+    }
+}
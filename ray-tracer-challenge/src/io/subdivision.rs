@@ -0,0 +1,4 @@
+//! Options for `WavefrontObj::parse_subdivided`. The subdivision itself is
+//! shared with the other ray tracer, in `mesh_formats::subdivision`.
+
+pub use mesh_formats::subdivision::{Scheme, Subdivision};
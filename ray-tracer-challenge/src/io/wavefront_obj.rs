@@ -1,186 +1,716 @@
-use std::{collections::HashMap, io::BufRead};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead},
+};
+
+use mesh_formats::subdivision::{Polygon, PolygonMesh};
 
 use crate::{
+    io::{mtl::MtlLibrary, subdivision::Subdivision},
     math::{point::Point3d, vector::Vec3d},
-    scene::object::{bounded::Bounded, group::Group, triangle::Triangle, Object},
+    scene::{
+        material::Material,
+        object::{
+            bounded::Bounded,
+            group::Group,
+            mesh::{Mesh, MeshFace},
+            triangle::Triangle,
+            Object,
+        },
+    },
 };
 
+/// How much each face counts towards the normals generated at its corners
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalWeighting {
+    /// By the angle between the face's edges at the corner, so that
+    /// splitting a face into more triangles doesn't change the result
+    Angle,
+    /// By the face's area, so that big faces dominate
+    Area,
+}
+
+/// A group of faces, named by a `g` or `o` record. Faces before the first
+/// of those are in the default group.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-enum ObjGroup {
+pub enum ObjGroup {
     Default,
     Named(String),
 }
 
+impl ObjGroup {
+    pub fn named(name: &str) -> Self {
+        ObjGroup::Named(String::from(name))
+    }
+}
+
 pub struct WavefrontObj {
     ignored: u32,
-    groups: HashMap<ObjGroup, Vec<Triangle>>,
+    /// The corners of each triangle, made into `Triangle`s or a `Mesh` once
+    /// the groups are taken
+    groups: HashMap<ObjGroup, Vec<[FaceVertex; 3]>>,
+    /// The `usemtl` material name for each triangle in `groups`
+    group_materials: HashMap<ObjGroup, Vec<Option<String>>>,
+    material_libraries: Vec<String>,
     vertices: Vec<Point3d>,
     normals: Vec<Vec3d>,
+    texture_coordinates: Vec<(f64, f64)>,
+}
+
+/// An OBJ file which couldn't be parsed, with the line number (counting from
+/// 1) where the problem was found
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    Io(io::Error),
+    InvalidNumber(String),
+    /// A record had the wrong number of values
+    WrongValueCount {
+        record: &'static str,
+        found: usize,
+    },
+    InvalidIndex(String),
+    /// An index referred to a vertex, normal or texture coordinate which
+    /// hasn't been defined (yet)
+    IndexOutOfRange {
+        element: &'static str,
+        index: i64,
+        count: usize,
+    },
+    /// A face had fewer than three vertices
+    TooFewVertices(usize),
+    MissingName(&'static str),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::Io(e) => write!(f, "{}", e),
+            ParseErrorKind::InvalidNumber(s) => write!(f, "invalid number '{}'", s),
+            ParseErrorKind::WrongValueCount { record, found } => {
+                write!(f, "wrong number of values for '{}': {}", record, found)
+            }
+            ParseErrorKind::InvalidIndex(s) => write!(f, "invalid index '{}'", s),
+            ParseErrorKind::IndexOutOfRange {
+                element,
+                index,
+                count,
+            } => write!(
+                f,
+                "{} index {} is out of range; {} defined so far",
+                element, index, count
+            ),
+            ParseErrorKind::TooFewVertices(n) => {
+                write!(f, "face has {} vertices, but needs at least 3", n)
+            }
+            ParseErrorKind::MissingName(record) => write!(f, "'{}' needs a name", record),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ParseErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The indices of a face's vertex into the position, texture coordinate and
+/// normal lists
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FaceVertex {
+    position: usize,
+    texture: Option<usize>,
+    normal: Option<usize>,
 }
 
 impl WavefrontObj {
-    pub fn parse(reader: impl BufRead) -> Self {
-        let lines_iter = reader.lines().map(|l| l.unwrap());
+    /// Parses the geometry of an OBJ file. Records which aren't needed for
+    /// rendering triangle meshes, such as curves and lines, are counted and
+    /// skipped. Faces with more than three vertices are triangulated, and
+    /// faces with no area are dropped.
+    pub fn parse(reader: impl BufRead) -> Result<Self, ParseError> {
+        Self::parse_with(reader, None)
+    }
+
+    /// Like `parse`, but smooths the faces with `subdivision` before they're
+    /// triangulated. The subdivided faces have no normals, so call
+    /// `generate_normals` afterwards for smooth shading.
+    pub fn parse_subdivided(
+        reader: impl BufRead,
+        subdivision: &Subdivision,
+    ) -> Result<Self, ParseError> {
+        Self::parse_with(reader, Some(subdivision))
+    }
 
+    fn parse_with(
+        reader: impl BufRead,
+        subdivision: Option<&Subdivision>,
+    ) -> Result<Self, ParseError> {
         let mut obj = WavefrontObj {
             ignored: 0,
             groups: HashMap::new(),
+            group_materials: HashMap::new(),
+            material_libraries: Vec::new(),
             vertices: Vec::new(),
             normals: Vec::new(),
+            texture_coordinates: Vec::new(),
         };
 
-        let mut current_group_name = ObjGroup::Default;
-        let mut current_group_val = Vec::<Triangle>::new();
-        for line in lines_iter {
-            let split = line.split_once(' ');
-            let status: Option<()> = split.and_then(|(head, tail)| {
-                let trimmed = tail.trim();
-                match head {
-                    "v" => {
-                        let res = parse_vertex(trimmed);
-                        res.map(|p| obj.vertices.push(p))
-                    }
-                    "f" => {
-                        let res = parse_face(trimmed, &obj.vertices, &obj.normals);
-                        res.map(|mut ts| current_group_val.append(&mut ts))
+        let mut current_group = ObjGroup::Default;
+        let mut current_material = None;
+        // Faces to subdivide, with their groups and materials
+        let mut polygons = Vec::new();
+        for (n, line) in reader.lines().enumerate() {
+            let error = |kind| ParseError { line: n + 1, kind };
+            let line = line.map_err(|e| error(ParseErrorKind::Io(e)))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (head, tail) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let tail = tail.trim();
+            match head {
+                "v" => {
+                    // Some exporters add a weight, or a vertex color
+                    let ns = parse_numbers(tail, "v", &[3, 4, 6]).map_err(error)?;
+                    obj.vertices.push(Point3d::new(ns[0], ns[1], ns[2]));
+                }
+                "vn" => {
+                    let ns = parse_numbers(tail, "vn", &[3]).map_err(error)?;
+                    obj.normals.push(Vec3d::new(ns[0], ns[1], ns[2]));
+                }
+                "vt" => {
+                    let ns = parse_numbers(tail, "vt", &[1, 2, 3]).map_err(error)?;
+                    obj.texture_coordinates
+                        .push((ns[0], ns.get(1).copied().unwrap_or(0.0)));
+                }
+                "f" => {
+                    let face = parse_face(tail, &obj).map_err(error)?;
+                    if subdivision.is_some() {
+                        polygons.push((current_group.clone(), current_material.clone(), face));
+                    } else {
+                        obj.add_face(&current_group, &current_material, &face);
                     }
-                    "g" => {
-                        let group_vals = std::mem::take(&mut current_group_val);
-                        obj.groups.insert(current_group_name.clone(), group_vals);
-                        current_group_name = ObjGroup::Named(String::from(trimmed));
-                        current_group_val.clear();
-                        Some(())
+                }
+                // Objects are treated as groups
+                "g" | "o" => {
+                    current_group = if tail.is_empty() {
+                        ObjGroup::Default
+                    } else {
+                        ObjGroup::Named(String::from(tail))
+                    };
+                }
+                "usemtl" => {
+                    if tail.is_empty() {
+                        return Err(error(ParseErrorKind::MissingName("usemtl")));
                     }
-                    "vn" => {
-                        let res = parse_normal(trimmed);
-                        res.map(|n| obj.normals.push(n))
+                    current_material = Some(String::from(tail));
+                }
+                "mtllib" => {
+                    if tail.is_empty() {
+                        return Err(error(ParseErrorKind::MissingName("mtllib")));
                     }
-                    _ => None,
+                    obj.material_libraries
+                        .extend(tail.split_whitespace().map(String::from));
+                }
+                // Smoothing groups only matter when generating normals, which
+                // files with smooth shading include anyway
+                "s" => (),
+                _ => obj.ignored += 1,
+            }
+        }
+        if let Some(subdivision) = subdivision {
+            obj.add_subdivided(polygons, subdivision);
+        }
+
+        Ok(obj)
+    }
+
+    /// Triangulates a face into a group
+    fn add_face(&mut self, group: &ObjGroup, material: &Option<String>, face: &[FaceVertex]) {
+        let mut triangles = self.triangulate(face);
+        self.group_materials
+            .entry(group.clone())
+            .or_default()
+            .extend(triangles.iter().map(|_| material.clone()));
+        self.groups
+            .entry(group.clone())
+            .or_default()
+            .append(&mut triangles);
+    }
+
+    /// Adds the faces held back by `parse_subdivided` once they've been
+    /// subdivided. Subdividing adds vertices and texture coordinates, so the
+    /// whole of both lists is passed through and replaced.
+    fn add_subdivided(
+        &mut self,
+        polygons: Vec<(ObjGroup, Option<String>, Vec<FaceVertex>)>,
+        subdivision: &Subdivision,
+    ) {
+        let mut labels = Vec::with_capacity(polygons.len());
+        let faces = polygons
+            .into_iter()
+            .enumerate()
+            .map(|(tag, (group, material, face))| {
+                labels.push((group, material));
+                Polygon {
+                    vertices: face.iter().map(|v| v.position).collect(),
+                    texture_coordinates: face.iter().map(|v| v.texture).collect(),
+                    tag,
                 }
-            });
+            })
+            .collect();
+        let mesh = PolygonMesh {
+            positions: self
+                .vertices
+                .iter()
+                .map(|p| [p.x(), p.y(), p.z()])
+                .collect(),
+            texture_coordinates: std::mem::take(&mut self.texture_coordinates),
+            faces,
+        }
+        .subdivide(subdivision);
+
+        self.vertices = mesh
+            .positions
+            .into_iter()
+            .map(|[x, y, z]| Point3d::new(x, y, z))
+            .collect();
+        self.texture_coordinates = mesh.texture_coordinates;
+        for polygon in mesh.faces {
+            let (group, material) = &labels[polygon.tag];
+            let face = polygon
+                .vertices
+                .iter()
+                .enumerate()
+                .map(|(i, &position)| FaceVertex {
+                    position,
+                    texture: polygon.texture_coordinates.as_ref().map(|ts| ts[i]),
+                    normal: None,
+                })
+                .collect::<Vec<_>>();
+            self.add_face(group, material, &face);
+        }
+    }
+
+    /// The files named by `mtllib` records, in order
+    pub fn material_libraries(&self) -> &[String] {
+        &self.material_libraries
+    }
 
-            match status {
-                Some(_) => (),
-                None => obj.ignored += 1,
+    /// Gives smooth normals to the faces which don't have any, by averaging
+    /// the normals of the faces around each vertex. Faces whose normals are
+    /// more than `crease_angle` radians apart aren't averaged together, so
+    /// that hard edges stay sharp.
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: f64) {
+        let group_names = self.groups.keys().cloned().collect::<Vec<_>>();
+        let faces = group_names
+            .iter()
+            .flat_map(|g| self.groups[g].iter().map(|f| f.map(|v| v.position)))
+            .collect::<Vec<_>>();
+        let normals = corner_normals(&self.vertices, &faces, weighting, crease_angle.cos());
+
+        // Corners which end up with the same normal share it
+        let mut indices = HashMap::new();
+        let mut normals = normals.into_iter();
+        for g in &group_names {
+            for face in self.groups.get_mut(g).unwrap() {
+                let face_normals = normals.next().unwrap();
+                if face.iter().all(|v| v.normal.is_some()) {
+                    continue;
+                }
+                for (corner, normal) in face.iter_mut().zip(face_normals) {
+                    let bits = [normal.x(), normal.y(), normal.z()].map(f64::to_bits);
+                    let index = *indices.entry(bits).or_insert_with(|| {
+                        self.normals.push(normal);
+                        self.normals.len() - 1
+                    });
+                    corner.normal = Some(index);
+                }
             }
         }
-        obj.groups.insert(current_group_name, current_group_val);
+    }
 
-        obj
+    /// The groups which have faces and haven't been taken yet
+    pub fn groups(&self) -> impl Iterator<Item = &ObjGroup> {
+        self.groups.keys()
     }
 
+    /// Removes a group, returning its faces as their own object. Each face
+    /// gets a material made by `material`, since each triangle owns its own.
+    /// Returns `None` if there's no such group, or if it was already taken.
+    pub fn take_group(
+        &mut self,
+        group: &ObjGroup,
+        material: impl Fn() -> Material,
+    ) -> Option<impl Object> {
+        self.take_triangles(group).map(|triangles| {
+            Self::build(
+                triangles
+                    .into_iter()
+                    .map(|(t, _)| t.with_material(material())),
+            )
+        })
+    }
+
+    /// Like `take_group`, but gives each face its material from `library`, as
+    /// `to_object_with_materials` does
+    pub fn take_group_with_materials(
+        &mut self,
+        group: &ObjGroup,
+        library: &MtlLibrary,
+    ) -> Option<impl Object> {
+        self.take_triangles(group).map(|triangles| {
+            Self::build(
+                triangles
+                    .into_iter()
+                    .map(|(t, name)| apply_library_material(t, name, library)),
+            )
+        })
+    }
+
+    /// Builds one object out of every group that hasn't been taken
     pub fn to_object(self) -> impl Object {
-        let all_triangles = self.groups.into_values().flatten().collect::<Vec<_>>();
+        let all_triangles = self
+            .groups
+            .values()
+            .flatten()
+            .map(|corners| self.triangle(corners))
+            .collect::<Vec<_>>();
         Bounded::new(Group::new(all_triangles))
     }
-}
 
-fn parse_vertex(tail: &str) -> Option<Point3d> {
-    let nums = tail
-        .split_whitespace()
-        .map(|s| s.parse::<f64>().ok())
-        .collect::<Option<Vec<f64>>>();
-
-    nums.and_then(|ns| {
-        if ns.len() == 3 {
-            Some(Point3d::new(ns[0], ns[1], ns[2]))
-        } else {
-            None
+    /// Builds one mesh out of every group that hasn't been taken. Its faces
+    /// share the file's vertex data instead of copying it into each
+    /// triangle, which saves a lot of memory for big models. `usemtl`
+    /// records are ignored, since a mesh has only one material.
+    pub fn to_mesh(self, material: Material) -> Mesh {
+        let faces = self
+            .groups
+            .into_values()
+            .flatten()
+            .map(|corners| MeshFace {
+                vertices: corners.map(|v| v.position as u32),
+                normals: match corners.map(|v| v.normal) {
+                    [Some(n1), Some(n2), Some(n3)] => Some([n1, n2, n3].map(|n| n as u32)),
+                    _ => None,
+                },
+                texture_coordinates: match corners.map(|v| v.texture) {
+                    [Some(t1), Some(t2), Some(t3)] => Some([t1, t2, t3].map(|t| t as u32)),
+                    _ => None,
+                },
+            })
+            .collect();
+        Mesh::new(
+            self.vertices,
+            self.normals,
+            self.texture_coordinates,
+            faces,
+            material,
+        )
+        .expect("OBJ faces are checked when parsing")
+    }
+
+    /// Like `to_object`, but gives each triangle the material named by the
+    /// `usemtl` record before its face, as described in `library`. Faces
+    /// without a material, or whose material isn't in the library, get the
+    /// default material.
+    pub fn to_object_with_materials(mut self, library: &MtlLibrary) -> impl Object {
+        let groups = std::mem::take(&mut self.groups);
+        let mut group_materials = std::mem::take(&mut self.group_materials);
+        let all_triangles = groups
+            .into_iter()
+            .flat_map(|(group, faces)| {
+                let materials = group_materials.remove(&group).unwrap_or_default();
+                faces.into_iter().zip(materials)
+            })
+            .map(|(corners, name)| apply_library_material(self.triangle(&corners), name, library))
+            .collect::<Vec<_>>();
+        Bounded::new(Group::new(all_triangles))
+    }
+
+    /// Removes a group's triangles, paired with their `usemtl` material names
+    fn take_triangles(&mut self, group: &ObjGroup) -> Option<Vec<(Triangle, Option<String>)>> {
+        let faces = self.groups.remove(group)?;
+        let materials = self.group_materials.remove(group).unwrap_or_default();
+        Some(
+            faces
+                .iter()
+                .map(|corners| self.triangle(corners))
+                .zip(materials)
+                .collect(),
+        )
+    }
+
+    fn build(triangles: impl Iterator<Item = Triangle>) -> Bounded<Group<Triangle>> {
+        Bounded::new(Group::new(triangles.collect()))
+    }
+
+    /// Splits a face into the corners of triangles, dropping any with no area
+    fn triangulate(&self, face: &[FaceVertex]) -> Vec<[FaceVertex; 3]> {
+        let positions = face
+            .iter()
+            .map(|v| &self.vertices[v.position])
+            .collect::<Vec<_>>();
+
+        ear_clip(&positions)
+            .into_iter()
+            .map(|corners| corners.map(|i| face[i]))
+            .filter(|corners| {
+                let [p1, p2, p3] = corners.map(|v| &self.vertices[v.position]);
+                (p2 - p1).cross(&(p3 - p1)).mag() > 0.0
+            })
+            .collect()
+    }
+
+    /// Makes a triangle from its corners, carrying over normals and texture
+    /// coordinates where every corner has them
+    fn triangle(&self, corners: &[FaceVertex; 3]) -> Triangle {
+        let points = corners.map(|v| self.vertices[v.position].clone());
+        let normals = corners.map(|v| v.normal.map(|n| self.normals[n].clone()));
+        let triangle = match normals {
+            [Some(n1), Some(n2), Some(n3)] => {
+                let [p1, p2, p3] = points;
+                Triangle::smooth([(p1, n1), (p2, n2), (p3, n3)], Default::default())
+            }
+            _ => Triangle::flat(points, Default::default()),
+        };
+
+        match corners.map(|v| v.texture.map(|t| self.texture_coordinates[t])) {
+            [Some(t1), Some(t2), Some(t3)] => triangle.with_texture_coordinates([t1, t2, t3]),
+            _ => triangle,
         }
-    })
+    }
 }
 
-fn parse_normal(tail: &str) -> Option<Vec3d> {
-    let nums = tail
-        .split_whitespace()
-        .map(|s| s.parse::<f64>().ok())
-        .collect::<Option<Vec<f64>>>();
-
-    nums.and_then(|ns| {
-        if ns.len() == 3 {
-            Some(Vec3d::new(ns[0], ns[1], ns[2]))
-        } else {
-            None
+/// Finds the normal at each corner of each face, averaging the weighted
+/// normals of the faces sharing that vertex whose normals are within the
+/// crease angle, given by its cosine, of the face's own
+fn corner_normals(
+    vertices: &[Point3d],
+    faces: &[[usize; 3]],
+    weighting: NormalWeighting,
+    cos_crease_angle: f64,
+) -> Vec<[Vec3d; 3]> {
+    let face_normals = faces
+        .iter()
+        .map(|f| {
+            let [p1, p2, p3] = f.map(|i| &vertices[i]);
+            // The same way round as `Triangle::flat`
+            (p3 - p1).cross(&(p2 - p1))
+        })
+        .collect::<Vec<_>>();
+    let unit_normals = face_normals.iter().map(|n| n.norm()).collect::<Vec<_>>();
+
+    let mut faces_at_vertex = vec![Vec::new(); vertices.len()];
+    for (i, f) in faces.iter().enumerate() {
+        for &v in f {
+            faces_at_vertex[v].push(i);
         }
-    })
+    }
+
+    // The weighted normal each face adds at each of its corners
+    let contributions = faces
+        .iter()
+        .zip(&face_normals)
+        .map(|(f, n)| {
+            [0, 1, 2].map(|c| match weighting {
+                NormalWeighting::Area => n.clone(),
+                NormalWeighting::Angle => {
+                    let p = &vertices[f[c]];
+                    let e1 = &vertices[f[(c + 1) % 3]] - p;
+                    let e2 = &vertices[f[(c + 2) % 3]] - p;
+                    let cos = e1.dot(&e2) / (e1.mag() * e2.mag());
+                    n.norm()
+                        .map_or(n.clone(), |n| &n * cos.clamp(-1.0, 1.0).acos())
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    faces
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            [0, 1, 2].map(|c| {
+                let sum = faces_at_vertex[f[c]]
+                    .iter()
+                    .filter(|&&j| match (&unit_normals[i], &unit_normals[j]) {
+                        (Some(n), Some(other)) => n.dot(other) >= cos_crease_angle,
+                        _ => false,
+                    })
+                    .map(|&j| {
+                        let corner = faces[j].iter().position(|&v| v == f[c]).unwrap();
+                        &contributions[j][corner]
+                    })
+                    .fold(Vec3d::new(0.0, 0.0, 0.0), |sum, n| &sum + n);
+                sum.norm()
+                    .or_else(|| unit_normals[i].clone())
+                    .unwrap_or(Vec3d::new(0.0, 0.0, 1.0))
+            })
+        })
+        .collect()
+}
+
+fn apply_library_material(
+    triangle: Triangle,
+    name: Option<String>,
+    library: &MtlLibrary,
+) -> Triangle {
+    match name.as_deref().and_then(|name| library.get(name)) {
+        Some(m) => triangle.with_material(m.to_material()),
+        None => triangle,
+    }
 }
 
-fn parse_face(
+fn parse_numbers(
     tail: &str,
-    read_vertices: &[Point3d],
-    read_normals: &[Vec3d],
-) -> Option<Vec<Triangle>> {
-    let tokens = tail.split_whitespace();
+    record: &'static str,
+    allowed_counts: &[usize],
+) -> Result<Vec<f64>, ParseErrorKind> {
+    let ns = tail
+        .split_whitespace()
+        .map(|s| {
+            s.parse::<f64>()
+                .map_err(|_| ParseErrorKind::InvalidNumber(String::from(s)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let indices: Option<Vec<(usize, Option<usize>)>> = tokens
+    if allowed_counts.contains(&ns.len()) {
+        Ok(ns)
+    } else {
+        Err(ParseErrorKind::WrongValueCount {
+            record,
+            found: ns.len(),
+        })
+    }
+}
+
+/// Parses a face's vertices, each of which is written as `v`, `v/vt`, `v//vn`
+/// or `v/vt/vn`
+fn parse_face(tail: &str, obj: &WavefrontObj) -> Result<Vec<FaceVertex>, ParseErrorKind> {
+    let face = tail
+        .split_whitespace()
         .map(|token| {
-            let indices = token.split('/').collect::<Vec<_>>();
-            let v_n_indices_unparsed = match indices.len() {
-                1 => Some((indices[0], None)),
-                2 => Some((indices[0], None)),
-                3 => Some((indices[0], Some(indices[2]))),
-                _ => None,
+            let parts = token.split('/').collect::<Vec<_>>();
+            if parts.len() > 3 {
+                return Err(ParseErrorKind::InvalidIndex(String::from(token)));
+            }
+            let optional_index = |n: usize, element, count| match parts.get(n) {
+                None | Some(&"") => Ok(None),
+                Some(part) => resolve_index(part, element, count).map(Some),
             };
 
-            v_n_indices_unparsed.and_then(|(v_unparsed, n_unparsed)| {
-                let v_parsed = v_unparsed.parse::<usize>().ok();
-                match n_unparsed {
-                    Some(val) => {
-                        let n_parsed = val.parse::<usize>().ok();
-                        n_parsed.and_then(|n| v_parsed.map(|v| (v, Some(n))))
-                    }
-                    None => v_parsed.map(|v| (v, None)),
-                }
+            Ok(FaceVertex {
+                position: resolve_index(parts[0], "vertex", obj.vertices.len())?,
+                texture: optional_index(1, "texture coordinate", obj.texture_coordinates.len())?,
+                normal: optional_index(2, "normal", obj.normals.len())?,
             })
         })
-        .collect();
-
-    let mapped_indices = indices.map(|ns| {
-        ns.iter()
-            .map(|(vi, ni)| (&read_vertices[vi - 1], ni.map(|n| &read_normals[n - 1])))
-            .collect::<Vec<_>>()
-    });
-
-    mapped_indices.and_then(|vertices| {
-        if vertices.len() >= 3 {
-            let triangulated = fan_triangulate(&vertices);
-            Some(
-                triangulated
-                    .into_iter()
-                    .map(|verts| {
-                        let points = [verts[0].0, verts[1].0, verts[2].0].map(|p| p.clone());
-                        let normals = verts[0].1.and_then(|first| {
-                            verts[1].1.and_then(|second| {
-                                verts[2]
-                                    .1
-                                    .map(|third| [first, second, third].map(|n| n.clone()))
-                            })
-                        });
-
-                        match normals {
-                            Some(ns) => {
-                                let [p1, p2, p3] = points;
-                                let [n1, n2, n3] = ns;
-                                Triangle::smooth([(p1, n1), (p2, n2), (p3, n3)], Default::default())
-                            }
-                            None => Triangle::flat(points, Default::default()),
-                        }
-                    })
-                    .collect::<Vec<_>>(),
-            )
-        } else {
-            None
-        }
-    })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if face.len() < 3 {
+        Err(ParseErrorKind::TooFewVertices(face.len()))
+    } else {
+        Ok(face)
+    }
 }
 
-fn fan_triangulate<'a>(
-    vertices: &[(&'a Point3d, Option<&'a Vec3d>)],
-) -> Vec<[(&'a Point3d, Option<&'a Vec3d>); 3]> {
-    let mut triangles = Vec::<[(&Point3d, Option<&Vec3d>); 3]>::new();
+/// Turns a 1-based index, or a negative index counting back from the last
+/// element defined, into a 0-based index
+fn resolve_index(
+    token: &str,
+    element: &'static str,
+    count: usize,
+) -> Result<usize, ParseErrorKind> {
+    let index = token
+        .parse::<i64>()
+        .map_err(|_| ParseErrorKind::InvalidIndex(String::from(token)))?;
+
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => count as i64 + i,
+        _ => -1,
+    };
 
-    for i in 2..vertices.len() {
-        triangles.push([vertices[0], vertices[i - 1], vertices[i]])
+    if (0..count as i64).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(ParseErrorKind::IndexOutOfRange {
+            element,
+            index,
+            count,
+        })
+    }
+}
+
+/// Triangulates a polygon by ear clipping, returning the corners of each
+/// triangle as indices into `points`. Unlike a fan, this handles concave
+/// polygons. Convex polygons are split into the same triangles as a fan
+/// around the first point.
+fn ear_clip(points: &[&Point3d]) -> Vec<[usize; 3]> {
+    // Work in the plane the polygon (mostly) lies in, by dropping the axis its
+    // normal is closest to. Newell's method copes with non-planar polygons.
+    let mut normal = [0.0; 3];
+    for (i, p) in points.iter().enumerate() {
+        let q = points[(i + 1) % points.len()];
+        normal[0] += (p.y() - q.y()) * (p.z() + q.z());
+        normal[1] += (p.z() - q.z()) * (p.x() + q.x());
+        normal[2] += (p.x() - q.x()) * (p.y() + q.y());
+    }
+    let dropped_axis = (0..3)
+        .max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))
+        .unwrap();
+    let flat = points
+        .iter()
+        .map(|p| match dropped_axis {
+            0 => (p.y(), p.z()),
+            1 => (p.z(), p.x()),
+            _ => (p.x(), p.y()),
+        })
+        .collect::<Vec<_>>();
+    // Positive for counter-clockwise turns in the polygon's winding
+    let orientation = normal[dropped_axis].signum();
+    let turn = |a: usize, b: usize, c: usize| {
+        let (a, b, c) = (flat[a], flat[b], flat[c]);
+        orientation * ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0))
+    };
+
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let is_ear = |i: usize| {
+            let (a, b, c) = (remaining[i - 1], remaining[i], remaining[(i + 1) % n]);
+            turn(a, b, c) > 0.0
+                && remaining.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || turn(a, b, p) < 0.0
+                        || turn(b, c, p) < 0.0
+                        || turn(c, a, p) < 0.0
+                })
+        };
+        // Fall back to clipping the first corner of degenerate polygons
+        let ear = (1..n).find(|&i| is_ear(i)).unwrap_or(1);
+        triangles.push([remaining[ear - 1], remaining[ear], remaining[(ear + 1) % n]]);
+        remaining.remove(ear);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
     }
 
     triangles
@@ -188,8 +718,24 @@ fn fan_triangulate<'a>(
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        draw::color::Color,
+        io::subdivision::Scheme,
+        scene::{material::Surface, ray::Ray},
+    };
+
     use super::*;
 
+    impl WavefrontObj {
+        /// The triangles in a group, without taking it
+        fn triangles(&self, group: &ObjGroup) -> Vec<Triangle> {
+            self.groups[group]
+                .iter()
+                .map(|c| self.triangle(c))
+                .collect()
+        }
+    }
+
     mod parse {
         use crate::math::vector::Vec3d;
 
@@ -203,7 +749,7 @@ She set out one day
 in a relative way,
 and came back the previous night.";
 
-            let parsed = WavefrontObj::parse(gibberish.as_bytes());
+            let parsed = WavefrontObj::parse(gibberish.as_bytes()).unwrap();
 
             assert_eq!(parsed.ignored, 5);
         }
@@ -217,7 +763,7 @@ v 1 0 0
 v 1 1 0
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
             assert_eq!(parsed.vertices[0], Point3d::new(-1.0, 1.0, 0.0));
             assert_eq!(parsed.vertices[1], Point3d::new(-1.0, 0.5, 0.0));
@@ -237,9 +783,9 @@ f 1 2 3
 f 1 3 4
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
-            let g = &parsed.groups.get(&ObjGroup::Default).unwrap();
+            let g = &parsed.triangles(&ObjGroup::Default);
             let t1 = &g[0];
             let t2 = &g[1];
 
@@ -279,9 +825,9 @@ v 0 2 0
 f 1 2 3 4 5
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
-            let g = &parsed.groups.get(&ObjGroup::Default).unwrap();
+            let g = &parsed.triangles(&ObjGroup::Default);
             let t1 = &g[0];
             let t2 = &g[1];
             let t3 = &g[2];
@@ -335,16 +881,10 @@ g SecondGroup
 f 1 3 4
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
-            let g1 = parsed
-                .groups
-                .get(&ObjGroup::Named(String::from("FirstGroup")))
-                .unwrap();
-            let g2 = parsed
-                .groups
-                .get(&ObjGroup::Named(String::from("SecondGroup")))
-                .unwrap();
+            let g1 = parsed.triangles(&ObjGroup::Named(String::from("FirstGroup")));
+            let g2 = parsed.triangles(&ObjGroup::Named(String::from("SecondGroup")));
             let t1 = &g1[0];
             let t2 = &g2[0];
 
@@ -380,7 +920,7 @@ vn 0.707 0 -0.707
 vn 1 2 3
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
             assert_eq!(
                 parsed.normals,
@@ -403,13 +943,17 @@ vn -1 0 0
 vn 1 0 0
 vn 0 1 0
 
+vt 0 0
+vt 1 0
+vt 0 1
+
 f 1//3 2//1 3//2
-f 1/0/3 2/102/1 3/14/2
+f 1/1/3 2/2/1 3/3/2
 ";
 
-            let parsed = WavefrontObj::parse(data.as_bytes());
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
 
-            let g = parsed.groups.get(&ObjGroup::Default).unwrap();
+            let g = parsed.triangles(&ObjGroup::Default);
             let t1 = &g[0];
             let t2 = &g[1];
 
@@ -426,6 +970,191 @@ f 1/0/3 2/102/1 3/14/2
             );
             assert_eq!(t1, t2);
         }
+
+        #[test]
+        fn faces_with_texture_coordinates() {
+            let data = "
+v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vt 0.5 1
+vt 0 0 0
+vt 1
+
+f 1/1 2/2 3/3
+f 1 2 3
+";
+
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+
+            let g = parsed.triangles(&ObjGroup::Default);
+            assert_eq!(
+                g[0].texture_coordinates(),
+                Some(&[(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)])
+            );
+            assert_eq!(g[1].texture_coordinates(), None);
+        }
+
+        #[test]
+        fn negative_indices_count_back_from_the_last_element() {
+            let data = "
+v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 -1
+f -3//-1 -2//-1 -1//-1
+v 0 5 0
+f 1 -3 -1
+";
+
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+
+            let g = parsed.triangles(&ObjGroup::Default);
+            assert_eq!(
+                g[0],
+                Triangle::flat(
+                    [
+                        Point3d::new(0.0, 1.0, 0.0),
+                        Point3d::new(-1.0, 0.0, 0.0),
+                        Point3d::new(1.0, 0.0, 0.0),
+                    ],
+                    Default::default()
+                )
+            );
+            assert_eq!(
+                g[1],
+                Triangle::flat(
+                    [
+                        Point3d::new(0.0, 1.0, 0.0),
+                        Point3d::new(-1.0, 0.0, 0.0),
+                        Point3d::new(0.0, 5.0, 0.0),
+                    ],
+                    Default::default()
+                )
+            );
+        }
+
+        #[test]
+        fn out_of_range_indices_are_errors() {
+            for face in [
+                "f 1 2 4",
+                "f 1 2 0",
+                "f 1 2 -4",
+                "f 1/1 2/1 3/1",
+                "f 1//2 2//1 3//1",
+            ] {
+                let data = format!("v 0 1 0\nv -1 0 0\nv 1 0 0\nvn 0 0 1\n{}\n", face);
+
+                let error = WavefrontObj::parse(data.as_bytes()).err().unwrap();
+
+                assert_eq!(error.line, 5);
+                assert!(
+                    matches!(error.kind, ParseErrorKind::IndexOutOfRange { .. }),
+                    "{}",
+                    face
+                );
+            }
+        }
+
+        #[test]
+        fn malformed_records_are_errors() {
+            let cases = [
+                ("v 1 2 x", "line 1: invalid number 'x'"),
+                ("v 1 2", "line 1: wrong number of values for 'v': 2"),
+                (
+                    "v 1 2 3\nf 1 1",
+                    "line 2: face has 2 vertices, but needs at least 3",
+                ),
+                ("v 1 2 3\nf 1 1 a", "line 2: invalid index 'a'"),
+                ("usemtl", "line 1: 'usemtl' needs a name"),
+            ];
+
+            for (data, message) in cases {
+                let error = WavefrontObj::parse(data.as_bytes()).err().unwrap();
+
+                assert_eq!(error.to_string(), message);
+            }
+        }
+
+        #[test]
+        fn triangulating_concave_polygons() {
+            // An arrowhead, whose notch at the fourth point would be cut off by a fan
+            let data = "
+v 0 0 0
+v 2 1 0
+v 0 2 0
+v 1 1 0
+
+f 1 2 3 4
+";
+
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+
+            let g = parsed.triangles(&ObjGroup::Default);
+            assert_eq!(g.len(), 2);
+            for t in &g {
+                assert!(t
+                    .intersect(&Ray::new(
+                        Point3d::new(0.5, 1.0, -1.0),
+                        Vec3d::new(0.0, 0.0, 1.0)
+                    ))
+                    .is_empty());
+            }
+            assert_eq!(
+                g.iter()
+                    .filter(|t| !t
+                        .intersect(&Ray::new(
+                            Point3d::new(1.5, 0.9, -1.0),
+                            Vec3d::new(0.0, 0.0, 1.0)
+                        ))
+                        .is_empty())
+                    .count(),
+                1
+            );
+        }
+
+        #[test]
+        fn materials_objects_and_smoothing_groups() {
+            let data = "
+# A comment
+mtllib first.mtl second.mtl
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+o Thing
+s 1
+f 1 2 3
+usemtl Red
+f 1 3 4
+g Other
+s off
+f 1 2 3
+g Thing
+f 1 3 4
+";
+
+            let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+
+            assert_eq!(parsed.ignored, 0);
+            assert_eq!(parsed.material_libraries(), ["first.mtl", "second.mtl"]);
+            let thing = ObjGroup::Named(String::from("Thing"));
+            assert_eq!(parsed.groups.get(&thing).unwrap().len(), 3);
+            assert_eq!(
+                parsed.group_materials.get(&thing).unwrap(),
+                &[None, Some(String::from("Red")), Some(String::from("Red"))]
+            );
+            assert_eq!(
+                parsed
+                    .groups
+                    .get(&ObjGroup::Named(String::from("Other")))
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
     }
 
     #[test]
@@ -442,7 +1171,255 @@ g SecondGroup
 f 1 3 4
 ";
 
-        let parsed = WavefrontObj::parse(data.as_bytes());
+        let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
         parsed.to_object(); // At least make sure it doesn't panic...
     }
+
+    #[test]
+    fn converting_parsed_obj_file_to_mesh() {
+        let data = "
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+vn 0 0 -1
+
+g FirstGroup
+f 1//1 2//1 3//1
+g SecondGroup
+f 1 3 4
+";
+
+        let parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+        let mesh = parsed.to_mesh(Default::default());
+
+        let mut faces = mesh.faces().to_vec();
+        faces.sort_by_key(|f| f.vertices);
+        assert_eq!(
+            faces,
+            [
+                MeshFace {
+                    vertices: [0, 1, 2],
+                    normals: Some([0, 0, 0]),
+                    texture_coordinates: None
+                },
+                MeshFace {
+                    vertices: [0, 2, 3],
+                    normals: None,
+                    texture_coordinates: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn subdividing_faces_keeps_their_groups_and_materials() {
+        let data = "
+v -1 -1 -1
+v 1 -1 -1
+v -1 1 -1
+v 1 1 -1
+v -1 -1 1
+v 1 -1 1
+v -1 1 1
+v 1 1 1
+vn 0 0 -1
+
+f 1//1 3//1 4//1 2//1
+f 5 6 8 7
+f 1 2 6 5
+f 3 7 8 4
+g Sides
+usemtl Red
+f 1 5 7 3
+f 2 4 8 6
+";
+        let subdivision = Subdivision {
+            scheme: Scheme::CatmullClark,
+            levels: 1,
+            crease_angle: None,
+        };
+
+        let parsed = WavefrontObj::parse_subdivided(data.as_bytes(), &subdivision).unwrap();
+
+        // Each quad becomes four, each split into two triangles
+        let sides = ObjGroup::named("Sides");
+        assert_eq!(parsed.groups[&ObjGroup::Default].len(), 4 * 4 * 2);
+        assert_eq!(parsed.groups[&sides].len(), 2 * 4 * 2);
+        assert!(parsed.group_materials[&ObjGroup::Default]
+            .iter()
+            .all(Option::is_none));
+        assert!(parsed.group_materials[&sides]
+            .iter()
+            .all(|m| m.as_deref() == Some("Red")));
+        assert!(parsed
+            .groups
+            .values()
+            .flatten()
+            .flatten()
+            .all(|v| v.normal.is_none()));
+        // The corners are pulled in towards the middle
+        assert_eq!(parsed.vertices.len(), 8 + 12 + 6);
+        let origin = Point3d::new(0.0, 0.0, 0.0);
+        assert!(parsed.vertices[..8]
+            .iter()
+            .all(|v| (v - &origin).mag() < 1.0));
+    }
+
+    #[test]
+    fn subdividing_drops_faces_which_repeat_a_vertex() {
+        let data = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 5 5 5
+f 1 2 3
+f 4 1 1
+";
+        let subdivision = Subdivision {
+            scheme: Scheme::Loop,
+            levels: 1,
+            crease_angle: None,
+        };
+
+        let parsed = WavefrontObj::parse_subdivided(data.as_bytes(), &subdivision).unwrap();
+
+        assert_eq!(parsed.groups[&ObjGroup::Default].len(), 4);
+    }
+
+    #[test]
+    fn applying_materials_from_a_library() {
+        let data = "
+mtllib colors.mtl
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+usemtl Red
+f 1 3 4
+";
+        let library = MtlLibrary::parse("newmtl Red\nKd 1 0 0\n".as_bytes()).unwrap();
+
+        let object = WavefrontObj::parse(data.as_bytes())
+            .unwrap()
+            .to_object_with_materials(&library);
+
+        let color_at = |x, y| {
+            let ray = Ray::new(Point3d::new(x, y, -1.0), Vec3d::new(0.0, 0.0, 1.0));
+            object.intersect(&ray)[0]
+                .object()
+                .material()
+                .surface
+                .color_at(&Point3d::new(x, y, 0.0))
+        };
+        assert_eq!(color_at(-0.5, 0.25), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(color_at(0.5, 0.75), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn taking_groups_as_their_own_objects() {
+        let data = "
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+g Body
+f 1 2 3
+g Wheels
+f 1 3 4
+";
+        let mut parsed = WavefrontObj::parse(data.as_bytes()).unwrap();
+
+        let mut groups = parsed.groups().cloned().collect::<Vec<_>>();
+        groups.sort_by_key(|g| format!("{:?}", g));
+        assert_eq!(groups, [ObjGroup::named("Body"), ObjGroup::named("Wheels")]);
+
+        let wheels = parsed
+            .take_group(&ObjGroup::named("Wheels"), || Material {
+                surface: Surface::Color(Color::new(0.0, 0.0, 1.0)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(parsed
+            .take_group(&ObjGroup::named("Wheels"), Material::default)
+            .is_none());
+        assert!(parsed
+            .take_group(&ObjGroup::Default, Material::default)
+            .is_none());
+
+        let body = parsed.to_object();
+        let ray = |x, y| Ray::new(Point3d::new(x, y, -1.0), Vec3d::new(0.0, 0.0, 1.0));
+        assert!(body.intersect(&ray(0.5, 0.75)).is_empty());
+        assert_eq!(body.intersect(&ray(-0.5, 0.25)).len(), 1);
+        assert_eq!(
+            wheels.intersect(&ray(0.5, 0.75))[0]
+                .object()
+                .material()
+                .surface
+                .color_at(&Point3d::new(0.5, 0.75, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    mod generate_normals {
+        use crate::math::vector;
+
+        use super::*;
+
+        /// Two faces folded 90 degrees along the X axis, the second twice the
+        /// area of the first, plus a separate face with its own normals
+        const FOLD: &str = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 2
+v 1 1 0
+v 2 1 0
+v 1 2 0
+vn 1 0 0
+
+f 1 2 3
+f 1 4 2
+f 5//1 6//1 7//1
+";
+
+        fn generated_normals(weighting: NormalWeighting, crease_angle: f64) -> Vec<[Vec3d; 3]> {
+            let mut parsed = WavefrontObj::parse(FOLD.as_bytes()).unwrap();
+            parsed.generate_normals(weighting, crease_angle.to_radians());
+            parsed.groups[&ObjGroup::Default]
+                .iter()
+                .map(|f| f.map(|v| parsed.normals[v.normal.unwrap()].clone()))
+                .collect()
+        }
+
+        #[test]
+        fn edges_sharper_than_the_crease_angle_stay_sharp() {
+            let normals = generated_normals(NormalWeighting::Angle, 80.0);
+
+            assert_eq!(normals[0], [0, 1, 2].map(|_| Vec3d::new(0.0, 0.0, -1.0)));
+            assert_eq!(normals[1], [0, 1, 2].map(|_| Vec3d::new(0.0, -1.0, 0.0)));
+            assert_eq!(normals[2], [0, 1, 2].map(|_| Vec3d::new(1.0, 0.0, 0.0)));
+        }
+
+        #[test]
+        fn normals_are_weighted_by_angle_or_area() {
+            let by_angle = generated_normals(NormalWeighting::Angle, 100.0);
+            let by_area = generated_normals(NormalWeighting::Area, 100.0);
+
+            // Both faces have a right angle at the first vertex, but the
+            // second face has twice the area
+            vector::test_utils::assert_vec_approx_equals(
+                &by_angle[0][0],
+                &Vec3d::new(0.0, -1.0, -1.0).norm().unwrap(),
+            );
+            vector::test_utils::assert_vec_approx_equals(
+                &by_area[1][0],
+                &Vec3d::new(0.0, -2.0, -1.0).norm().unwrap(),
+            );
+            assert_eq!(by_angle[2], by_area[2]);
+        }
+    }
 }
@@ -4,6 +4,7 @@ pub mod draw;
 pub mod io;
 pub mod math;
 pub mod scene;
+pub mod stats;
 pub mod util;
 
 #[global_allocator]
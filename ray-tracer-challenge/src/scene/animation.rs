@@ -0,0 +1,311 @@
+use std::f64::consts;
+
+use crate::{
+    draw::canvas::Canvas,
+    math::{matrix::InvertibleMatrix, point::Point3d, vector::Vec3d},
+};
+
+use super::{
+    camera::{Camera, RenderOpts},
+    transformation,
+    world::World,
+};
+
+/// Spacing of the keyframes generated for an orbiting camera, in degrees
+const ORBIT_STEP_DEGREES: f64 = 5.0;
+
+/// Values which can be linearly interpolated. `t` is expected to be in [0, 1],
+/// where 0 gives `self` and 1 gives `other`.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + t * (other - self)
+    }
+}
+
+impl Lerp for Point3d {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + &(&(other - self) * t)
+    }
+}
+
+impl Lerp for Vec3d {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + &(&(other - self) * t)
+    }
+}
+
+/// A value which changes over time, linearly interpolated between keyframes.
+/// Before the first keyframe and after the last, the value is held constant.
+#[derive(Debug, Clone)]
+pub struct Keyframes<T> {
+    /// (time, value) pairs sorted by time
+    frames: Vec<(f64, T)>,
+}
+
+impl<T: Lerp + Clone> Keyframes<T> {
+    /// Creates an animation from (time, value) pairs. Panics if there are no keyframes.
+    pub fn new(mut frames: Vec<(f64, T)>) -> Self {
+        assert!(!frames.is_empty(), "Cannot animate without keyframes");
+        frames.sort_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+        Keyframes { frames }
+    }
+
+    /// Creates an animation that holds a single value for all time
+    pub fn constant(value: T) -> Self {
+        Keyframes::new(vec![(0.0, value)])
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let next = self.frames.partition_point(|(t, _)| *t <= time);
+        if next == 0 {
+            self.frames[0].1.clone()
+        } else if next == self.frames.len() {
+            self.frames[next - 1].1.clone()
+        } else {
+            let (t0, v0) = &self.frames[next - 1];
+            let (t1, v1) = &self.frames[next];
+            v0.lerp(v1, (time - t0) / (t1 - t0))
+        }
+    }
+
+    pub fn frames(&self) -> &[(f64, T)] {
+        &self.frames
+    }
+}
+
+/// A camera moving along keyframed paths, described by the inputs to
+/// `transformation::view_transform`, for rendering image sequences
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    pub hsize: usize,
+    pub vsize: usize,
+    pub from: Keyframes<Point3d>,
+    pub to: Keyframes<Point3d>,
+    pub up: Keyframes<Vec3d>,
+    /// Field of view in radians
+    pub fov: Keyframes<f64>,
+}
+
+impl CameraPath {
+    /// A camera held still
+    pub fn fixed(
+        hsize: usize,
+        vsize: usize,
+        fov: f64,
+        from: Point3d,
+        to: Point3d,
+        up: Vec3d,
+    ) -> Self {
+        CameraPath {
+            hsize,
+            vsize,
+            from: Keyframes::constant(from),
+            to: Keyframes::constant(to),
+            up: Keyframes::constant(up),
+            fov: Keyframes::constant(fov),
+        }
+    }
+
+    /// Replaces the path of `from` with one circling the initial `to` point
+    /// once over `duration`, starting from the initial `from`. The camera turns
+    /// counter-clockwise about the initial `up`, as seen from above. Useful for
+    /// turntables.
+    pub fn orbit(self, duration: f64) -> Self {
+        let center = self.to.at(0.0);
+        let offset = &self.from.at(0.0) - &center;
+        let axis = self.up.at(0.0).norm().expect("up should not be zero");
+
+        let steps = (360.0 / ORBIT_STEP_DEGREES) as usize;
+        let frames = (0..=steps)
+            .map(|n| {
+                let fraction = n as f64 / steps as f64;
+                let (sin, cos) = (consts::TAU * fraction).sin_cos();
+
+                // Rodrigues' rotation formula
+                let rotated = &(&(&offset * cos) + &(&axis.cross(&offset) * sin))
+                    + &(&axis * (axis.dot(&offset) * (1.0 - cos)));
+                (duration * fraction, &center + &rotated)
+            })
+            .collect();
+
+        CameraPath {
+            from: Keyframes::new(frames),
+            ..self
+        }
+    }
+
+    pub fn camera_at(&self, time: f64) -> Camera {
+        let transform = transformation::view_transform(
+            &self.from.at(time),
+            &self.to.at(time),
+            &self.up.at(time),
+        );
+        Camera::new(
+            self.hsize,
+            self.vsize,
+            self.fov.at(time),
+            InvertibleMatrix::try_from(transform).unwrap(),
+        )
+    }
+
+    /// Lazily renders `frames` frames, with frame N showing the camera at time
+    /// `N / frames_per_second`
+    pub fn render_sequence<'a>(
+        &'a self,
+        world: &'a World,
+        opts: &'a RenderOpts,
+        frames: usize,
+        frames_per_second: f64,
+    ) -> impl Iterator<Item = Canvas> + 'a {
+        (0..frames).map(move |frame| {
+            self.camera_at(frame as f64 / frames_per_second)
+                .render(world, opts)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::util::test_utils::are_within_tolerance;
+
+    use super::*;
+
+    fn test_keyframes() -> Keyframes<f64> {
+        Keyframes::new(vec![(1.0, 10.0), (0.0, 0.0), (2.0, 0.0)])
+    }
+
+    mod keyframes {
+        use super::*;
+
+        #[test]
+        fn keyframes_are_sorted_by_time() {
+            let times = test_keyframes()
+                .frames()
+                .iter()
+                .map(|(t, _)| *t)
+                .collect::<Vec<_>>();
+
+            assert_eq!(times, vec![0.0, 1.0, 2.0]);
+        }
+
+        #[test]
+        fn value_between_keyframes_is_interpolated() {
+            let k = test_keyframes();
+
+            assert_eq!(k.at(1.0), 10.0);
+            assert_eq!(k.at(0.25), 2.5);
+            assert_eq!(k.at(1.5), 5.0);
+        }
+
+        #[test]
+        fn value_is_held_outside_keyframes() {
+            let k = test_keyframes();
+
+            assert_eq!(k.at(-1.0), 0.0);
+            assert_eq!(k.at(5.0), 0.0);
+        }
+
+        #[test]
+        fn interpolating_points() {
+            let k = Keyframes::new(vec![
+                (0.0, Point3d::new(0.0, 0.0, 0.0)),
+                (1.0, Point3d::new(2.0, -4.0, 8.0)),
+            ]);
+
+            assert_eq!(k.at(0.5), Point3d::new(1.0, -2.0, 4.0));
+        }
+    }
+
+    mod camera_path {
+        use super::*;
+
+        #[test]
+        fn camera_at_a_point_in_time() {
+            let path = CameraPath {
+                from: Keyframes::new(vec![
+                    (0.0, Point3d::new(0.0, 0.0, 0.0)),
+                    (2.0, Point3d::new(0.0, 0.0, 8.0)),
+                ]),
+                fov: Keyframes::new(vec![(0.0, 1.0), (2.0, 2.0)]),
+                ..CameraPath::fixed(
+                    11,
+                    11,
+                    1.0,
+                    Point3d::new(0.0, 0.0, 0.0),
+                    Point3d::new(0.0, 0.0, -1.0),
+                    Vec3d::new(0.0, 1.0, 0.0),
+                )
+            };
+            let c = path.camera_at(1.0);
+
+            assert_eq!(c.fov, 1.5);
+            assert_eq!(
+                c.transform,
+                InvertibleMatrix::try_from(transformation::translation(0.0, 0.0, -4.0)).unwrap()
+            );
+        }
+
+        #[test]
+        fn orbiting_camera_circles_the_target() {
+            let path = CameraPath::fixed(
+                11,
+                11,
+                1.0,
+                Point3d::new(0.0, 1.0, 5.0),
+                Point3d::new(0.0, 1.0, 0.0),
+                Vec3d::new(0.0, 1.0, 0.0),
+            )
+            .orbit(4.0);
+
+            let quarter_turn = path.from.at(1.0);
+            assert!(are_within_tolerance(quarter_turn.x(), 5.0, 1e-9));
+            assert!(are_within_tolerance(quarter_turn.y(), 1.0, 1e-9));
+            assert!(are_within_tolerance(quarter_turn.z(), 0.0, 1e-9));
+
+            let full_turn = path.from.at(4.0);
+            assert!(are_within_tolerance(full_turn.x(), 0.0, 1e-9));
+            assert!(are_within_tolerance(full_turn.z(), 5.0, 1e-9));
+        }
+
+        #[test]
+        fn orbiting_camera_turns_about_its_up_vector() {
+            let path = CameraPath::fixed(
+                11,
+                11,
+                1.0,
+                Point3d::new(0.0, 0.0, 5.0),
+                Point3d::new(0.0, 0.0, 0.0),
+                Vec3d::new(1.0, 0.0, 0.0),
+            )
+            .orbit(4.0);
+
+            let quarter_turn = path.from.at(1.0);
+            assert!(are_within_tolerance(quarter_turn.x(), 0.0, 1e-9));
+            assert!(are_within_tolerance(quarter_turn.y(), -5.0, 1e-9));
+            assert!(are_within_tolerance(quarter_turn.z(), 0.0, 1e-9));
+        }
+
+        #[test]
+        fn rendering_a_sequence() {
+            let world = World::default();
+            let path = CameraPath::fixed(
+                4,
+                3,
+                1.0,
+                Point3d::new(0.0, 0.0, 0.0),
+                Point3d::new(0.0, 0.0, -1.0),
+                Vec3d::new(0.0, 1.0, 0.0),
+            );
+            let frames = path
+                .render_sequence(&world, &RenderOpts::default(), 3, 24.0)
+                .collect::<Vec<_>>();
+
+            assert_eq!(frames.len(), 3);
+            assert!(frames.iter().all(|c| c.width() == 4 && c.height() == 3));
+        }
+    }
+}
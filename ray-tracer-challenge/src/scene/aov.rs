@@ -0,0 +1,221 @@
+use std::f64::consts;
+
+use crate::{
+    draw::{
+        canvas::Canvas,
+        color::{self, Color},
+    },
+    math::vector::Vec3d,
+};
+
+/// An arbitrary output variable: a pass describing the first surface seen
+/// through each pixel, rendered alongside the color for compositing and debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    ObjectId,
+    MaterialId,
+    Motion,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Motion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object-id",
+            Aov::MaterialId => "material-id",
+            Aov::Motion => "motion",
+        }
+    }
+}
+
+/// The AOV passes for a render, with one value per pixel in row-major order.
+/// Pixels where nothing was hit have an infinite depth and zeros in every
+/// other pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovBuffers {
+    pub width: usize,
+    pub height: usize,
+    /// Distance from the camera along its view direction, in world units
+    pub depth: Vec<f64>,
+    /// Outward surface normal, in world space
+    pub normal: Vec<Vec3d>,
+    /// Color of the surface, before lighting
+    pub albedo: Vec<Color>,
+    /// One more than the index of the hit object in `World::objects`
+    pub object_id: Vec<u32>,
+    /// `Material::id` of the hit object
+    pub material_id: Vec<u32>,
+    /// How far the surface has moved across the image since the previous
+    /// camera position, in pixels
+    pub motion: Vec<(f64, f64)>,
+}
+
+impl AovBuffers {
+    pub(super) fn new(width: usize, height: usize) -> Self {
+        let size = width * height;
+        AovBuffers {
+            width,
+            height,
+            depth: vec![f64::INFINITY; size],
+            normal: vec![Vec3d::new(0.0, 0.0, 0.0); size],
+            albedo: vec![color::black(); size],
+            object_id: vec![0; size],
+            material_id: vec![0; size],
+            motion: vec![(0.0, 0.0); size],
+        }
+    }
+
+    /// Returns the raw values of a pass as a canvas, for writing to a float
+    /// image. Scalar passes are copied into every channel, and motion vectors
+    /// are stored in red and green.
+    pub fn image(&self, aov: Aov) -> Canvas {
+        self.canvas(|i| match aov {
+            Aov::Depth => gray(self.depth[i]),
+            Aov::Normal => {
+                let n = &self.normal[i];
+                Color::new(n.x(), n.y(), n.z())
+            }
+            Aov::Albedo => self.albedo[i].clone(),
+            Aov::ObjectId => gray(self.object_id[i] as f64),
+            Aov::MaterialId => gray(self.material_id[i] as f64),
+            Aov::Motion => Color::new(self.motion[i].0, self.motion[i].1, 0.0),
+        })
+    }
+
+    /// Returns a pass mapped into [0, 1] for viewing. Depth is shown brightest
+    /// nearest the camera, normals are mapped from [-1, 1], each ID gets its
+    /// own color, and motion is shown relative to gray, scaled by the largest
+    /// motion in the image.
+    pub fn preview(&self, aov: Aov) -> Canvas {
+        match aov {
+            Aov::Depth => {
+                let max_depth = self
+                    .depth
+                    .iter()
+                    .copied()
+                    .filter(|d| d.is_finite())
+                    .fold(0.0, f64::max);
+                self.canvas(|i| {
+                    let d = self.depth[i];
+                    if d.is_finite() && max_depth > 0.0 {
+                        gray(1.0 - d / max_depth)
+                    } else {
+                        color::black()
+                    }
+                })
+            }
+            Aov::Normal => self.canvas(|i| {
+                let n = &self.normal[i];
+                if self.object_id[i] == 0 {
+                    color::black()
+                } else {
+                    Color::new(0.5 + n.x() / 2.0, 0.5 + n.y() / 2.0, 0.5 + n.z() / 2.0)
+                }
+            }),
+            Aov::Albedo => self.image(aov),
+            Aov::ObjectId => self.canvas(|i| id_color(self.object_id[i])),
+            Aov::MaterialId => self.canvas(|i| id_color(self.material_id[i])),
+            Aov::Motion => {
+                let max_motion = self
+                    .motion
+                    .iter()
+                    .map(|(x, y)| x.abs().max(y.abs()))
+                    .fold(0.0, f64::max);
+                let scale = if max_motion > 0.0 {
+                    0.5 / max_motion
+                } else {
+                    0.0
+                };
+                self.canvas(|i| {
+                    let (x, y) = self.motion[i];
+                    Color::new(0.5 + x * scale, 0.5 + y * scale, 0.5)
+                })
+            }
+        }
+    }
+
+    fn canvas(&self, color_at: impl Fn(usize) -> Color) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.write((x, y), color_at(y * self.width + x));
+            }
+        }
+        canvas
+    }
+}
+
+fn gray(v: f64) -> Color {
+    Color::new(v, v, v)
+}
+
+/// Spreads IDs around the color wheel by steps of the golden angle, so that
+/// neighboring IDs get clearly different colors. Zero is black.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return color::black();
+    }
+    let hue = (id as f64 * 0.618_033_988_749_895).fract() * consts::TAU;
+    let channel = |offset: f64| 0.5 + 0.5 * f64::cos(hue + offset * consts::TAU);
+    Color::new(channel(0.0), channel(1.0 / 3.0), channel(2.0 / 3.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::draw::color::test_utils::assert_colors_approx_equal;
+
+    use super::*;
+
+    fn buffers() -> AovBuffers {
+        let mut aovs = AovBuffers::new(3, 1);
+        aovs.depth = vec![2.0, 4.0, f64::INFINITY];
+        aovs.object_id = vec![1, 2, 0];
+        aovs.motion = vec![(2.0, -1.0), (0.0, 0.0), (0.0, 0.0)];
+        aovs
+    }
+
+    #[test]
+    fn depth_preview_is_brightest_nearest_the_camera() {
+        let preview = buffers().preview(Aov::Depth);
+
+        assert_colors_approx_equal(preview.at(0, 0).unwrap(), &gray(0.5));
+        assert_colors_approx_equal(preview.at(1, 0).unwrap(), &color::black());
+        assert_colors_approx_equal(preview.at(2, 0).unwrap(), &color::black());
+    }
+
+    #[test]
+    fn ids_get_distinct_colors() {
+        let preview = buffers().preview(Aov::ObjectId);
+
+        assert_ne!(preview.at(0, 0), preview.at(1, 0));
+        assert_eq!(preview.at(2, 0), Some(&color::black()));
+    }
+
+    #[test]
+    fn motion_preview_is_scaled_by_the_largest_motion() {
+        let aovs = buffers();
+
+        assert_colors_approx_equal(
+            aovs.preview(Aov::Motion).at(0, 0).unwrap(),
+            &Color::new(1.0, 0.25, 0.5),
+        );
+        assert_colors_approx_equal(
+            aovs.image(Aov::Motion).at(0, 0).unwrap(),
+            &Color::new(2.0, -1.0, 0.0),
+        );
+    }
+}
@@ -1,32 +1,127 @@
+use std::f64::consts;
+
 use crate::{
-    draw::canvas::Canvas,
-    math::{matrix::InvertibleMatrix, point::Point3d},
+    draw::{
+        canvas::Canvas,
+        color::{self, Color},
+        filter::Filter,
+    },
+    math::{
+        matrix::{InvertibleMatrix, SquareMatrix},
+        point::Point3d,
+        vector::Vec3d,
+    },
 };
 
-use super::{ray::Ray, world::World};
+use super::{
+    aov::AovBuffers,
+    debug::PixelTrace,
+    passes::RenderPasses,
+    progress::{CancellationToken, Progress, ProgressObserver, Tracker},
+    ray::Ray,
+    world::World,
+};
 
 use rayon::prelude::*;
 
+/// The angle between successive points of a Vogel spiral, in radians
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,
     pub fov: f64,
     pub transform: InvertibleMatrix<4>,
+    pub projection: Projection,
+    /// Diameter of the lens, in world units. Zero (the default) gives a pinhole
+    /// camera with everything in focus. Only used by the perspective projection.
+    pub aperture: f64,
+    /// Distance from the camera to the plane in perfect focus
+    pub focal_distance: f64,
     pixel_size: f64,
     half_width: f64,
     half_height: f64,
 }
 
+/// How rays are cast through the canvas
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    /// Parallel rays cast from the camera's XY plane, covering a view `height`
+    /// world units tall. The field of view is ignored.
+    Orthographic { height: f64 },
+    /// Equidistant fisheye. The angle between a ray and the view direction is
+    /// proportional to its pixel's distance from the canvas center, with `vfov`
+    /// degrees spanning the canvas height, so 180 gives a hemisphere across
+    /// it. The field of view is ignored.
+    Fisheye { vfov: f64 },
+    /// 360° panorama, with longitude across the canvas and latitude down it.
+    /// The canvas should be twice as wide as it is tall. The field of view is ignored.
+    Equirectangular,
+}
+
+/// How sample positions are chosen within each pixel. Random choices are made
+/// by hashing the pixel's position, so renders are repeatable.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Sampler {
+    /// `anti_aliasing_samples` × `anti_aliasing_samples` samples at the centers of a regular grid
+    #[default]
+    Grid,
+    /// One sample placed randomly in each cell of the grid
+    Jittered,
+    /// The first `anti_aliasing_samples`² points of the Halton sequence (bases 2
+    /// and 3), shifted randomly for each pixel
+    Halton,
+    /// Samples each pixel's corners, which are shared with its neighbours, and
+    /// recursively splits the pixel into quadrants while a quadrant's corners
+    /// differ by more than `threshold` in any color channel, up to `max_depth`
+    /// times. `anti_aliasing_samples` is ignored.
+    Adaptive { threshold: f64, max_depth: usize },
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RenderOpts {
     pub anti_aliasing_samples: usize,
+    pub sampler: Sampler,
+    /// How samples are weighted into the pixels around them. Ignored by the
+    /// adaptive sampler, which always averages a pixel's own samples.
+    pub filter: Filter,
+    /// Only render the pixels inside this region, leaving the rest of the canvas black
+    pub region: Option<Region>,
 }
 
 impl Default for RenderOpts {
     fn default() -> Self {
         Self {
             anti_aliasing_samples: 1,
+            sampler: Sampler::Grid,
+            filter: Filter::Box,
+            region: None,
+        }
+    }
+}
+
+/// A rectangle of pixels, with (x, y) being the top-left corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    /// Returns the part of this region which lies inside a canvas of the given size
+    pub fn clamp_to(&self, hsize: usize, vsize: usize) -> Region {
+        let x = self.x.min(hsize);
+        let y = self.y.min(vsize);
+        Region {
+            x,
+            y,
+            width: self.width.min(hsize - x),
+            height: self.height.min(vsize - y),
         }
     }
 }
@@ -50,6 +145,9 @@ impl Camera {
             vsize,
             fov,
             transform,
+            projection: Projection::Perspective,
+            aperture: 0.0,
+            focal_distance: 1.0,
             pixel_size,
             half_width,
             half_height,
@@ -60,61 +158,184 @@ impl Camera {
         Camera::new(hsize, vsize, fov, InvertibleMatrix::identity())
     }
 
-    fn rays_for_pixel(&self, px: usize, py: usize, samples: usize) -> impl Iterator<Item = Ray> {
-        let pixel_size = self.pixel_size;
-        let half_width = self.half_width;
-        let half_height = self.half_height;
+    fn rays_for_pixel(
+        &self,
+        px: usize,
+        py: usize,
+        samples: usize,
+        sampler: &Sampler,
+    ) -> impl Iterator<Item = Ray> {
         let inverse = self.transform.inverse().clone();
+        // Rotating the lens pattern differently for each pixel turns the blur's
+        // banding into noise
+        let lens_rotation = hash(&[px as u64, py as u64]) * consts::TAU;
 
-        let mut rays = Vec::with_capacity(samples);
+        let sample_count = samples * samples;
+        let positions = sample_positions(px, py, samples, sampler);
 
-        for nx in 0..samples {
-            for ny in 0..samples {
-                // offset from the edge of the canvas to the pixel's center
-                let sample_offset = 1.0 / samples as f64;
-                let xoffset = (px as f64 + sample_offset * (nx as f64 + 0.5)) * pixel_size;
-                let yoffset = (py as f64 + sample_offset * (ny as f64 + 0.5)) * pixel_size;
+        let mut rays = Vec::with_capacity(sample_count);
+        for (index, (x, y)) in positions.into_iter().enumerate() {
+            // Each sample passes through a different point on the lens
+            let lens = (
+                (index as f64 + 0.5) / sample_count as f64,
+                index as f64 * GOLDEN_ANGLE + lens_rotation,
+            );
 
-                // untransformed coordinates of the pixel in world space
-                let world_x = half_width - xoffset;
-                let world_y = half_height - yoffset;
+            rays.push(self.ray_through(x, y, lens, &inverse))
+        }
 
-                let pixel = &inverse * &Point3d::new(world_x, world_y, -1.0);
-                let origin = &inverse * &Point3d::new(0.0, 0.0, 0.0);
-                let direction = (&pixel - &origin).norm().unwrap();
+        rays.into_iter()
+    }
 
-                rays.push(Ray { origin, direction })
-            }
+    /// Casts a ray through a point on the canvas, in pixels from the top-left
+    /// corner. `lens` chooses the point on the lens the ray passes through, as
+    /// described by `lens_sample`.
+    fn ray_through(&self, x: f64, y: f64, lens: (f64, f64), inverse: &SquareMatrix<4>) -> Ray {
+        if self.projection != Projection::Perspective {
+            return self.project(x, y, inverse);
         }
 
-        rays.into_iter()
+        // offset from the edge of the canvas to the sample
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_size;
+
+        // untransformed coordinates of the pixel in world space
+        let world_x = self.half_width - xoffset;
+        let world_y = self.half_height - yoffset;
+
+        if self.aperture > 0.0 {
+            let (lens_x, lens_y) = self.lens_sample(lens.0, lens.1);
+            let d = self.focal_distance;
+
+            let focus = inverse * &Point3d::new(world_x * d, world_y * d, -d);
+            let origin = inverse * &Point3d::new(lens_x, lens_y, 0.0);
+            let direction = (&focus - &origin).norm().unwrap();
+
+            Ray { origin, direction }
+        } else {
+            let pixel = inverse * &Point3d::new(world_x, world_y, -1.0);
+            let origin = inverse * &Point3d::new(0.0, 0.0, 0.0);
+            let direction = (&pixel - &origin).norm().unwrap();
+
+            Ray { origin, direction }
+        }
     }
 
-    pub fn render(&self, world: &World, opts: &RenderOpts) -> Canvas {
-        let mut image = Canvas::new(self.hsize, self.vsize);
+    /// Returns a point on the lens in camera space. `area` in [0, 1) is the
+    /// fraction of the lens's area lying closer to its center than the point,
+    /// and `angle` is in radians. Stepping `angle` by `GOLDEN_ANGLE` for evenly
+    /// spaced `area`s spreads points evenly over the lens, in a Vogel spiral.
+    fn lens_sample(&self, area: f64, angle: f64) -> (f64, f64) {
+        let radius = self.aperture / 2.0 * f64::sqrt(area);
+        let (sin, cos) = f64::sin_cos(angle);
+        (radius * cos, radius * sin)
+    }
 
-        let indices = (0..self.hsize)
-            .into_par_iter()
-            .flat_map_iter(|x| (0..self.vsize).map(move |y| (x, y)));
+    /// Like `ray_through`, for projections other than perspective
+    fn project(&self, x: f64, y: f64, inverse: &SquareMatrix<4>) -> Ray {
+        let hsize = self.hsize as f64;
+        let vsize = self.vsize as f64;
 
-        let colors = indices
-            .map(|(x, y)| {
+        // offset from the canvas center, with +x to the left as in the perspective projection
+        let dx = hsize / 2.0 - x;
+        let dy = vsize / 2.0 - y;
+
+        let (origin, direction) = match self.projection {
+            Projection::Orthographic { height } => {
+                let pixel_size = height / vsize;
                 (
-                    (x, y),
-                    self.rays_for_pixel(x, y, opts.anti_aliasing_samples),
+                    Point3d::new(dx * pixel_size, dy * pixel_size, 0.0),
+                    Vec3d::new(0.0, 0.0, -1.0),
                 )
-            })
-            .map(|(p, rays)| {
+            }
+            Projection::Fisheye { vfov } => {
+                let r = f64::sqrt(dx * dx + dy * dy);
+                let theta = r * vfov.to_radians() / vsize;
+                let (sin_theta, cos_theta) = f64::sin_cos(theta);
+                let direction = if r > 0.0 {
+                    Vec3d::new(sin_theta * dx / r, sin_theta * dy / r, -cos_theta)
+                } else {
+                    Vec3d::new(0.0, 0.0, -1.0)
+                };
+                (Point3d::new(0.0, 0.0, 0.0), direction)
+            }
+            Projection::Equirectangular => {
+                let longitude = (x / hsize - 0.5) * consts::TAU;
+                let latitude = (0.5 - y / vsize) * consts::PI;
+                let (sin_lon, cos_lon) = f64::sin_cos(longitude);
+                let (sin_lat, cos_lat) = f64::sin_cos(latitude);
                 (
-                    p,
-                    &(rays
+                    Point3d::new(0.0, 0.0, 0.0),
+                    Vec3d::new(-cos_lat * sin_lon, sin_lat, -cos_lat * cos_lon),
+                )
+            }
+            Projection::Perspective => unreachable!("perspective rays are cast in ray_through"),
+        };
+
+        Ray {
+            origin: inverse * &origin,
+            direction: (inverse * &direction).norm().unwrap(),
+        }
+    }
+
+    pub fn render(&self, world: &World, opts: &RenderOpts) -> Canvas {
+        self.render_with_progress(world, opts, &|_: &Progress| {}, &CancellationToken::new())
+    }
+
+    /// Renders a world, telling `observer` about each pixel as it's finished.
+    /// Once `cancel` is cancelled no more pixels are started, and the canvas is
+    /// returned with the unfinished pixels left black.
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        opts: &RenderOpts,
+        observer: &dyn ProgressObserver,
+        cancel: &CancellationToken,
+    ) -> Canvas {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+
+        let region = match &opts.region {
+            Some(r) => r.clamp_to(self.hsize, self.vsize),
+            None => Region {
+                x: 0,
+                y: 0,
+                width: self.hsize,
+                height: self.vsize,
+            },
+        };
+
+        let tracker = Tracker::new(observer, cancel, region.width * region.height);
+        let indices = (region.x..region.x + region.width)
+            .into_par_iter()
+            .flat_map_iter(|x| (region.y..region.y + region.height).map(move |y| (x, y)))
+            .filter(|_| !tracker.is_cancelled());
+
+        let colors = match opts.sampler {
+            Sampler::Adaptive {
+                threshold,
+                max_depth,
+            } => self.render_adaptive(world, &region, indices, &tracker, threshold, max_depth),
+            _ if opts.filter != Filter::Box => {
+                self.render_filtered(world, &region, indices, &tracker, opts)
+            }
+            _ => indices
+                .map(|(x, y)| {
+                    (
+                        (x, y),
+                        self.rays_for_pixel(x, y, opts.anti_aliasing_samples, &opts.sampler),
+                    )
+                })
+                .map(|(p, rays)| {
+                    let color = &(rays
                         .map(|r| world.color_at(&r))
                         .reduce(|acc, c| &acc + &c)
                         .unwrap())
-                        * (1.0 / (opts.anti_aliasing_samples.pow(2)) as f64),
-                )
-            })
-            .collect::<Vec<_>>();
+                        * (1.0 / (opts.anti_aliasing_samples.pow(2)) as f64);
+                    tracker.pixel_done();
+                    (p, color)
+                })
+                .collect::<Vec<_>>(),
+        };
 
         colors.into_iter().for_each(|(p, c)| {
             image.write(p, c);
@@ -122,11 +343,412 @@ impl Camera {
 
         image
     }
+
+    /// Traces every ray `render` casts through pixel (x, y), recording how its
+    /// color came about. Adaptive sampling falls back to a grid, and the
+    /// pixel's color is the plain average of its rays, without filtering.
+    pub fn debug_pixel(&self, world: &World, x: usize, y: usize, opts: &RenderOpts) -> PixelTrace {
+        let samples = opts.anti_aliasing_samples;
+        let rays = self
+            .rays_for_pixel(x, y, samples, &opts.sampler)
+            .map(|r| world.trace_ray(&r))
+            .collect::<Vec<_>>();
+
+        let mut color = [0.0; 3];
+        for ray in &rays {
+            for (total, c) in color.iter_mut().zip(ray.color) {
+                *total += c / rays.len() as f64;
+            }
+        }
+
+        PixelTrace { x, y, color, rays }
+    }
+
+    /// Renders the color split into `LightingPasses`, with a canvas for each.
+    /// Samples are placed as for `render`, except that adaptive sampling falls
+    /// back to a grid, and are averaged within each pixel without filtering.
+    pub fn render_passes(&self, world: &World, opts: &RenderOpts) -> RenderPasses {
+        let mut passes = RenderPasses::new(self.hsize, self.vsize, world.lights.len());
+
+        let region = match &opts.region {
+            Some(r) => r.clamp_to(self.hsize, self.vsize),
+            None => Region {
+                x: 0,
+                y: 0,
+                width: self.hsize,
+                height: self.vsize,
+            },
+        };
+        let samples = opts.anti_aliasing_samples;
+
+        let pixels = (region.x..region.x + region.width)
+            .into_par_iter()
+            .flat_map_iter(|x| (region.y..region.y + region.height).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let total = self
+                    .rays_for_pixel(x, y, samples, &opts.sampler)
+                    .map(|r| world.passes_at(&r))
+                    .reduce(|acc, p| acc.add(&p))
+                    .unwrap();
+                ((x, y), total.scale(1.0 / (samples * samples) as f64))
+            })
+            .collect::<Vec<_>>();
+
+        for (p, pixel) in pixels {
+            passes.write(p, pixel);
+        }
+
+        passes
+    }
+
+    /// Renders the AOV passes, from one ray through the center of each pixel.
+    /// Nothing in a world moves, so motion vectors come from the camera moving
+    /// since `previous`, the camera for the last frame; they're zero without one.
+    /// Depth of field is ignored.
+    pub fn render_aovs(&self, world: &World, previous: Option<&Camera>) -> AovBuffers {
+        let inverse = self.transform.inverse().clone();
+        let mut aovs = AovBuffers::new(self.hsize, self.vsize);
+
+        let samples = (0..self.vsize)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let ray = self.ray_through(x as f64 + 0.5, y as f64 + 0.5, (0.0, 0.0), &inverse);
+                let sample = world.first_hit(&ray).map(|(index, hit)| {
+                    let point = ray.position(hit.t());
+                    let depth = -(&*self.transform * &point).z();
+                    let motion = self
+                        .image_position(&point)
+                        .zip(previous.and_then(|p| p.image_position(&point)))
+                        .map(|(now, before)| (now.0 - before.0, now.1 - before.1))
+                        .unwrap_or((0.0, 0.0));
+                    (
+                        depth,
+                        (*hit.normal).clone(),
+                        hit.color.clone(),
+                        index as u32 + 1,
+                        hit.object().material().id,
+                        motion,
+                    )
+                });
+                (y * self.hsize + x, sample)
+            })
+            .collect::<Vec<_>>();
+
+        for (i, sample) in samples {
+            if let Some((depth, normal, albedo, object_id, material_id, motion)) = sample {
+                aovs.depth[i] = depth;
+                aovs.normal[i] = normal;
+                aovs.albedo[i] = albedo;
+                aovs.object_id[i] = object_id;
+                aovs.material_id[i] = material_id;
+                aovs.motion[i] = motion;
+            }
+        }
+
+        aovs
+    }
+
+    /// Returns where a point in world space appears on the canvas, in pixels
+    /// from the top-left corner, inverting `ray_through` for a pinhole camera.
+    /// The position may lie outside the canvas. Returns `None` for points the
+    /// projection can't see, such as those behind a perspective camera.
+    pub fn image_position(&self, point: &Point3d) -> Option<(f64, f64)> {
+        let p = &*self.transform * point;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        let hsize = self.hsize as f64;
+        let vsize = self.vsize as f64;
+
+        match self.projection {
+            Projection::Perspective => {
+                if z >= 0.0 {
+                    return None;
+                }
+                let world_x = x / -z;
+                let world_y = y / -z;
+                Some((
+                    (self.half_width - world_x) / self.pixel_size,
+                    (self.half_height - world_y) / self.pixel_size,
+                ))
+            }
+            Projection::Orthographic { height } => {
+                if z > 0.0 {
+                    return None;
+                }
+                let pixel_size = height / vsize;
+                Some((hsize / 2.0 - x / pixel_size, vsize / 2.0 - y / pixel_size))
+            }
+            Projection::Fisheye { vfov } => {
+                let r_xy = f64::sqrt(x * x + y * y);
+                if r_xy == 0.0 {
+                    return (z < 0.0).then_some((hsize / 2.0, vsize / 2.0));
+                }
+                let theta = f64::atan2(r_xy, -z);
+                let r = theta * vsize / vfov.to_radians();
+                Some((hsize / 2.0 - r * x / r_xy, vsize / 2.0 - r * y / r_xy))
+            }
+            Projection::Equirectangular => {
+                if x == 0.0 && y == 0.0 && z == 0.0 {
+                    return None;
+                }
+                let longitude = f64::atan2(-x, -z);
+                let latitude = f64::atan2(y, f64::sqrt(x * x + z * z));
+                Some((
+                    (longitude / consts::TAU + 0.5) * hsize,
+                    (0.5 - latitude / consts::PI) * vsize,
+                ))
+            }
+        }
+    }
+
+    /// Renders a region, splatting each sample onto the pixels within the
+    /// filter's reach. All samples are traced before any are splatted, so that
+    /// they are added up in the same order every time. Only the pixels whose
+    /// own samples were traced are returned, even though a cancelled render
+    /// splats onto the others too.
+    fn render_filtered(
+        &self,
+        world: &World,
+        region: &Region,
+        indices: impl ParallelIterator<Item = (usize, usize)>,
+        tracker: &Tracker,
+        opts: &RenderOpts,
+    ) -> Vec<((usize, usize), Color)> {
+        let samples = indices
+            .map(|(x, y)| {
+                let positions = sample_positions(x, y, opts.anti_aliasing_samples, &opts.sampler);
+                let colors = self
+                    .rays_for_pixel(x, y, opts.anti_aliasing_samples, &opts.sampler)
+                    .map(|r| world.color_at(&r));
+                let pixel_samples = positions.into_iter().zip(colors).collect::<Vec<_>>();
+                tracker.pixel_done();
+                ((x, y), pixel_samples)
+            })
+            .collect::<Vec<_>>();
+
+        let filter = &opts.filter;
+        let reach = (filter.radius() + 0.5).ceil() as usize;
+        let region_right = region.x + region.width - 1;
+        let region_bottom = region.y + region.height - 1;
+
+        let mut sums = vec![color::black(); region.width * region.height];
+        let mut weights = vec![0.0; region.width * region.height];
+        let mut finished = vec![false; region.width * region.height];
+        for ((px, py), pixel_samples) in samples {
+            finished[(py - region.y) * region.width + px - region.x] = true;
+            for ((sx, sy), c) in pixel_samples {
+                for y in py.saturating_sub(reach).max(region.y)..=(py + reach).min(region_bottom) {
+                    for x in px.saturating_sub(reach).max(region.x)..=(px + reach).min(region_right)
+                    {
+                        // offset of the sample from the pixel's center
+                        let weight = filter.weight(sx - (x as f64 + 0.5), sy - (y as f64 + 0.5));
+                        if weight != 0.0 {
+                            let n = (y - region.y) * region.width + x - region.x;
+                            sums[n] = &sums[n] + &(&c * weight);
+                            weights[n] += weight;
+                        }
+                    }
+                }
+            }
+        }
+
+        sums.into_iter()
+            .zip(weights)
+            .enumerate()
+            .filter(|&(n, _)| finished[n])
+            .map(|(n, (sum, weight))| {
+                let p = (region.x + n % region.width, region.y + n / region.width);
+                if weight == 0.0 {
+                    (p, color::black())
+                } else {
+                    (p, &sum * (1.0 / weight))
+                }
+            })
+            .collect()
+    }
+
+    /// Renders a region with adaptive supersampling. Colors are first found at
+    /// every pixel corner, so that neighbouring pixels share them, and then each
+    /// pixel is subdivided where its corners disagree.
+    fn render_adaptive(
+        &self,
+        world: &World,
+        region: &Region,
+        indices: impl ParallelIterator<Item = (usize, usize)>,
+        tracker: &Tracker,
+        threshold: f64,
+        max_depth: usize,
+    ) -> Vec<((usize, usize), Color)> {
+        let inverse = self.transform.inverse().clone();
+        let color_at = |x: f64, y: f64| {
+            let point = [x.to_bits(), y.to_bits()];
+            let lens = (hash(&point), hash(&[point[0], point[1], 1]) * consts::TAU);
+            world.color_at(&self.ray_through(x, y, lens, &inverse))
+        };
+
+        // Neighbouring pixels share corners, so the corners are laid out on a
+        // grid with one more row and column than the region. Corners left out
+        // by cancelling are `None`, and the pixels using them are skipped.
+        let columns = region.width + 1;
+        let corners = (0..columns * (region.height + 1))
+            .into_par_iter()
+            .map(|i| {
+                (!tracker.is_cancelled()).then(|| {
+                    color_at(
+                        (region.x + i % columns) as f64,
+                        (region.y + i / columns) as f64,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        indices
+            .filter_map(|(x, y)| {
+                let corner = |dx: usize, dy: usize| {
+                    corners[(y - region.y + dy) * columns + x - region.x + dx].clone()
+                };
+                let color = subdivide(
+                    &color_at,
+                    (x as f64, y as f64),
+                    1.0,
+                    [corner(0, 0)?, corner(1, 0)?, corner(0, 1)?, corner(1, 1)?],
+                    threshold,
+                    max_depth,
+                );
+                tracker.pixel_done();
+                Some(((x, y), color))
+            })
+            .collect()
+    }
+}
+
+/// Returns the positions of a pixel's samples on the canvas, in pixels
+fn sample_positions(px: usize, py: usize, samples: usize, sampler: &Sampler) -> Vec<(f64, f64)> {
+    let pixel = [px as u64, py as u64];
+    // Shifting the Halton points differently for each pixel turns aliasing into noise
+    let halton_shift = (
+        hash(&[pixel[0], pixel[1], 1]),
+        hash(&[pixel[0], pixel[1], 2]),
+    );
+    let sample_offset = 1.0 / samples as f64;
+
+    let mut positions = Vec::with_capacity(samples * samples);
+    for nx in 0..samples {
+        for ny in 0..samples {
+            let index = nx * samples + ny;
+            positions.push(match sampler {
+                Sampler::Grid | Sampler::Adaptive { .. } => (
+                    px as f64 + sample_offset * (nx as f64 + 0.5),
+                    py as f64 + sample_offset * (ny as f64 + 0.5),
+                ),
+                Sampler::Jittered => {
+                    let jitter = |dimension| hash(&[pixel[0], pixel[1], index as u64, dimension]);
+                    (
+                        px as f64 + sample_offset * (nx as f64 + jitter(0)),
+                        py as f64 + sample_offset * (ny as f64 + jitter(1)),
+                    )
+                }
+                Sampler::Halton => (
+                    px as f64 + (halton(index, 2) + halton_shift.0).fract(),
+                    py as f64 + (halton(index, 3) + halton_shift.1).fract(),
+                ),
+            });
+        }
+    }
+
+    positions
+}
+
+/// Returns the average color of a square of the canvas with its top-left corner
+/// at `origin`, given the colors at its corners (top-left, top-right, bottom-left,
+/// bottom-right). If the corners differ by more than `threshold` in any channel,
+/// the square is split into quadrants which are averaged instead.
+fn subdivide(
+    color_at: &impl Fn(f64, f64) -> Color,
+    origin: (f64, f64),
+    size: f64,
+    corners: [Color; 4],
+    threshold: f64,
+    depth: usize,
+) -> Color {
+    let [top_left, top_right, bottom_left, bottom_right] = corners;
+    let average = |a: &Color, b: &Color, c: &Color, d: &Color| &(&(&(a + b) + c) + d) * 0.25;
+
+    let spread = |channel: fn(&Color) -> f64| {
+        let values = [&top_left, &top_right, &bottom_left, &bottom_right].map(channel);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        max - min
+    };
+    let similar = spread(Color::r) <= threshold
+        && spread(Color::g) <= threshold
+        && spread(Color::b) <= threshold;
+
+    if depth == 0 || similar {
+        return average(&top_left, &top_right, &bottom_left, &bottom_right);
+    }
+
+    let (x, y) = origin;
+    let half = size / 2.0;
+    let top = color_at(x + half, y);
+    let left = color_at(x, y + half);
+    let center = color_at(x + half, y + half);
+    let right = color_at(x + size, y + half);
+    let bottom = color_at(x + half, y + size);
+
+    let quadrant =
+        |origin, corners| subdivide(color_at, origin, half, corners, threshold, depth - 1);
+    average(
+        &quadrant(
+            (x, y),
+            [top_left, top.clone(), left.clone(), center.clone()],
+        ),
+        &quadrant(
+            (x + half, y),
+            [top, top_right, center.clone(), right.clone()],
+        ),
+        &quadrant(
+            (x, y + half),
+            [left, center.clone(), bottom_left, bottom.clone()],
+        ),
+        &quadrant((x + half, y + half), [center, right, bottom, bottom_right]),
+    )
+}
+
+/// Returns the `index`th element of the Halton sequence in the given base,
+/// which lies in [0, 1)
+fn halton(mut index: usize, base: usize) -> f64 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f64;
+        result += fraction * (index % base) as f64;
+        index /= base;
+    }
+    result
+}
+
+/// Maps a list of numbers to a pseudo-random number in [0, 1). Used in place of
+/// an RNG so that renders are repeatable, however the work is divided.
+fn hash(values: &[u64]) -> f64 {
+    let h = values.iter().fold(0u64, |acc, v| {
+        let mut h = (acc ^ v).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^ (h >> 31)
+    });
+    (h >> 11) as f64 / (1u64 << 53) as f64
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts;
+    use std::{
+        f64::consts,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
 
     use crate::{
         draw::color::{self, Color},
@@ -177,7 +799,7 @@ mod tests {
         fn constructing_ray_through_center_of_canvas() {
             let c = Camera::default(201, 101, consts::FRAC_PI_2);
 
-            let r = c.rays_for_pixel(100, 50, 1);
+            let r = c.rays_for_pixel(100, 50, 1, &Sampler::Grid);
 
             assert_eq!(
                 r.collect::<Vec<_>>(),
@@ -192,7 +814,9 @@ mod tests {
         fn constructing_ray_through_corner_of_canvas() {
             let c = Camera::default(201, 101, consts::FRAC_PI_2);
 
-            let r = &c.rays_for_pixel(0, 0, 1).collect::<Vec<_>>()[0];
+            let r = &c
+                .rays_for_pixel(0, 0, 1, &Sampler::Grid)
+                .collect::<Vec<_>>()[0];
 
             assert_eq!(r.origin, Point3d::new(0.0, 0.0, 0.0));
             vector::test_utils::assert_vec_approx_equals(
@@ -214,7 +838,9 @@ mod tests {
                 InvertibleMatrix::try_from(transform).unwrap(),
             );
 
-            let r = &c.rays_for_pixel(100, 50, 1).collect::<Vec<_>>()[0];
+            let r = &c
+                .rays_for_pixel(100, 50, 1, &Sampler::Grid)
+                .collect::<Vec<_>>()[0];
 
             let t = consts::SQRT_2 / 2.0;
             assert_eq!(
@@ -227,7 +853,7 @@ mod tests {
         fn generating_multiple_samples_for_antialiasing() {
             let c = Camera::default(1, 1, consts::FRAC_PI_2);
 
-            let r = c.rays_for_pixel(0, 0, 2);
+            let r = c.rays_for_pixel(0, 0, 2, &Sampler::Grid);
             let dt = c.pixel_size / 4.0;
 
             assert_eq!(
@@ -254,6 +880,578 @@ mod tests {
         }
     }
 
+    mod sampling {
+        use std::cell::Cell;
+
+        use super::*;
+
+        fn in_cell(position: (f64, f64), cell: (f64, f64), cell_size: f64) -> bool {
+            (cell.0..cell.0 + cell_size).contains(&position.0)
+                && (cell.1..cell.1 + cell_size).contains(&position.1)
+        }
+
+        #[test]
+        fn grid_samples_are_at_cell_centers() {
+            assert_eq!(
+                sample_positions(3, 4, 2, &Sampler::Grid),
+                vec![(3.25, 4.25), (3.25, 4.75), (3.75, 4.25), (3.75, 4.75)]
+            );
+        }
+
+        #[test]
+        fn jittered_samples_stay_in_their_cells() {
+            let positions = sample_positions(3, 4, 2, &Sampler::Jittered);
+            let cells = [(3.0, 4.0), (3.0, 4.5), (3.5, 4.0), (3.5, 4.5)];
+
+            assert!(positions
+                .iter()
+                .zip(cells)
+                .all(|(p, cell)| in_cell(*p, cell, 0.5)));
+            assert_ne!(positions, sample_positions(3, 4, 2, &Sampler::Grid));
+        }
+
+        #[test]
+        fn random_samples_are_repeatable() {
+            for sampler in [Sampler::Jittered, Sampler::Halton] {
+                assert_eq!(
+                    sample_positions(3, 4, 3, &sampler),
+                    sample_positions(3, 4, 3, &sampler)
+                );
+                assert_ne!(
+                    sample_positions(3, 4, 3, &sampler),
+                    sample_positions(4, 3, 3, &sampler)
+                        .iter()
+                        .map(|(x, y)| (x - 1.0, y + 1.0))
+                        .collect::<Vec<_>>()
+                );
+            }
+        }
+
+        #[test]
+        fn halton_samples_stay_in_the_pixel() {
+            let positions = sample_positions(3, 4, 4, &Sampler::Halton);
+
+            assert_eq!(positions.len(), 16);
+            assert!(positions.iter().all(|p| in_cell(*p, (3.0, 4.0), 1.0)));
+        }
+
+        #[test]
+        fn halton_sequence() {
+            assert_eq!(halton(0, 2), 0.0);
+            assert_eq!(halton(1, 2), 0.5);
+            assert_eq!(halton(2, 2), 0.25);
+            assert_eq!(halton(3, 2), 0.75);
+            assert!(util::are_equal(halton(1, 3), 1.0 / 3.0));
+            assert!(util::are_equal(halton(5, 3), 7.0 / 9.0));
+        }
+
+        #[test]
+        fn smooth_squares_are_not_subdivided() {
+            let calls = Cell::new(0);
+            let color_at = |_, _| {
+                calls.set(calls.get() + 1);
+                color::white()
+            };
+            let corners = [
+                Color::new(0.5, 0.5, 0.5),
+                Color::new(0.55, 0.5, 0.5),
+                Color::new(0.5, 0.45, 0.5),
+                Color::new(0.5, 0.5, 0.5),
+            ];
+
+            let c = subdivide(&color_at, (0.0, 0.0), 1.0, corners, 0.1, 4);
+
+            assert_eq!(calls.get(), 0);
+            color::test_utils::assert_colors_approx_equal(&c, &Color::new(0.5125, 0.4875, 0.5));
+        }
+
+        #[test]
+        fn edges_are_subdivided() {
+            let calls = Cell::new(0);
+            // Black to the left of x = 0.5, white to the right
+            let color_at = |x: f64, _| {
+                calls.set(calls.get() + 1);
+                if x < 0.5 {
+                    color::black()
+                } else {
+                    color::white()
+                }
+            };
+            let corners = [
+                color::black(),
+                color::white(),
+                color::black(),
+                color::white(),
+            ];
+
+            let c = subdivide(&color_at, (0.0, 0.0), 1.0, corners, 0.1, 1);
+
+            // One split samples the midpoints of the edges and the center
+            assert_eq!(calls.get(), 5);
+            color::test_utils::assert_colors_approx_equal(&c, &Color::new(0.75, 0.75, 0.75));
+        }
+
+        #[test]
+        fn rendering_with_adaptive_sampling() {
+            let w = World::basic();
+            let from = Point3d::new(0.0, 0.0, -5.0);
+            let to = Point3d::new(0.0, 0.0, 0.0);
+            let up = Vec3d::new(0.0, 1.0, 0.0);
+            let c = Camera::new(
+                11,
+                11,
+                consts::FRAC_PI_2,
+                InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up))
+                    .unwrap(),
+            );
+            let opts = RenderOpts {
+                sampler: Sampler::Adaptive {
+                    threshold: 0.005,
+                    max_depth: 3,
+                },
+                ..Default::default()
+            };
+
+            let adaptive = c.render(&w, &opts);
+            let grid = c.render(
+                &w,
+                &RenderOpts {
+                    anti_aliasing_samples: 8,
+                    ..Default::default()
+                },
+            );
+
+            // Subdividing approaches the pixel's average color
+            let (a, g) = (adaptive.at(5, 5).unwrap(), grid.at(5, 5).unwrap());
+            assert!(util::test_utils::are_within_tolerance(a.r(), g.r(), 0.01));
+            assert!(util::test_utils::are_within_tolerance(a.g(), g.g(), 0.01));
+            assert!(util::test_utils::are_within_tolerance(a.b(), g.b(), 0.01));
+            // The background is flat
+            assert_eq!(adaptive.at(0, 0).unwrap(), &color::black());
+        }
+    }
+
+    mod filtering {
+        use crate::{
+            math::point::Point3d,
+            scene::{
+                light::PointLight,
+                material::{Material, Surface},
+                object::{plane::Plane, transformed::Transformed},
+            },
+        };
+
+        use super::*;
+
+        const MITCHELL: Filter = Filter::MitchellNetravali {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        };
+
+        fn basic_camera() -> Camera {
+            let from = Point3d::new(0.0, 0.0, -5.0);
+            let to = Point3d::new(0.0, 0.0, 0.0);
+            let up = Vec3d::new(0.0, 1.0, 0.0);
+            Camera::new(
+                11,
+                11,
+                consts::FRAC_PI_2,
+                InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up))
+                    .unwrap(),
+            )
+        }
+
+        #[test]
+        fn filtering_preserves_flat_color() {
+            // A wall lit only by ambient light, filling the view
+            let wall = Transformed::new(
+                Plane {
+                    material: Material {
+                        surface: Surface::Color(Color::new(0.2, 0.4, 0.6)),
+                        ambient: 1.0,
+                        diffuse: 0.0,
+                        specular: 0.0,
+                        ..Default::default()
+                    },
+                },
+                InvertibleMatrix::try_from(transformation::sequence(&[
+                    transformation::rotation_x(consts::FRAC_PI_2),
+                    transformation::translation(0.0, 0.0, 5.0),
+                ]))
+                .unwrap(),
+            );
+            let w = World {
+                objects: vec![Box::new(wall)],
+                lights: vec![PointLight {
+                    position: Point3d::new(0.0, 0.0, -10.0),
+                    intensity: color::white(),
+                }],
+                ..Default::default()
+            };
+            let opts = RenderOpts {
+                anti_aliasing_samples: 2,
+                sampler: Sampler::Jittered,
+                filter: Filter::Gaussian {
+                    radius: 1.5,
+                    alpha: 2.0,
+                },
+                ..Default::default()
+            };
+
+            let image = basic_camera().render(&w, &opts);
+
+            // Weights are normalized, so the color comes through unchanged, even at the edges
+            for (x, y) in [(0, 0), (5, 5), (10, 3), (10, 10)] {
+                color::test_utils::assert_colors_approx_equal(
+                    image.at(x, y).unwrap(),
+                    &Color::new(0.2, 0.4, 0.6),
+                );
+            }
+        }
+
+        #[test]
+        fn filtering_spreads_samples_into_neighboring_pixels() {
+            let w = World::basic();
+            let c = basic_camera();
+            let opts = RenderOpts {
+                filter: MITCHELL,
+                ..Default::default()
+            };
+
+            let filtered = c.render(&w, &opts);
+            let unfiltered = c.render(&w, &RenderOpts::default());
+
+            // The pixel just outside the sphere's edge picks up some of its color
+            assert_eq!(unfiltered.at(3, 5).unwrap(), &color::black());
+            assert_ne!(filtered.at(3, 5).unwrap(), &color::black());
+            // Splatting happens in a fixed order
+            assert_eq!(filtered.ppm(), c.render(&w, &opts).ppm());
+        }
+
+        #[test]
+        fn filtering_a_region_only_splats_inside_it() {
+            let w = World::basic();
+            let opts = RenderOpts {
+                filter: MITCHELL,
+                region: Some(Region {
+                    x: 3,
+                    y: 3,
+                    width: 4,
+                    height: 4,
+                }),
+                ..Default::default()
+            };
+
+            let image = basic_camera().render(&w, &opts);
+
+            assert_eq!(image.at(2, 5).unwrap(), &color::black());
+            assert_ne!(image.at(3, 5).unwrap(), &color::black());
+        }
+    }
+
+    mod depth_of_field {
+        use crate::math::{point::Point3d, util::test_utils::are_within_tolerance};
+
+        use super::*;
+
+        fn lens_camera() -> Camera {
+            Camera {
+                aperture: 0.5,
+                focal_distance: 4.0,
+                ..Camera::default(1, 1, consts::FRAC_PI_2)
+            }
+        }
+
+        #[test]
+        fn lens_rays_start_within_the_aperture() {
+            let c = lens_camera();
+            let rays = c
+                .rays_for_pixel(0, 0, 4, &Sampler::Grid)
+                .collect::<Vec<_>>();
+
+            assert_eq!(rays.len(), 16);
+            for r in &rays {
+                assert_eq!(r.origin.z(), 0.0);
+                assert!(f64::hypot(r.origin.x(), r.origin.y()) <= 0.25);
+            }
+            // Every sample passes through a different point on the lens
+            assert!(rays
+                .iter()
+                .enumerate()
+                .all(|(i, a)| rays[i + 1..].iter().all(|b| a.origin != b.origin)));
+        }
+
+        #[test]
+        fn lens_rays_converge_on_the_focal_plane() {
+            let c = lens_camera();
+
+            // The single sample of the pixel is on the view axis
+            for r in c.rays_for_pixel(0, 0, 1, &Sampler::Grid) {
+                let t = -4.0 / r.direction.z();
+                let p = &r.origin + &(&r.direction * t);
+                assert!(are_within_tolerance(p.x(), 0.0, 1e-9));
+                assert!(are_within_tolerance(p.y(), 0.0, 1e-9));
+            }
+        }
+
+        #[test]
+        fn lens_is_transformed_with_the_camera() {
+            let transform = transformation::translation(0.0, -2.0, 5.0);
+            let c = Camera {
+                transform: InvertibleMatrix::try_from(transform).unwrap(),
+                ..lens_camera()
+            };
+
+            for r in c.rays_for_pixel(0, 0, 1, &Sampler::Grid) {
+                let t = -4.0 / r.direction.z();
+                let p = &r.origin + &(&r.direction * t);
+                assert_eq!(p, Point3d::new(0.0, 2.0, -9.0));
+            }
+        }
+
+        #[test]
+        fn lens_pattern_differs_between_pixels() {
+            let c = Camera {
+                aperture: 0.5,
+                focal_distance: 4.0,
+                ..Camera::default(2, 1, consts::FRAC_PI_2)
+            };
+
+            let a = c.rays_for_pixel(0, 0, 1, &Sampler::Grid).next().unwrap();
+            let b = c.rays_for_pixel(1, 0, 1, &Sampler::Grid).next().unwrap();
+            assert_ne!(a.origin, b.origin);
+        }
+
+        #[test]
+        fn hash_spreads_neighbouring_pixels_over_the_unit_interval() {
+            let values = (0..16)
+                .flat_map(|x| (0..16).map(move |y| hash(&[x, y])))
+                .collect::<Vec<_>>();
+
+            assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+            // Swapping the coordinates gives a different rotation
+            assert_ne!(hash(&[1, 2]), hash(&[2, 1]));
+            // Every sixteenth of the interval is used by some pixel
+            for bucket in 0..16 {
+                assert!(values.iter().any(|v| (v * 16.0) as usize == bucket));
+            }
+        }
+    }
+
+    mod projection {
+        use crate::math::{
+            point::Point3d,
+            vector::{self, Vec3d},
+        };
+
+        use super::*;
+
+        fn camera_with(projection: Projection) -> Camera {
+            Camera {
+                projection,
+                ..Camera::default(200, 100, consts::PI)
+            }
+        }
+
+        fn direction_at(c: &Camera, x: f64, y: f64) -> Vec3d {
+            c.project(x, y, &SquareMatrix::<4>::identity()).direction
+        }
+
+        #[test]
+        fn orthographic_rays_are_parallel() {
+            let c = camera_with(Projection::Orthographic { height: 10.0 });
+
+            let corner = c.project(0.0, 0.0, &SquareMatrix::<4>::identity());
+            let center = c.project(100.0, 50.0, &SquareMatrix::<4>::identity());
+
+            assert_eq!(
+                corner,
+                Ray::new(Point3d::new(10.0, 5.0, 0.0), Vec3d::new(0.0, 0.0, -1.0))
+            );
+            assert_eq!(
+                center,
+                Ray::new(Point3d::new(0.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, -1.0))
+            );
+        }
+
+        #[test]
+        fn fisheye_angle_is_proportional_to_distance_from_center() {
+            let c = camera_with(Projection::Fisheye { vfov: 180.0 });
+
+            assert_eq!(direction_at(&c, 100.0, 50.0), Vec3d::new(0.0, 0.0, -1.0));
+            // A quarter of the canvas height to the right is at 45°
+            let t = consts::SQRT_2 / 2.0;
+            vector::test_utils::assert_vec_approx_equals(
+                &direction_at(&c, 125.0, 50.0),
+                &Vec3d::new(-t, 0.0, -t),
+            );
+            // The top of the canvas is at 90°
+            vector::test_utils::assert_vec_approx_equals(
+                &direction_at(&c, 100.0, 0.0),
+                &Vec3d::new(0.0, 1.0, 0.0),
+            );
+        }
+
+        #[test]
+        fn equirectangular_covers_the_whole_sphere() {
+            let c = camera_with(Projection::Equirectangular);
+
+            assert_eq!(direction_at(&c, 100.0, 50.0), Vec3d::new(0.0, 0.0, -1.0));
+            vector::test_utils::assert_vec_approx_equals(
+                &direction_at(&c, 150.0, 50.0),
+                &Vec3d::new(-1.0, 0.0, 0.0),
+            );
+            vector::test_utils::assert_vec_approx_equals(
+                &direction_at(&c, 0.0, 50.0),
+                &Vec3d::new(0.0, 0.0, 1.0),
+            );
+            vector::test_utils::assert_vec_approx_equals(
+                &direction_at(&c, 30.0, 0.0),
+                &Vec3d::new(0.0, 1.0, 0.0),
+            );
+        }
+
+        #[test]
+        fn projected_rays_are_transformed_with_the_camera() {
+            let transform = transformation::translation(0.0, -2.0, 5.0);
+            let c = Camera {
+                projection: Projection::Orthographic { height: 10.0 },
+                ..Camera::new(
+                    200,
+                    100,
+                    consts::PI,
+                    InvertibleMatrix::try_from(transform).unwrap(),
+                )
+            };
+
+            let r = &c
+                .rays_for_pixel(0, 0, 1, &Sampler::Grid)
+                .collect::<Vec<_>>()[0];
+
+            assert_eq!(
+                r,
+                &Ray::new(Point3d::new(9.95, 6.95, -5.0), Vec3d::new(0.0, 0.0, -1.0))
+            );
+        }
+    }
+
+    mod aovs {
+        use crate::{
+            math::util::test_utils::are_within_tolerance,
+            scene::{
+                material::{Material, Surface},
+                object::{sphere::Sphere, Object},
+            },
+        };
+
+        use super::*;
+
+        fn camera_at(from: Point3d, to: Point3d) -> Camera {
+            let up = Vec3d::new(0.0, 1.0, 0.0);
+            Camera::new(
+                11,
+                11,
+                consts::FRAC_PI_2,
+                InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up))
+                    .unwrap(),
+            )
+        }
+
+        fn tagged_sphere_world() -> World {
+            World {
+                objects: vec![Box::new(Sphere::new(Material {
+                    surface: Surface::Color(Color::new(0.8, 1.0, 0.6)),
+                    id: 7,
+                    ..Default::default()
+                })) as Box<dyn Object>],
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn image_position_inverts_each_projection() {
+            let from = Point3d::new(1.0, 2.0, -5.0);
+            let up = Vec3d::new(0.0, 1.0, 0.0);
+            let transform = InvertibleMatrix::try_from(transformation::view_transform(
+                &from,
+                &Point3d::new(0.0, 0.0, 0.0),
+                &up,
+            ))
+            .unwrap();
+
+            for projection in [
+                Projection::Perspective,
+                Projection::Orthographic { height: 10.0 },
+                Projection::Fisheye { vfov: 180.0 },
+                Projection::Equirectangular,
+            ] {
+                let c = Camera {
+                    projection: projection.clone(),
+                    ..Camera::new(200, 100, consts::FRAC_PI_2, transform.clone())
+                };
+                let ray = c.ray_through(37.5, 20.25, (0.0, 0.0), c.transform.inverse());
+
+                let (x, y) = c.image_position(&ray.position(3.0)).unwrap();
+
+                assert!(
+                    are_within_tolerance(x, 37.5, 1e-9) && are_within_tolerance(y, 20.25, 1e-9),
+                    "{:?} gave ({}, {})",
+                    projection,
+                    x,
+                    y
+                );
+            }
+        }
+
+        #[test]
+        fn points_behind_a_perspective_camera_are_not_on_the_image() {
+            let c = camera_at(Point3d::new(0.0, 0.0, -5.0), Point3d::new(0.0, 0.0, 0.0));
+
+            assert_eq!(c.image_position(&Point3d::new(0.0, 0.0, -6.0)), None);
+        }
+
+        #[test]
+        fn rendering_aovs() {
+            let w = tagged_sphere_world();
+            let c = camera_at(Point3d::new(0.0, 0.0, -5.0), Point3d::new(0.0, 0.0, 0.0));
+
+            let aovs = c.render_aovs(&w, None);
+
+            let center = 5 * 11 + 5;
+            assert!(are_within_tolerance(aovs.depth[center], 4.0, 1e-9));
+            assert_eq!(aovs.normal[center], Vec3d::new(0.0, 0.0, -1.0));
+            assert_eq!(aovs.albedo[center], Color::new(0.8, 1.0, 0.6));
+            assert_eq!(aovs.object_id[center], 1);
+            assert_eq!(aovs.material_id[center], 7);
+            assert_eq!(aovs.motion[center], (0.0, 0.0));
+
+            // The corners miss the sphere
+            assert_eq!(aovs.depth[0], f64::INFINITY);
+            assert_eq!(aovs.object_id[0], 0);
+            assert_eq!(aovs.material_id[0], 0);
+        }
+
+        #[test]
+        fn motion_vectors_follow_the_camera() {
+            let w = tagged_sphere_world();
+            let c = camera_at(Point3d::new(0.0, 0.0, -5.0), Point3d::new(0.0, 0.0, 0.0));
+            let previous = camera_at(Point3d::new(0.5, 0.0, -5.0), Point3d::new(0.5, 0.0, 0.0));
+
+            let still = c.render_aovs(&w, Some(&c));
+            let moved = c.render_aovs(&w, Some(&previous));
+
+            let center = 5 * 11 + 5;
+            assert_eq!(still.motion[center], (0.0, 0.0));
+            // The sphere's front was 0.5 units left of the previous camera's
+            // view direction, at a depth of 4, so it was 0.125 / (2 / 11)
+            // pixels left of the center
+            let (x, y) = moved.motion[center];
+            assert!(are_within_tolerance(x, 0.6875, 1e-9), "x = {}", x);
+            assert!(are_within_tolerance(y, 0.0, 1e-9), "y = {}", y);
+        }
+    }
+
     #[test]
     fn rendering_a_world_with_a_camera() {
         let w = World::basic();
@@ -271,4 +1469,208 @@ mod tests {
             &Color::new(0.38066, 0.47583, 0.2855),
         );
     }
+
+    #[test]
+    fn rendering_passes_with_a_camera() {
+        let w = World::basic();
+        let from = Point3d::new(0.0, 0.0, -5.0);
+        let to = Point3d::new(0.0, 0.0, 0.0);
+        let up = Vec3d::new(0.0, 1.0, 0.0);
+        let transform =
+            InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up)).unwrap();
+        let c = Camera::new(11, 11, consts::FRAC_PI_2, transform);
+        let opts = RenderOpts {
+            anti_aliasing_samples: 2,
+            ..Default::default()
+        };
+
+        let image = c.render(&w, &opts);
+        let passes = c.render_passes(&w, &opts);
+
+        for (x, y) in [(5, 5), (2, 7), (0, 0)] {
+            let sum = passes
+                .named()
+                .iter()
+                .filter(|(name, _)| !name.starts_with("light-"))
+                .fold(color::black(), |acc, (_, canvas)| {
+                    &acc + canvas.at(x, y).unwrap()
+                });
+            color::test_utils::assert_colors_approx_equal(&sum, image.at(x, y).unwrap());
+        }
+        assert_eq!(passes.lights.len(), 1);
+    }
+
+    #[test]
+    fn debugging_a_pixel_traces_each_of_its_rays() {
+        let w = World::basic();
+        let from = Point3d::new(0.0, 0.0, -5.0);
+        let to = Point3d::new(0.0, 0.0, 0.0);
+        let up = Vec3d::new(0.0, 1.0, 0.0);
+        let transform =
+            InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up)).unwrap();
+        let c = Camera::new(11, 11, consts::FRAC_PI_2, transform);
+        let opts = RenderOpts {
+            anti_aliasing_samples: 2,
+            ..Default::default()
+        };
+
+        let trace = c.debug_pixel(&w, 5, 5, &opts);
+
+        assert_eq!((trace.x, trace.y), (5, 5));
+        assert_eq!(trace.rays.len(), 4);
+        assert!(trace.rays.iter().all(|r| r.hit.is_some()));
+        let [r, g, b] = trace.color;
+        color::test_utils::assert_colors_approx_equal(
+            &Color::new(r, g, b),
+            c.render(&w, &opts).at(5, 5).unwrap(),
+        );
+    }
+
+    #[test]
+    fn rendering_a_region_of_a_world() {
+        let w = World::basic();
+        let from = Point3d::new(0.0, 0.0, -5.0);
+        let to = Point3d::new(0.0, 0.0, 0.0);
+        let up = Vec3d::new(0.0, 1.0, 0.0);
+        let transform =
+            InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up)).unwrap();
+        let c = Camera::new(11, 11, consts::FRAC_PI_2, transform);
+
+        let opts = RenderOpts {
+            region: Some(Region {
+                x: 4,
+                y: 5,
+                width: 3,
+                height: 1,
+            }),
+            ..Default::default()
+        };
+        let image = c.render(&w, &opts);
+
+        assert_eq!((image.width(), image.height()), (11, 11));
+        color::test_utils::assert_colors_approx_equal(
+            image.at(5, 5).unwrap(),
+            &Color::new(0.38066, 0.47583, 0.2855),
+        );
+        assert_eq!(image.at(5, 4).unwrap(), &color::black());
+        assert_eq!(image.at(7, 5).unwrap(), &color::black());
+    }
+
+    fn progress_test_opts() -> Vec<RenderOpts> {
+        vec![
+            RenderOpts::default(),
+            RenderOpts {
+                sampler: Sampler::Adaptive {
+                    threshold: 0.1,
+                    max_depth: 2,
+                },
+                ..Default::default()
+            },
+            RenderOpts {
+                filter: Filter::Tent { radius: 1.0 },
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn rendering_reports_progress_for_every_pixel() {
+        let w = World::basic();
+        let c = Camera::default(8, 8, consts::FRAC_PI_2);
+
+        for opts in progress_test_opts() {
+            let updates = Mutex::new(Vec::new());
+            let observer = |p: &Progress| updates.lock().unwrap().push(p.clone());
+
+            c.render_with_progress(&w, &opts, &observer, &CancellationToken::new());
+
+            let updates = updates.into_inner().unwrap();
+            assert_eq!(updates.len(), 64);
+            assert!(updates.iter().all(|p| p.pixels_total == 64));
+            assert_eq!(updates.iter().map(|p| p.pixels_done).max(), Some(64));
+        }
+    }
+
+    #[test]
+    fn cancelling_a_render_leaves_the_unfinished_pixels_black() {
+        let mut w = World::basic();
+        w.void_color = Color::new(1.0, 1.0, 1.0);
+        let from = Point3d::new(0.0, 0.0, -5.0);
+        let to = Point3d::new(0.0, 0.0, 0.0);
+        let up = Vec3d::new(0.0, 1.0, 0.0);
+        let transform =
+            InvertibleMatrix::try_from(transformation::view_transform(&from, &to, &up)).unwrap();
+        let c = Camera::new(8, 8, consts::FRAC_PI_2, transform);
+
+        for opts in progress_test_opts() {
+            let cancel = CancellationToken::new();
+            let finished = AtomicUsize::new(0);
+            let observer = |p: &Progress| {
+                finished.fetch_max(p.pixels_done, Ordering::Relaxed);
+                if p.pixels_done == 10 {
+                    cancel.cancel();
+                }
+            };
+
+            let image = c.render_with_progress(&w, &opts, &observer, &cancel);
+
+            // Pixels already started on other threads are still finished, and
+            // nothing in the scene is black
+            let rendered = (0..8)
+                .flat_map(|x| (0..8).map(move |y| (x, y)))
+                .filter(|&(x, y)| image.at(x, y).unwrap() != &color::black())
+                .count();
+            assert_eq!(rendered, finished.into_inner());
+            assert!(rendered < 64);
+        }
+    }
+
+    #[test]
+    fn cancelled_filtered_renders_do_not_splat_onto_unfinished_pixels() {
+        let mut w = World::basic();
+        w.void_color = Color::new(1.0, 1.0, 1.0);
+        let c = Camera::default(8, 8, consts::FRAC_PI_2);
+        let opts = RenderOpts {
+            filter: Filter::Gaussian {
+                radius: 2.0,
+                alpha: 1.0,
+            },
+            ..Default::default()
+        };
+        let cancel = CancellationToken::new();
+        let finished = AtomicUsize::new(0);
+        let observer = |p: &Progress| {
+            finished.fetch_max(p.pixels_done, Ordering::Relaxed);
+            cancel.cancel();
+        };
+
+        let image = c.render_with_progress(&w, &opts, &observer, &cancel);
+
+        // The finished pixels' samples reach several pixels around them
+        let rendered = (0..8)
+            .flat_map(|x| (0..8).map(move |y| (x, y)))
+            .filter(|&(x, y)| image.at(x, y).unwrap() != &color::black())
+            .count();
+        assert_eq!(rendered, finished.into_inner());
+    }
+
+    #[test]
+    fn clamping_a_region_overlapping_the_canvas_edge() {
+        let r = Region {
+            x: 90,
+            y: 95,
+            width: 20,
+            height: 20,
+        };
+
+        assert_eq!(
+            r.clamp_to(100, 100),
+            Region {
+                x: 90,
+                y: 95,
+                width: 10,
+                height: 5
+            }
+        );
+    }
 }
@@ -0,0 +1,361 @@
+//! Records everything that goes into the color of a ray, for working out why a
+//! pixel looks wrong. The records serialize to JSON.
+
+use std::collections::HashSet;
+
+use by_address::ByAddress;
+use serde::Serialize;
+
+use crate::{
+    draw::color::Color,
+    math::{point::Point3d, vector::Vec3d},
+};
+
+use super::{
+    intersect::{self, Precomputation},
+    object::Object,
+    ray::Ray,
+    world::{World, WorldIntersection},
+};
+
+/// Every ray cast through a pixel, as traced by `Camera::debug_pixel`
+#[derive(Debug, Clone, Serialize)]
+pub struct PixelTrace {
+    pub x: usize,
+    pub y: usize,
+    /// The average color of the rays
+    pub color: [f64; 3],
+    pub rays: Vec<RayTrace>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RayKind {
+    Camera,
+    Reflection,
+    Refraction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RayTrace {
+    pub kind: RayKind,
+    pub origin: [f64; 3],
+    pub direction: [f64; 3],
+    /// How many more reflections or refractions may follow this ray
+    pub remaining: usize,
+    /// Every intersection along the ray, including those behind its origin, sorted by `t`
+    pub intersections: Vec<IntersectionTrace>,
+    /// The nearest intersection in front of the ray's origin
+    pub hit: Option<HitTrace>,
+    pub color: [f64; 3],
+}
+
+/// Identifies the object hit. Objects don't have names, so they're identified
+/// by the index of the top-level object they belong to in `World::objects`,
+/// and by the address of the primitive itself, which tells apart the parts of
+/// groups and CSG shapes within a trace.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectRef {
+    pub index: usize,
+    pub address: String,
+    pub material_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntersectionTrace {
+    pub t: f64,
+    pub object: ObjectRef,
+    pub normal: [f64; 3],
+    pub color: [f64; 3],
+}
+
+/// The fields of the hit's `Precomputation`, along with what was traced from it
+#[derive(Debug, Clone, Serialize)]
+pub struct HitTrace {
+    pub t: f64,
+    pub object: ObjectRef,
+    pub point: [f64; 3],
+    pub eye_v: [f64; 3],
+    pub normal_v: [f64; 3],
+    pub inside: bool,
+    pub over_point: [f64; 3],
+    pub under_point: [f64; 3],
+    pub reflect_v: [f64; 3],
+    pub refraction_exiting: f64,
+    pub refraction_entering: f64,
+    pub object_color: [f64; 3],
+    pub shadows: Vec<ShadowTrace>,
+    /// Fresnel reflectance, for materials which are both reflective and transparent
+    pub schlick: Option<f64>,
+    pub total_internal_reflection: bool,
+    pub reflection: Option<Box<RayTrace>>,
+    pub refraction: Option<Box<RayTrace>>,
+}
+
+/// The shadow ray from a hit towards one of `World::lights`
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowTrace {
+    pub light: usize,
+    pub distance: f64,
+    /// Fraction of the light reaching the hit, after passing through the blockers
+    pub attenuation: f64,
+    pub blockers: Vec<BlockerTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockerTrace {
+    pub t: f64,
+    pub object: ObjectRef,
+    pub transparency: f64,
+}
+
+impl World {
+    /// Traces a ray as `color_at` does, recording each step
+    pub fn trace_ray(&self, ray: &Ray) -> RayTrace {
+        self.trace(ray, RayKind::Camera, self.max_reflection_depth)
+    }
+
+    fn trace(&self, ray: &Ray, kind: RayKind, remaining: usize) -> RayTrace {
+        let mut indexed = self
+            .objects
+            .iter()
+            .enumerate()
+            .flat_map(|(index, object)| object.intersect(ray).into_iter().map(move |i| (index, i)))
+            .collect::<Vec<_>>();
+        indexed.sort_by(|(_, a), (_, b)| a.t().partial_cmp(&b.t()).unwrap());
+        let (indices, xs): (Vec<_>, Vec<_>) = indexed.into_iter().unzip();
+
+        let intersections = indices
+            .iter()
+            .zip(xs.iter())
+            .map(|(&index, i)| IntersectionTrace {
+                t: i.t(),
+                object: object_ref(index, i),
+                normal: vector(&i.normal),
+                color: color(&i.color),
+            })
+            .collect();
+
+        let hit = intersect::hit(&xs).map(|h| {
+            let index = indices[xs.iter().position(|i| std::ptr::eq(i, h)).unwrap()];
+            let comps = h.prepare_computations(ray, &xs);
+            self.trace_hit(&comps, object_ref(index, h), remaining)
+        });
+
+        RayTrace {
+            kind,
+            origin: point(&ray.origin),
+            direction: vector(&ray.direction),
+            remaining,
+            intersections,
+            hit,
+            color: color(&self.color_at_internal(ray, remaining)),
+        }
+    }
+
+    fn trace_hit(
+        &self,
+        comps: &Precomputation<&dyn Object>,
+        object: ObjectRef,
+        remaining: usize,
+    ) -> HitTrace {
+        let m = comps.object.material();
+
+        let reflection = (remaining > 0 && m.reflectivity > 0.0).then(|| {
+            let reflect_ray = Ray::new(comps.over_point.clone(), (*comps.reflect_v).clone());
+            Box::new(self.trace(&reflect_ray, RayKind::Reflection, remaining - 1))
+        });
+
+        // As in refracted_color, using Snell's law
+        let n_ratio = comps.refraction_exiting / comps.refraction_entering;
+        let cos_i = comps.eye_v.dot(&comps.normal_v);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        let total_internal_reflection = m.transparency > 0.0 && sin2_t > 1.0;
+        let refraction = (remaining > 0 && m.transparency > 0.0 && !total_internal_reflection)
+            .then(|| {
+                let cos_t = f64::sqrt(1.0 - sin2_t);
+                let direction =
+                    &(&*comps.normal_v * (n_ratio * cos_i - cos_t)) - &(&*comps.eye_v * n_ratio);
+                let refract_ray = Ray::new(comps.under_point.clone(), direction);
+                Box::new(self.trace(&refract_ray, RayKind::Refraction, remaining - 1))
+            });
+
+        HitTrace {
+            t: comps.t,
+            object,
+            point: point(&comps.point),
+            eye_v: vector(&comps.eye_v),
+            normal_v: vector(&comps.normal_v),
+            inside: comps.inside,
+            over_point: point(&comps.over_point),
+            under_point: point(&comps.under_point),
+            reflect_v: vector(&comps.reflect_v),
+            refraction_exiting: comps.refraction_exiting,
+            refraction_entering: comps.refraction_entering,
+            object_color: color(&comps.object_color),
+            shadows: (0..self.lights.len())
+                .map(|light| self.trace_shadow(&comps.over_point, light))
+                .collect(),
+            schlick: (m.reflectivity > 0.0 && m.transparency > 0.0).then(|| comps.schlick()),
+            total_internal_reflection,
+            reflection,
+            refraction,
+        }
+    }
+
+    fn trace_shadow(&self, from: &Point3d, light: usize) -> ShadowTrace {
+        let position = &self.lights[light].position;
+        let v = position - from;
+        let distance = v.mag();
+
+        // As in shadow_attenuation, each object only blocks the light once
+        let blockers = match v.norm() {
+            Some(direction) => {
+                let r = Ray::new(from.clone(), direction);
+                let mut indexed = self
+                    .objects
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, object)| {
+                        object.intersect(&r).into_iter().map(move |i| (index, i))
+                    })
+                    .collect::<Vec<_>>();
+                indexed.sort_by(|(_, a), (_, b)| a.t().partial_cmp(&b.t()).unwrap());
+                let mut seen = HashSet::<ByAddress<&dyn Object>>::new();
+                indexed
+                    .iter()
+                    .filter(|(_, i)| i.t() > 0.0 && i.t() < distance)
+                    .take_while(|(_, i)| seen.insert(ByAddress(*i.object())))
+                    .map(|(index, i)| BlockerTrace {
+                        t: i.t(),
+                        object: object_ref(*index, i),
+                        transparency: i.object().material().transparency,
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        ShadowTrace {
+            light,
+            distance,
+            attenuation: self.shadow_attenuation(from, &self.lights[light]),
+            blockers,
+        }
+    }
+}
+
+fn object_ref(index: usize, i: &WorldIntersection) -> ObjectRef {
+    ObjectRef {
+        index,
+        address: format!("{:p}", *i.object() as *const dyn Object),
+        material_id: i.object().material().id,
+    }
+}
+
+fn point(p: &Point3d) -> [f64; 3] {
+    [p.x(), p.y(), p.z()]
+}
+
+fn vector(v: &Vec3d) -> [f64; 3] {
+    [v.x(), v.y(), v.z()]
+}
+
+fn color(c: &Color) -> [f64; 3] {
+    [c.r(), c.g(), c.b()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::{matrix::InvertibleMatrix, vector::Vec3d},
+        scene::{
+            material::Material,
+            object::{plane::Plane, transformed::Transformed},
+            transformation,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn tracing_a_ray_records_its_intersections_and_shadows() {
+        let w = World::basic();
+        let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+
+        let trace = w.trace_ray(&r);
+
+        let ts = trace.intersections.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+        let objects = trace
+            .intersections
+            .iter()
+            .map(|i| i.object.index)
+            .collect::<Vec<_>>();
+        assert_eq!(objects, vec![0, 1, 1, 0]);
+
+        let hit = trace.hit.unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.point, [0.0, 0.0, -1.0]);
+        assert_eq!(hit.normal_v, [0.0, 0.0, -1.0]);
+        assert!(!hit.inside);
+        assert_eq!(hit.shadows.len(), 1);
+        assert_eq!(hit.shadows[0].attenuation, 1.0);
+        assert!(hit.shadows[0].blockers.is_empty());
+        assert!(hit.reflection.is_none() && hit.refraction.is_none());
+
+        let c = w.color_at(&r);
+        assert_eq!(trace.color, [c.r(), c.g(), c.b()]);
+    }
+
+    #[test]
+    fn shadow_blockers_are_recorded() {
+        let w = World::basic();
+        // From behind both spheres, as seen from the light
+        let r = Ray::new(Point3d::new(10.0, -10.0, 10.0), Vec3d::new(-1.0, 1.0, -1.0));
+
+        let trace = w.trace_ray(&r).hit.unwrap();
+
+        assert_eq!(trace.shadows[0].attenuation, 0.0);
+        assert_eq!(trace.shadows[0].blockers[0].object.index, 0);
+    }
+
+    #[test]
+    fn reflections_and_refractions_are_traced_recursively() {
+        let mut w = World::basic();
+        let floor = Transformed::new(
+            Plane {
+                material: Material {
+                    transparency: 0.5,
+                    refractive_index: 1.5,
+                    reflectivity: 0.5,
+                    ..Default::default()
+                },
+            },
+            InvertibleMatrix::try_from(transformation::translation(0.0, -1.0, 0.0)).unwrap(),
+        );
+        w.objects.push(Box::new(floor));
+        let sqrt2 = std::f64::consts::SQRT_2;
+        let r = Ray::new(
+            Point3d::new(0.0, 0.0, -3.0),
+            Vec3d::new(0.0, -sqrt2 / 2.0, sqrt2 / 2.0),
+        );
+
+        let trace = w.trace_ray(&r);
+
+        let hit = trace.hit.as_ref().unwrap();
+        assert_eq!(hit.object.index, 2);
+        assert!(hit.schlick.is_some());
+        let reflection = hit.reflection.as_ref().unwrap();
+        let refraction = hit.refraction.as_ref().unwrap();
+        assert_eq!(reflection.kind, RayKind::Reflection);
+        assert_eq!(refraction.kind, RayKind::Refraction);
+        assert_eq!(reflection.remaining, w.max_reflection_depth - 1);
+        assert_eq!(reflection.origin, hit.over_point);
+        assert_eq!(refraction.origin, hit.under_point);
+
+        let json = serde_json::to_string(&trace).unwrap();
+        assert!(json.contains("\"kind\":\"refraction\""));
+        assert_ne!(trace.color, [0.0; 3]);
+    }
+}
@@ -28,6 +28,9 @@ pub struct Material {
     pub reflectivity: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    /// Identifies the material in the material ID pass. Zero (the default)
+    /// means untagged.
+    pub id: u32,
 }
 
 impl PartialEq for Material {
@@ -47,10 +50,25 @@ impl Default for Material {
             reflectivity: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            id: 0,
         }
     }
 }
 
+/// The Phong reflection terms for one light at a point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shading {
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+}
+
+impl Shading {
+    pub fn total(&self) -> Color {
+        &(&self.ambient + &self.diffuse) + &self.specular
+    }
+}
+
 pub fn lighting(
     material: &Material,
     point: &Point3d,
@@ -60,6 +78,28 @@ pub fn lighting(
     normalv: &NormalizedVec3d,
     shadow_attenuation: f64,
 ) -> Color {
+    shading(
+        material,
+        point,
+        object_color,
+        light,
+        eyev,
+        normalv,
+        shadow_attenuation,
+    )
+    .total()
+}
+
+/// Like `lighting`, keeping the ambient, diffuse and specular terms separate
+pub fn shading(
+    material: &Material,
+    point: &Point3d,
+    object_color: &Color,
+    light: &PointLight,
+    eyev: &NormalizedVec3d,
+    normalv: &NormalizedVec3d,
+    shadow_attenuation: f64,
+) -> Shading {
     let effective_color = object_color * &light.intensity;
     let lightv = (&light.position - point).norm().unwrap();
 
@@ -86,7 +126,11 @@ pub fn lighting(
         )
     };
 
-    &(&ambient + &diffuse) + &specular
+    Shading {
+        ambient,
+        diffuse,
+        specular,
+    }
 }
 
 #[cfg(test)]
@@ -154,6 +198,31 @@ mod tests {
             assert_eq!(result, Color::new(1.9, 1.9, 1.9));
         }
 
+        #[test]
+        fn shading_splits_lighting_into_its_terms() {
+            let (m, position) = setup();
+            let eyev = NormalizedVec3d::try_from(Vec3d::new(0.0, 0.0, -1.0)).unwrap();
+            let normalv = NormalizedVec3d::try_from(Vec3d::new(0.0, 0.0, -1.0)).unwrap();
+            let light = PointLight {
+                position: Point3d::new(0.0, 0.0, -10.0),
+                intensity: Color::new(1.0, 1.0, 1.0),
+            };
+
+            let result = shading(
+                &m,
+                &position,
+                &m.surface.color_at(&position),
+                &light,
+                &eyev,
+                &normalv,
+                1.0,
+            );
+            assert_eq!(result.ambient, Color::new(0.1, 0.1, 0.1));
+            assert_eq!(result.diffuse, Color::new(0.9, 0.9, 0.9));
+            assert_eq!(result.specular, Color::new(0.9, 0.9, 0.9));
+            assert_eq!(result.total(), Color::new(1.9, 1.9, 1.9));
+        }
+
         #[test]
         fn lighting_with_eye_between_light_and_surface_eye_offset_45_degrees() {
             let (m, position) = setup();
@@ -1,16 +1,25 @@
 use crate::draw::canvas::Canvas;
 
 use self::{
+    aov::AovBuffers,
     camera::{Camera, RenderOpts},
+    debug::PixelTrace,
+    passes::RenderPasses,
+    progress::{CancellationToken, ProgressObserver},
     world::World,
 };
 
+pub mod animation;
+pub mod aov;
 pub mod camera;
+pub mod debug;
 pub mod intersect;
 pub mod light;
 pub mod material;
 pub mod object;
+pub mod passes;
 pub mod pattern;
+pub mod progress;
 pub mod ray;
 pub mod transformation;
 pub mod world;
@@ -24,4 +33,26 @@ impl Scene {
     pub fn render(&self, opts: &RenderOpts) -> Canvas {
         self.camera.render(&self.world, opts)
     }
+
+    pub fn render_with_progress(
+        &self,
+        opts: &RenderOpts,
+        observer: &dyn ProgressObserver,
+        cancel: &CancellationToken,
+    ) -> Canvas {
+        self.camera
+            .render_with_progress(&self.world, opts, observer, cancel)
+    }
+
+    pub fn render_passes(&self, opts: &RenderOpts) -> RenderPasses {
+        self.camera.render_passes(&self.world, opts)
+    }
+
+    pub fn debug_pixel(&self, x: usize, y: usize, opts: &RenderOpts) -> PixelTrace {
+        self.camera.debug_pixel(&self.world, x, y, opts)
+    }
+
+    pub fn render_aovs(&self) -> AovBuffers {
+        self.camera.render_aovs(&self.world, None)
+    }
 }
@@ -4,6 +4,7 @@ use crate::{
     draw::color::Color,
     math::{point::Point3d, vector::NormalizedVec3d},
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::Object;
@@ -73,6 +74,33 @@ impl Bounds {
             maximum: Point3d::new(0.0, 0.0, 0.0),
         })
     }
+
+    /// Whether the ray passes through the box, anywhere along its length
+    pub fn hit(&self, ray: &Ray) -> bool {
+        let (xtmin, xtmax) = check_axis(
+            self.minimum.x(),
+            self.maximum.x(),
+            ray.origin.x(),
+            ray.direction.x(),
+        );
+        let (ytmin, ytmax) = check_axis(
+            self.minimum.y(),
+            self.maximum.y(),
+            ray.origin.y(),
+            ray.direction.y(),
+        );
+        let (ztmin, ztmax) = check_axis(
+            self.minimum.z(),
+            self.maximum.z(),
+            ray.origin.z(),
+            ray.direction.z(),
+        );
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        tmin <= tmax
+    }
 }
 
 impl Default for Bounds {
@@ -98,29 +126,7 @@ impl<T: Object> Bounded<T> {
     }
 
     fn test(&self, ray: &Ray) -> bool {
-        let (xtmin, xtmax) = check_axis(
-            self.bounds.minimum.x(),
-            self.bounds.maximum.x(),
-            ray.origin.x(),
-            ray.direction.x(),
-        );
-        let (ytmin, ytmax) = check_axis(
-            self.bounds.minimum.y(),
-            self.bounds.maximum.y(),
-            ray.origin.y(),
-            ray.direction.y(),
-        );
-        let (ztmin, ztmax) = check_axis(
-            self.bounds.minimum.z(),
-            self.bounds.maximum.z(),
-            ray.origin.z(),
-            ray.direction.z(),
-        );
-
-        let tmin = xtmin.max(ytmin).max(ztmin);
-        let tmax = xtmax.min(ytmax).min(ztmax);
-
-        tmin <= tmax
+        self.bounds.hit(ray)
     }
 }
 
@@ -144,6 +150,7 @@ impl<T: Object> Object for Bounded<T> {
     }
 
     fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        stats::count(Counter::BoundedNodesVisited);
         if self.test(ray) {
             self.child.intersect(ray)
         } else {
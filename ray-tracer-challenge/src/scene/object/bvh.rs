@@ -0,0 +1,277 @@
+use crate::{
+    draw::color::Color,
+    math::{point::Point3d, vector::NormalizedVec3d},
+    scene::{
+        intersect::{self, Intersection},
+        material::Material,
+        ray::Ray,
+    },
+    stats::{self, Counter},
+};
+
+use super::{bounded::Bounds, Object};
+
+/// The most children kept in one leaf of the hierarchy
+const MAX_LEAF_CHILDREN: usize = 2;
+
+/// Like a `Group`, but with the children sorted into a hierarchy of bounding
+/// boxes, so that rays only test the children they might hit. Suits scenes
+/// with many objects, such as instances of one shared mesh. `Mesh` keeps its
+/// faces in one too.
+pub struct Bvh<T> {
+    children: Vec<T>,
+    /// The hierarchy over `children`, with the root first
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    bounds: Bounds,
+    contents: NodeContents,
+}
+
+enum NodeContents {
+    /// A range of `children`
+    Leaf { start: usize, end: usize },
+    /// Indices of the children in `nodes`
+    Inner { left: usize, right: usize },
+}
+
+impl<T: Object> Bvh<T> {
+    pub fn new(children: Vec<T>) -> Self {
+        Bvh::with_bounds(children, |c| c.bounds(), MAX_LEAF_CHILDREN)
+    }
+}
+
+impl<T> Bvh<T> {
+    /// Builds the hierarchy over children which aren't objects themselves,
+    /// with leaves of up to `max_leaf_children`
+    pub(super) fn with_bounds(
+        children: Vec<T>,
+        bounds: impl Fn(&T) -> Bounds,
+        max_leaf_children: usize,
+    ) -> Self {
+        let bounds = children.iter().map(bounds).collect::<Vec<_>>();
+        let mut order = (0..children.len()).collect::<Vec<_>>();
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            build(&mut nodes, &bounds, &mut order, 0, max_leaf_children);
+        }
+
+        // Put the children in the order the leaves refer to
+        let mut slots = children.into_iter().map(Some).collect::<Vec<_>>();
+        let children = order
+            .iter()
+            .map(|&i| slots[i].take().expect("each child should be placed once"))
+            .collect();
+
+        Bvh { children, nodes }
+    }
+
+    pub(super) fn children(&self) -> &[T] {
+        &self.children
+    }
+
+    /// Calls `visit` with the children in each leaf whose bounds `ray` hits
+    pub(super) fn visit_leaves<'a>(&'a self, ray: &Ray, mut visit: impl FnMut(&'a [T])) {
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            stats::count(Counter::BoundedNodesVisited);
+            let node = &self.nodes[index];
+            if !node.bounds.hit(ray) {
+                continue;
+            }
+
+            match node.contents {
+                NodeContents::Leaf { start, end } => visit(&self.children[start..end]),
+                NodeContents::Inner { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+    }
+
+    /// The bounds of every child, which are empty if there are none
+    pub(super) fn root_bounds(&self) -> Bounds {
+        self.nodes
+            .first()
+            .map_or(Bounds::from_bounds::<Bounds>(&[]), |root| {
+                root.bounds.clone()
+            })
+    }
+}
+
+/// Builds the node for `order`, which are indices into `bounds` and start at
+/// `offset` in the final list of children, and returns its index
+fn build(
+    nodes: &mut Vec<Node>,
+    bounds: &[Bounds],
+    order: &mut [usize],
+    offset: usize,
+    max_leaf_children: usize,
+) -> usize {
+    let node_bounds = Bounds::from_bounds(&order.iter().map(|&i| &bounds[i]).collect::<Vec<_>>());
+    let extent = &node_bounds.maximum - &node_bounds.minimum;
+
+    let index = nodes.len();
+    nodes.push(Node {
+        bounds: node_bounds,
+        contents: NodeContents::Leaf {
+            start: offset,
+            end: offset + order.len(),
+        },
+    });
+
+    if order.len() > max_leaf_children {
+        let extent = [extent.x(), extent.y(), extent.z()];
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap();
+        let centroid = |i: usize| {
+            let Bounds { minimum, maximum } = &bounds[i];
+            let coordinate = |p: &Point3d| [p.x(), p.y(), p.z()][axis];
+            (coordinate(minimum) + coordinate(maximum)) / 2.0
+        };
+        order.sort_unstable_by(|&a, &b| centroid(a).total_cmp(&centroid(b)));
+
+        let mid = order.len() / 2;
+        let (left_order, right_order) = order.split_at_mut(mid);
+        let left = build(nodes, bounds, left_order, offset, max_leaf_children);
+        let right = build(nodes, bounds, right_order, offset + mid, max_leaf_children);
+        nodes[index].contents = NodeContents::Inner { left, right };
+    }
+
+    index
+}
+
+impl<T: Object> Object for Bvh<T> {
+    /// A `Bvh` has no material of its own. Its intersections are with its
+    /// children, so the world only ever asks them for their materials.
+    fn material(&self) -> &Material {
+        unreachable!("a Bvh's intersections are with its children")
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        let mut intersections = Vec::new();
+        self.visit_leaves(ray, |leaf| {
+            intersections.extend(leaf.iter().flat_map(|c| c.intersect(ray)));
+        });
+        intersect::sort(&mut intersections);
+        intersections
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.root_bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        math::{matrix::InvertibleMatrix, vector::Vec3d},
+        scene::{
+            intersect as is,
+            object::{group::Group, sphere::Sphere, transformed::Transformed},
+            transformation,
+        },
+    };
+
+    use super::*;
+
+    fn spheres_along_x(count: usize) -> Vec<Transformed<Sphere>> {
+        (0..count)
+            .map(|i| {
+                Transformed::new(
+                    Sphere::unit(),
+                    InvertibleMatrix::try_from(transformation::translation(
+                        3.0 * i as f64,
+                        0.0,
+                        0.0,
+                    ))
+                    .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn intersecting_a_bvh_matches_a_group() {
+        let bvh = Bvh::new(spheres_along_x(9));
+        let group = Group::new(spheres_along_x(9));
+
+        for r in [
+            Ray::new(Point3d::new(6.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0)),
+            Ray::new(Point3d::new(-5.0, 0.0, 0.0), Vec3d::new(1.0, 0.0, 0.0)),
+            Ray::new(Point3d::new(7.5, 5.0, 0.0), Vec3d::new(0.0, -1.0, 0.0)),
+        ] {
+            assert_eq!(
+                is::test_utils::to_ts(&bvh.intersect(&r)),
+                is::test_utils::to_ts(&group.intersect(&r))
+            );
+        }
+    }
+
+    #[test]
+    fn instances_can_share_one_object() {
+        let shared = Arc::new(Sphere::unit());
+        let instances = (0..4)
+            .map(|i| {
+                Transformed::new(
+                    shared.clone(),
+                    InvertibleMatrix::try_from(transformation::translation(
+                        0.0,
+                        0.0,
+                        3.0 * i as f64,
+                    ))
+                    .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let bvh = Bvh::new(instances);
+        assert_eq!(Arc::strong_count(&shared), 5);
+
+        let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+        let xs = is::test_utils::to_ts(&bvh.intersect(&r));
+
+        assert_eq!(xs, vec![4.0, 6.0, 7.0, 9.0, 10.0, 12.0, 13.0, 15.0]);
+    }
+
+    #[test]
+    fn intersections_take_their_materials_from_the_children() {
+        let glass = Material {
+            transparency: 0.5,
+            ..Default::default()
+        };
+        let bvh = Bvh::new(vec![Sphere::new(glass), Sphere::unit()]);
+
+        let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+        let xs = bvh.intersect(&r);
+
+        assert_eq!(xs.len(), 4);
+        assert_eq!(
+            xs.iter()
+                .filter(|x| x.object().material().transparency == 0.5)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn bounds_of_a_bvh_cover_all_children() {
+        let bvh = Bvh::new(spheres_along_x(3));
+
+        assert_eq!(
+            bvh.bounds(),
+            Bounds {
+                minimum: Point3d::new(-1.0, -1.0, -1.0),
+                maximum: Point3d::new(7.0, 1.0, 1.0)
+            }
+        );
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     draw::color::Color,
     math::{point::Point3d, vector::NormalizedVec3d},
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::{bounded::Bounds, Object, PhysicalObject};
@@ -89,6 +90,7 @@ impl Object for Cone {
         &self,
         object_ray: &Ray,
     ) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        stats::count(Counter::ConeTests);
         let a = object_ray.direction.x().powi(2) - object_ray.direction.y().powi(2)
             + object_ray.direction.z().powi(2);
         let b = 2.0 * object_ray.origin.x() * object_ray.direction.x()
@@ -2,6 +2,7 @@ use crate::{
     draw::color::Color,
     math::{point::Point3d, vector::NormalizedVec3d},
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::{bounded::Bounds, Object, PhysicalObject};
@@ -39,6 +40,7 @@ impl Object for Cube {
         &self,
         object_ray: &Ray,
     ) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        stats::count(Counter::CubeTests);
         let (xtmin, xtmax) = check_axis(object_ray.origin.x(), object_ray.direction.x());
         let (ytmin, ytmax) = check_axis(object_ray.origin.y(), object_ray.direction.y());
         let (ztmin, ztmax) = check_axis(object_ray.origin.z(), object_ray.direction.z());
@@ -93,7 +95,7 @@ mod tests {
                 $(
                     #[test]
                     fn $name() {
-                        let (origin, direction, expected) = $value;
+                        let (origin, direction, expected): (_, _, Vec<f64>) = $value;
 
                         let c: Cube = Default::default();
                         let r = Ray::new(origin, direction);
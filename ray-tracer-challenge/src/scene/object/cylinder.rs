@@ -2,6 +2,7 @@ use crate::{
     draw::color::Color,
     math::{point::Point3d, vector::NormalizedVec3d},
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::{bounded::Bounds, Object, PhysicalObject};
@@ -95,6 +96,7 @@ impl Object for Cylinder {
         &self,
         object_ray: &Ray,
     ) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        stats::count(Counter::CylinderTests);
         let a = object_ray.direction.x().powi(2) + object_ray.direction.z().powi(2);
 
         // Ray is parallel to the y-axis
@@ -162,7 +164,7 @@ mod tests {
                 $(
                     #[test]
                     fn $name() {
-                        let (origin, direction, expected) = $value;
+                        let (origin, direction, expected): (_, _, Vec<f64>) = $value;
                         let cyl: Cylinder = Default::default();
                         let nd = direction.norm().unwrap();
                         let r = Ray::new(origin, nd);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::{
     draw::color::Color,
     math::vector::NormalizedVec3d,
@@ -22,3 +24,21 @@ impl<T: Object + ?Sized> Object for Box<T> {
         (**self).bounds()
     }
 }
+
+/// Lets many objects, such as `Transformed` instances, share one child
+impl<T: Object + ?Sized> Object for Arc<T> {
+    fn material(&self) -> &Material {
+        (**self).material()
+    }
+
+    fn intersect(
+        &self,
+        object_ray: &Ray,
+    ) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        (**self).intersect(object_ray)
+    }
+
+    fn bounds(&self) -> Bounds {
+        (**self).bounds()
+    }
+}
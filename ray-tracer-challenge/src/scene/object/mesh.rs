@@ -0,0 +1,291 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{
+    draw::color::Color,
+    math::{
+        point::Point3d,
+        vector::{NormalizedVec3d, Vec3d},
+    },
+    scene::{
+        intersect::{self, Intersection},
+        material::Material,
+        ray::Ray,
+    },
+};
+
+use super::{bounded::Bounds, bvh::Bvh, triangle, Object};
+
+/// The most faces kept in one leaf of a mesh's `Bvh`
+const MAX_LEAF_FACES: usize = 4;
+
+/// A triangle mesh whose faces index into shared lists of vertices, normals
+/// and texture coordinates, rather than each owning copies like `Triangle`.
+/// The faces are sorted into their own `Bvh`, and share one material.
+/// Intersections are with the mesh as a whole, so a closed mesh acts as a
+/// single object when refracting.
+pub struct Mesh {
+    vertices: Vec<Point3d>,
+    normals: Vec<Vec3d>,
+    texture_coordinates: Vec<(f64, f64)>,
+    faces: Bvh<MeshFace>,
+    material: Material,
+}
+
+/// The indices of a triangle's corners into a mesh's lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshFace {
+    pub vertices: [u32; 3],
+    /// Smooth faces interpolate the normals at their corners
+    pub normals: Option<[u32; 3]>,
+    pub texture_coordinates: Option<[u32; 3]>,
+}
+
+/// A face referring to a vertex, normal or texture coordinate which isn't in
+/// the mesh's lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFace(pub MeshFace);
+
+impl Display for InvalidFace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mesh face {:?} refers to data outside the mesh", self.0)
+    }
+}
+
+impl Error for InvalidFace {}
+
+impl Mesh {
+    /// Creates a mesh, dropping faces with no area
+    pub fn new(
+        vertices: Vec<Point3d>,
+        normals: Vec<Vec3d>,
+        texture_coordinates: Vec<(f64, f64)>,
+        faces: Vec<MeshFace>,
+        material: Material,
+    ) -> Result<Self, InvalidFace> {
+        let in_range = |indices: &[u32; 3], len: usize| indices.iter().all(|&i| (i as usize) < len);
+        if let Some(face) = faces.iter().find(|face| {
+            !(in_range(&face.vertices, vertices.len())
+                && face
+                    .normals
+                    .as_ref()
+                    .is_none_or(|n| in_range(n, normals.len()))
+                && face
+                    .texture_coordinates
+                    .as_ref()
+                    .is_none_or(|t| in_range(t, texture_coordinates.len())))
+        }) {
+            return Err(InvalidFace(face.clone()));
+        }
+
+        let faces = faces
+            .into_iter()
+            .filter(|f| {
+                let [p1, p2, p3] = f.vertices.map(|i| &vertices[i as usize]);
+                (p2 - p1).cross(&(p3 - p1)).mag() > 0.0
+            })
+            .collect();
+        let faces = Bvh::with_bounds(
+            faces,
+            |f| {
+                let corners = f.vertices.map(|i| &vertices[i as usize]);
+                Bounds::from_points(&corners).expect("face should have corners")
+            },
+            MAX_LEAF_FACES,
+        );
+
+        Ok(Mesh {
+            vertices,
+            normals,
+            texture_coordinates,
+            faces,
+            material,
+        })
+    }
+
+    pub fn faces(&self) -> &[MeshFace] {
+        self.faces.children()
+    }
+
+    pub fn texture_coordinates(&self) -> &[(f64, f64)] {
+        &self.texture_coordinates
+    }
+
+    fn corners(&self, face: &MeshFace) -> [&Point3d; 3] {
+        face.vertices.map(|i| &self.vertices[i as usize])
+    }
+
+    /// Intersects one face the same way `Triangle` does
+    fn intersect_face(
+        &self,
+        face: &MeshFace,
+        ray: &Ray,
+    ) -> Option<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        let [p1, p2, p3] = self.corners(face);
+        let edges = [p2 - p1, p3 - p1];
+        let (t, u, v) = triangle::intersect(ray, p1, &edges)?;
+
+        let p = ray.position(t);
+        let color = self.material.surface.color_at(&p);
+        let normal = match &face.normals {
+            Some(ns) => triangle::interpolate_normal(ns.map(|i| &self.normals[i as usize]), u, v),
+            None => triangle::flat_normal(&edges),
+        };
+
+        Some(Intersection::new(t, self as &dyn Object, color, normal))
+    }
+}
+
+impl Object for Mesh {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        let mut intersections = Vec::new();
+        self.faces.visit_leaves(ray, |leaf| {
+            intersections.extend(leaf.iter().filter_map(|f| self.intersect_face(f, ray)));
+        });
+        intersect::sort(&mut intersections);
+        intersections
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.faces.root_bounds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::object::{bounded::Bounded, group::Group, triangle::Triangle};
+
+    use super::*;
+
+    /// A grid of squares in the XY plane, split into triangles
+    fn grid(size: u32) -> (Vec<Point3d>, Vec<MeshFace>) {
+        let mut vertices = Vec::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                vertices.push(Point3d::new(x as f64, y as f64, 0.0));
+            }
+        }
+        let index = |x: u32, y: u32| y * (size + 1) + x;
+        let face = |vertices| MeshFace {
+            vertices,
+            normals: None,
+            texture_coordinates: None,
+        };
+        let mut faces = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                faces.push(face([index(x, y), index(x + 1, y), index(x + 1, y + 1)]));
+                faces.push(face([index(x, y), index(x + 1, y + 1), index(x, y + 1)]));
+            }
+        }
+        (vertices, faces)
+    }
+
+    #[test]
+    fn intersecting_a_mesh_matches_its_separate_triangles() {
+        let (vertices, faces) = grid(4);
+        let triangles = Bounded::new(Group::new(
+            faces
+                .iter()
+                .map(|f| {
+                    let points = f.vertices.map(|i| vertices[i as usize].clone());
+                    Triangle::flat(points, Default::default())
+                })
+                .collect(),
+        ));
+        let mesh = Mesh::new(vertices, vec![], vec![], faces, Default::default()).unwrap();
+
+        for (x, y) in [(0.3, 0.1), (1.9, 3.2), (2.5, 2.5), (3.99, 0.01), (4.5, 1.0)] {
+            let ray = Ray::new(Point3d::new(x, y, -2.0), Vec3d::new(0.1, 0.0, 1.0));
+
+            let from_mesh = mesh.intersect(&ray);
+            let from_triangles = triangles.intersect(&ray);
+
+            assert_eq!(from_mesh.len(), from_triangles.len());
+            for (m, t) in from_mesh.iter().zip(&from_triangles) {
+                assert_eq!(m.t(), t.t());
+                assert_eq!(m.normal, t.normal);
+                assert!(std::ptr::addr_eq(*m.object(), &mesh));
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_faces_interpolate_vertex_normals() {
+        let mesh = Mesh::new(
+            vec![
+                Point3d::new(0.0, 1.0, 0.0),
+                Point3d::new(-1.0, 0.0, 0.0),
+                Point3d::new(1.0, 0.0, 0.0),
+            ],
+            vec![
+                Vec3d::new(0.0, 1.0, 0.0),
+                Vec3d::new(-1.0, 0.0, 0.0),
+                Vec3d::new(1.0, 0.0, 0.0),
+            ],
+            vec![],
+            vec![MeshFace {
+                vertices: [0, 1, 2],
+                normals: Some([0, 1, 2]),
+                texture_coordinates: None,
+            }],
+            Default::default(),
+        )
+        .unwrap();
+        let r = Ray::new(Point3d::new(-0.2, 0.3, -2.0), Vec3d::new(0.0, 0.0, 1.0));
+
+        let is = mesh.intersect(&r);
+
+        crate::math::vector::test_utils::assert_vec_approx_equals(
+            &is[0].normal,
+            &Vec3d::new(-0.55470, 0.83205, 0.0),
+        );
+    }
+
+    #[test]
+    fn faces_with_no_area_are_dropped() {
+        let (vertices, mut faces) = grid(1);
+        faces.push(MeshFace {
+            vertices: [0, 1, 1],
+            normals: None,
+            texture_coordinates: None,
+        });
+
+        let mesh = Mesh::new(vertices, vec![], vec![], faces, Default::default()).unwrap();
+
+        assert_eq!(mesh.faces().len(), 2);
+        assert_eq!(
+            mesh.bounds(),
+            Bounds {
+                minimum: Point3d::new(0.0, 0.0, 0.0),
+                maximum: Point3d::new(1.0, 1.0, 0.0)
+            }
+        );
+    }
+
+    #[test]
+    fn faces_must_refer_to_existing_vertices() {
+        let (vertices, _) = grid(1);
+        let face = MeshFace {
+            vertices: [0, 1, 9],
+            normals: None,
+            texture_coordinates: None,
+        };
+
+        let result = Mesh::new(
+            vertices,
+            vec![],
+            vec![],
+            vec![face.clone()],
+            Default::default(),
+        );
+
+        assert_eq!(result.err(), Some(InvalidFace(face)));
+    }
+}
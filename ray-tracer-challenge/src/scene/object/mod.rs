@@ -32,12 +32,14 @@ fn build_basic_intersection<'a, T: PhysicalObject>(
 }
 
 pub mod bounded;
+pub mod bvh;
 pub mod cone;
 pub mod csg;
 pub mod cube;
 pub mod cylinder;
 pub mod group;
 pub mod impls;
+pub mod mesh;
 pub mod plane;
 pub mod sphere;
 pub mod transformed;
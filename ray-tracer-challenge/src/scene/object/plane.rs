@@ -2,6 +2,7 @@ use crate::{
     draw::color::Color,
     math::{point::Point3d, vector::NormalizedVec3d},
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::{bounded::Bounds, Object, PhysicalObject};
@@ -27,6 +28,7 @@ impl Object for Plane {
         &self,
         object_ray: &Ray,
     ) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+        stats::count(Counter::PlaneTests);
         // If ray y direction is 0 (epsilon comparison cause floating point)
         if f64::abs(object_ray.direction.y()) < 1e-8 {
             Vec::new()
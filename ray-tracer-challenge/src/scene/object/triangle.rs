@@ -7,6 +7,7 @@ use crate::{
         vector::{NormalizedVec3d, Vec3d},
     },
     scene::{intersect::Intersection, material::Material, ray::Ray},
+    stats::{self, Counter},
 };
 
 use super::{bounded::Bounds, Object};
@@ -23,19 +24,21 @@ pub struct Triangle {
     points: [Point3d; 3],
     edges: [Vec3d; 2],
     normal: TriangleNormal,
+    /// (u, v) texture coordinates at each point
+    texture_coordinates: Option<[(f64, f64); 3]>,
     material: Material,
 }
 
 impl Triangle {
     pub fn flat(points: [Point3d; 3], material: Material) -> Self {
-        let e1 = &points[1] - &points[0];
-        let e2 = &points[2] - &points[0];
-        let normal = NormalizedVec3d::try_from(e2.cross(&e1)).unwrap();
+        let edges = [&points[1] - &points[0], &points[2] - &points[0]];
+        let normal = flat_normal(&edges);
 
         Triangle {
             points,
-            edges: [e1, e2],
+            edges,
             normal: TriangleNormal::Flat(normal),
+            texture_coordinates: None,
             material,
         }
     }
@@ -50,9 +53,36 @@ impl Triangle {
             points: [p1, p2, p3],
             edges: [e1, e2],
             normal: TriangleNormal::Smooth([v1, v2, v3]),
+            texture_coordinates: None,
             material,
         }
     }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_texture_coordinates(mut self, texture_coordinates: [(f64, f64); 3]) -> Self {
+        self.texture_coordinates = Some(texture_coordinates);
+        self
+    }
+
+    pub fn texture_coordinates(&self) -> Option<&[(f64, f64); 3]> {
+        self.texture_coordinates.as_ref()
+    }
+
+    /// Interpolates the texture coordinates at a point on the triangle
+    pub fn texture_coordinates_at(&self, point: &Point3d) -> Option<(f64, f64)> {
+        let [t1, t2, t3] = self.texture_coordinates.as_ref()?;
+        let (u, v) = barycentric(point, &self.points[0], &self.edges);
+        let w = 1.0 - u - v;
+
+        Some((
+            w * t1.0 + u * t2.0 + v * t3.0,
+            w * t1.1 + u * t2.1 + v * t3.1,
+        ))
+    }
 }
 
 impl Object for Triangle {
@@ -61,43 +91,18 @@ impl Object for Triangle {
     }
 
     fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
-        let dir_cross_e2 = ray.direction.cross(&self.edges[1]);
-        let det = self.edges[0].dot(&dir_cross_e2);
-
-        if det.abs() < EPSILON {
-            vec![]
-        } else {
-            let f = 1.0 / det;
-            let p1_to_origin = &ray.origin - &self.points[0];
-            let u = f * p1_to_origin.dot(&dir_cross_e2);
-
-            if !(0.0..=1.0).contains(&u) {
-                vec![]
-            } else {
-                let origin_cross_e1 = p1_to_origin.cross(&self.edges[0]);
-                let v = f * ray.direction.dot(&origin_cross_e1);
-
-                if v < 0.0 || (u + v) > 1.0 {
-                    vec![]
-                } else {
-                    let t = f * self.edges[1].dot(&origin_cross_e1);
-
-                    let p = ray.position(t);
-                    let color = self.material().surface.color_at(&p);
-                    let normal = match &self.normal {
-                        TriangleNormal::Flat(n) => n.clone(),
-                        TriangleNormal::Smooth([v1, v2, v3]) => NormalizedVec3d::try_from(
-                            &(&(v2 * u) + &(v3 * v)) + &(v1 * (1.0 - u - v)),
-                        )
-                        .unwrap(),
-                    };
-
-                    let i = Intersection::new(t, self as &dyn Object, color, normal);
-
-                    vec![i]
-                }
-            }
-        }
+        let Some((t, u, v)) = intersect(ray, &self.points[0], &self.edges) else {
+            return vec![];
+        };
+
+        let p = ray.position(t);
+        let color = self.material().surface.color_at(&p);
+        let normal = match &self.normal {
+            TriangleNormal::Flat(n) => n.clone(),
+            TriangleNormal::Smooth(normals) => interpolate_normal(normals.each_ref(), u, v),
+        };
+
+        vec![Intersection::new(t, self as &dyn Object, color, normal)]
     }
 
     fn bounds(&self) -> Bounds {
@@ -105,12 +110,67 @@ impl Object for Triangle {
     }
 }
 
+/// Where a ray hits the triangle with corner `p1` and edges `e1` and `e2`
+/// leading to the other two corners, as the distance along the ray and the
+/// weights `(u, v)` of the second and third corners. `Mesh` shares this with
+/// `Triangle`, so that the two intersect identically.
+pub(super) fn intersect(ray: &Ray, p1: &Point3d, [e1, e2]: &[Vec3d; 2]) -> Option<(f64, f64, f64)> {
+    stats::count(Counter::TriangleTests);
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(&dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = &ray.origin - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+/// The weights `(u, v)` of the second and third corners at a point on the
+/// triangle with corner `p1` and edges `e1` and `e2`, as `intersect` returns
+/// them. Points off the triangle's plane are projected onto it.
+pub(crate) fn barycentric(point: &Point3d, p1: &Point3d, [e1, e2]: &[Vec3d; 2]) -> (f64, f64) {
+    let to_point = point - p1;
+
+    let (d11, d12, d22) = (e1.dot(e1), e1.dot(e2), e2.dot(e2));
+    let (dp1, dp2) = (to_point.dot(e1), to_point.dot(e2));
+    let denominator = d11 * d22 - d12 * d12;
+    (
+        (d22 * dp1 - d12 * dp2) / denominator,
+        (d11 * dp2 - d12 * dp1) / denominator,
+    )
+}
+
+/// The normal of a flat triangle with edges `e1` and `e2`
+pub(super) fn flat_normal([e1, e2]: &[Vec3d; 2]) -> NormalizedVec3d {
+    NormalizedVec3d::try_from(e2.cross(e1)).unwrap()
+}
+
+/// Blends the normals at a smooth triangle's corners, weighting the second
+/// and third by `u` and `v`
+pub(super) fn interpolate_normal([n1, n2, n3]: [&Vec3d; 3], u: f64, v: f64) -> NormalizedVec3d {
+    NormalizedVec3d::try_from(&(&(n2 * u) + &(n3 * v)) + &(n1 * (1.0 - u - v))).unwrap()
+}
+
 impl Debug for Triangle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Triangle")
             .field("points", &self.points)
             .field("edges", &self.edges)
             .field("normal", &self.normal)
+            .field("texture_coordinates", &self.texture_coordinates)
             .field("material", &format!("{:p}", &self.material))
             .finish()
     }
@@ -251,6 +311,34 @@ mod tests {
         }
     }
 
+    mod texture_coordinates {
+        use super::*;
+
+        #[test]
+        fn texture_coordinates_are_interpolated_across_the_triangle() {
+            let t = Triangle::flat(
+                [
+                    Point3d::new(0.0, 0.0, 0.0),
+                    Point3d::new(2.0, 0.0, 0.0),
+                    Point3d::new(0.0, 2.0, 0.0),
+                ],
+                Default::default(),
+            );
+            assert_eq!(t.texture_coordinates_at(&Point3d::new(0.5, 0.5, 0.0)), None);
+
+            let t = t.with_texture_coordinates([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+
+            assert_eq!(
+                t.texture_coordinates_at(&Point3d::new(0.0, 0.0, 0.0)),
+                Some((0.0, 0.0))
+            );
+            assert_eq!(
+                t.texture_coordinates_at(&Point3d::new(1.0, 0.5, 0.0)),
+                Some((0.5, 0.25))
+            );
+        }
+    }
+
     mod bounds {
         use super::*;
 
@@ -0,0 +1,134 @@
+use crate::draw::{
+    canvas::Canvas,
+    color::{self, Color},
+};
+
+/// The color seen along a ray, split by how the light got there, so that the
+/// parts can be rebalanced when compositing. The passes add up to the color
+/// from `World::color_at`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightingPasses {
+    /// Phong terms at the first surface hit, summed over all lights
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    /// Everything seen in reflections of the first surface hit
+    pub reflection: Color,
+    /// Everything seen through the first surface hit
+    pub refraction: Color,
+    /// `World::void_color`, where nothing is hit
+    pub background: Color,
+    /// Ambient, diffuse and specular terms at the first surface hit from each
+    /// of `World::lights`, in order. These add up to `ambient + diffuse + specular`.
+    pub lights: Vec<Color>,
+}
+
+impl LightingPasses {
+    pub fn new(light_count: usize) -> Self {
+        LightingPasses {
+            ambient: color::black(),
+            diffuse: color::black(),
+            specular: color::black(),
+            reflection: color::black(),
+            refraction: color::black(),
+            background: color::black(),
+            lights: vec![color::black(); light_count],
+        }
+    }
+
+    pub fn total(&self) -> Color {
+        [
+            &self.diffuse,
+            &self.specular,
+            &self.reflection,
+            &self.refraction,
+            &self.background,
+        ]
+        .into_iter()
+        .fold(self.ambient.clone(), |acc, c| &acc + c)
+    }
+
+    fn map(&self, other: &Self, f: impl Fn(&Color, &Color) -> Color) -> Self {
+        LightingPasses {
+            ambient: f(&self.ambient, &other.ambient),
+            diffuse: f(&self.diffuse, &other.diffuse),
+            specular: f(&self.specular, &other.specular),
+            reflection: f(&self.reflection, &other.reflection),
+            refraction: f(&self.refraction, &other.refraction),
+            background: f(&self.background, &other.background),
+            lights: self
+                .lights
+                .iter()
+                .zip(other.lights.iter())
+                .map(|(a, b)| f(a, b))
+                .collect(),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        self.map(other, |a, b| a + b)
+    }
+
+    pub fn scale(&self, factor: f64) -> Self {
+        self.map(self, |a, _| a * factor)
+    }
+}
+
+/// A rendered image for each of the `LightingPasses`
+pub struct RenderPasses {
+    pub ambient: Canvas,
+    pub diffuse: Canvas,
+    pub specular: Canvas,
+    pub reflection: Canvas,
+    pub refraction: Canvas,
+    pub background: Canvas,
+    pub lights: Vec<Canvas>,
+}
+
+impl RenderPasses {
+    pub fn new(width: usize, height: usize, light_count: usize) -> Self {
+        RenderPasses {
+            ambient: Canvas::new(width, height),
+            diffuse: Canvas::new(width, height),
+            specular: Canvas::new(width, height),
+            reflection: Canvas::new(width, height),
+            refraction: Canvas::new(width, height),
+            background: Canvas::new(width, height),
+            lights: (0..light_count)
+                .map(|_| Canvas::new(width, height))
+                .collect(),
+        }
+    }
+
+    pub fn write(&mut self, coords: (usize, usize), passes: LightingPasses) {
+        self.ambient.write(coords, passes.ambient);
+        self.diffuse.write(coords, passes.diffuse);
+        self.specular.write(coords, passes.specular);
+        self.reflection.write(coords, passes.reflection);
+        self.refraction.write(coords, passes.refraction);
+        self.background.write(coords, passes.background);
+        for (canvas, color) in self.lights.iter_mut().zip(passes.lights) {
+            canvas.write(coords, color);
+        }
+    }
+
+    /// Returns each pass along with a name for it, with the per-light passes
+    /// named `light-0`, `light-1` and so on
+    pub fn named(&self) -> Vec<(String, &Canvas)> {
+        let mut passes = vec![
+            ("ambient".to_string(), &self.ambient),
+            ("diffuse".to_string(), &self.diffuse),
+            ("specular".to_string(), &self.specular),
+            ("reflection".to_string(), &self.reflection),
+            ("refraction".to_string(), &self.refraction),
+            ("background".to_string(), &self.background),
+        ];
+        passes.extend(
+            self.lights
+                .iter()
+                .enumerate()
+                .map(|(i, canvas)| (format!("light-{}", i), canvas)),
+        );
+        passes
+    }
+}
@@ -15,6 +15,7 @@ pub mod checker3d;
 pub mod gradient;
 pub mod ring;
 pub mod stripe;
+pub mod vertex_colors;
 
 #[cfg(test)]
 pub mod test_utils {
@@ -0,0 +1,77 @@
+use crate::{
+    draw::color::Color,
+    math::{matrix::InvertibleMatrix, point::Point3d, vector::Vec3d},
+    scene::object::triangle,
+};
+
+use super::Pattern;
+
+/// A pattern blending between a color at each corner of a triangle, by the
+/// barycentric coordinates of the point. Points off the triangle's plane are
+/// projected onto it.
+pub struct VertexColors {
+    /// The first corner, and the edges from it to the other two
+    p1: Point3d,
+    edges: [Vec3d; 2],
+    colors: [Color; 3],
+    pub transform: InvertibleMatrix<4>,
+}
+
+impl VertexColors {
+    /// The points mustn't all lie on a line
+    pub fn new(points: [Point3d; 3], colors: [Color; 3]) -> Self {
+        let [p1, p2, p3] = points;
+        VertexColors {
+            edges: [&p2 - &p1, &p3 - &p1],
+            p1,
+            colors,
+            transform: Default::default(),
+        }
+    }
+}
+
+impl Pattern for VertexColors {
+    fn transform(&self) -> &InvertibleMatrix<4> {
+        &self.transform
+    }
+
+    fn at_local(&self, point: &Point3d) -> Color {
+        let (u, v) = triangle::barycentric(point, &self.p1, &self.edges);
+        let [c1, c2, c3] = &self.colors;
+        &(&(c1 * (1.0 - u - v)) + &(c2 * u)) + &(c3 * v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_blend_between_the_corners() {
+        let pattern = VertexColors::new(
+            [
+                Point3d::new(0.0, 0.0, 0.0),
+                Point3d::new(1.0, 0.0, 0.0),
+                Point3d::new(0.0, 1.0, 0.0),
+            ],
+            [
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+            ],
+        );
+
+        assert_eq!(
+            pattern.at_local(&Point3d::new(0.0, 1.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            pattern.at_local(&Point3d::new(0.5, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.0)
+        );
+        assert_eq!(
+            pattern.at_local(&Point3d::new(0.25, 0.25, 3.0)),
+            Color::new(0.5, 0.25, 0.25)
+        );
+    }
+}
@@ -13,16 +13,20 @@ use crate::{
         material::{Material, Surface},
         transformation,
     },
+    stats::{self, Counter},
 };
 
 use super::{
     intersect::{self, Intersection, Precomputation},
     light::PointLight,
-    material::lighting,
+    material::{lighting, shading},
     object::{sphere::Sphere, transformed::Transformed, Object},
+    passes::LightingPasses,
     ray::Ray,
 };
 
+pub type WorldIntersection<'a> = Intersection<&'a dyn Object, Color, NormalizedVec3d>;
+
 pub struct World {
     pub objects: Vec<Box<dyn Object>>,
     pub lights: Vec<PointLight>,
@@ -43,7 +47,7 @@ impl World {
         }
     }
 
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection<&dyn Object, Color, NormalizedVec3d>> {
+    pub(super) fn intersect(&self, ray: &Ray) -> Vec<WorldIntersection<'_>> {
         let mut intersections = self
             .objects
             .iter()
@@ -54,6 +58,17 @@ impl World {
         intersections
     }
 
+    /// Returns the nearest intersection in front of the ray's origin, along with
+    /// the index of the top-level object it belongs to
+    pub fn first_hit(&self, ray: &Ray) -> Option<(usize, WorldIntersection<'_>)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .flat_map(|(index, object)| object.intersect(ray).into_iter().map(move |i| (index, i)))
+            .filter(|(_, i)| i.t() >= 0.0)
+            .min_by(|(_, a), (_, b)| a.t().total_cmp(&b.t()))
+    }
+
     fn shade_hit(&self, comps: &Precomputation<&dyn Object>, remaining: usize) -> Option<Color> {
         self.lights
             .iter()
@@ -86,7 +101,7 @@ impl World {
             .reduce(|acc, c| &acc + &c)
     }
 
-    fn color_at_internal(&self, ray: &Ray, remaining: usize) -> Color {
+    pub(super) fn color_at_internal(&self, ray: &Ray, remaining: usize) -> Color {
         let xs = self.intersect(ray);
 
         intersect::hit(&xs)
@@ -98,16 +113,65 @@ impl World {
     }
 
     pub fn color_at(&self, ray: &Ray) -> Color {
+        stats::count(Counter::PrimaryRays);
         self.color_at_internal(ray, self.max_reflection_depth)
     }
 
-    fn shadow_attenuation(&self, point: &Point3d, light: &PointLight) -> f64 {
+    /// Like `color_at`, with the color split into `LightingPasses`
+    pub fn passes_at(&self, ray: &Ray) -> LightingPasses {
+        stats::count(Counter::PrimaryRays);
+        let mut passes = LightingPasses::new(self.lights.len());
+        let xs = self.intersect(ray);
+        let comps = match intersect::hit(&xs) {
+            Some(h) if !self.lights.is_empty() => h.prepare_computations(ray, &xs),
+            _ => {
+                passes.background = self.void_color.clone();
+                return passes;
+            }
+        };
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let shading = shading(
+                comps.object.material(),
+                &comps.point,
+                &comps.object_color,
+                light,
+                &comps.eye_v,
+                &comps.normal_v,
+                self.shadow_attenuation(&comps.over_point, light),
+            );
+            passes.lights[i] = shading.total();
+            passes.ambient = &passes.ambient + &shading.ambient;
+            passes.diffuse = &passes.diffuse + &shading.diffuse;
+            passes.specular = &passes.specular + &shading.specular;
+        }
+
+        let remaining = self.max_reflection_depth;
+        let reflected = self.reflected_color(&comps, remaining);
+        let refracted = self.refracted_color(&comps, remaining);
+        let m = comps.object.material();
+        let (reflected, refracted) = if m.reflectivity > 0.0 && m.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            (&reflected * reflectance, &refracted * (1.0 - reflectance))
+        } else {
+            (reflected, refracted)
+        };
+        // shade_hit adds the reflected and refracted colors once for each light
+        let light_count = self.lights.len() as f64;
+        passes.reflection = &reflected * light_count;
+        passes.refraction = &refracted * light_count;
+
+        passes
+    }
+
+    pub(super) fn shadow_attenuation(&self, point: &Point3d, light: &PointLight) -> f64 {
         let v = &light.position - point;
         let distance = v.mag();
         let direction = v.norm();
 
         direction
             .map(|d| {
+                stats::count(Counter::ShadowRays);
                 let r = Ray {
                     origin: point.clone(),
                     direction: d,
@@ -129,6 +193,7 @@ impl World {
         if remaining == 0 || comps.object.material().reflectivity == 0.0 {
             color::black()
         } else {
+            stats::count(Counter::ReflectionRays);
             let reflect_ray = Ray::new(comps.over_point.clone(), (*comps.reflect_v).clone());
             let color = self.color_at_internal(&reflect_ray, remaining - 1);
             &color * comps.object.material().reflectivity
@@ -151,6 +216,7 @@ impl World {
                 let cos_t = f64::sqrt(1.0 - sin2_t);
                 let direction =
                     &(&*comps.normal_v * (n_ratio * cos_i - cos_t)) - &(&*comps.eye_v * n_ratio);
+                stats::count(Counter::RefractionRays);
                 let refract_ray = Ray::new(comps.under_point.clone(), direction);
                 &self.color_at_internal(&refract_ray, remaining - 1)
                     * comps.object.material().transparency
@@ -236,6 +302,34 @@ mod tests {
         assert_eq!(xs[3].t(), 6.0);
     }
 
+    #[test]
+    fn color_at_counts_rays_and_intersection_tests() {
+        let w = World::basic();
+        let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+        let _counting = stats::test_utils::counting();
+        let before = stats::snapshot();
+
+        w.color_at(&r);
+
+        // Other tests may be counting at the same time, so only lower bounds can be checked
+        let counts = stats::snapshot().since(&before);
+        assert!(counts.get(Counter::PrimaryRays) >= 1);
+        assert!(counts.get(Counter::ShadowRays) >= 1);
+        assert!(counts.get(Counter::SphereTests) >= 4);
+    }
+
+    #[test]
+    fn first_hit_reports_the_top_level_object() {
+        let w = World::basic();
+        let from_inside = Ray::new(Point3d::new(0.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 1.0));
+        let from_outside = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+
+        let (index, hit) = w.first_hit(&from_inside).unwrap();
+        assert_eq!((index, hit.t()), (1, 0.5));
+        let (index, hit) = w.first_hit(&from_outside).unwrap();
+        assert_eq!((index, hit.t()), (0, 4.0));
+    }
+
     #[test]
     fn shading_an_intersection() {
         let w = World::basic();
@@ -746,4 +840,84 @@ mod tests {
             &Color::new(1.11500, 0.69643, 0.69243),
         );
     }
+
+    mod passes {
+        use super::*;
+
+        #[test]
+        fn passes_add_up_to_the_color() {
+            let mut w = World::basic();
+            let floor = Transformed::new(
+                Plane {
+                    material: Material {
+                        transparency: 0.5,
+                        refractive_index: 1.5,
+                        reflectivity: 0.5,
+                        ..Default::default()
+                    },
+                },
+                InvertibleMatrix::try_from(transformation::translation(0.0, -1.0, 0.0)).unwrap(),
+            );
+            w.objects.push(Box::new(floor));
+            // Reflections of the sky show up in the reflection pass
+            w.void_color = Color::new(0.2, 0.3, 0.4);
+            w.lights.push(PointLight {
+                position: Point3d::new(10.0, 10.0, -10.0),
+                intensity: Color::new(0.5, 0.5, 0.5),
+            });
+            let sqrt2 = std::f64::consts::SQRT_2;
+            let r = Ray::new(
+                Point3d::new(0.0, 0.0, -3.0),
+                Vec3d::new(0.0, -sqrt2 / 2.0, sqrt2 / 2.0),
+            );
+
+            let passes = w.passes_at(&r);
+
+            color::test_utils::assert_colors_approx_equal(&passes.total(), &w.color_at(&r));
+            assert_ne!(passes.reflection, color::black());
+            assert_ne!(passes.refraction, color::black());
+            assert_eq!(passes.background, color::black());
+            color::test_utils::assert_colors_approx_equal(
+                &(&passes.lights[0] + &passes.lights[1]),
+                &(&(&passes.ambient + &passes.diffuse) + &passes.specular),
+            );
+        }
+
+        #[test]
+        fn each_light_has_its_own_pass() {
+            let mut w = World::basic();
+            let dim_light = PointLight {
+                intensity: Color::new(0.5, 0.5, 0.5),
+                ..w.lights[0].clone()
+            };
+            w.lights.push(dim_light);
+            let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 0.0, 1.0));
+
+            let passes = w.passes_at(&r);
+
+            color::test_utils::assert_colors_approx_equal(
+                &passes.lights[0],
+                &Color::new(0.38066, 0.47583, 0.2855),
+            );
+            color::test_utils::assert_colors_approx_equal(
+                &passes.lights[1],
+                &Color::new(0.19033, 0.237915, 0.14275),
+            );
+        }
+
+        #[test]
+        fn a_miss_only_has_background() {
+            let w = World {
+                void_color: Color::new(0.2, 0.3, 0.4),
+                ..World::basic()
+            };
+            let r = Ray::new(Point3d::new(0.0, 0.0, -5.0), Vec3d::new(0.0, 1.0, 0.0));
+
+            let passes = w.passes_at(&r);
+
+            assert_eq!(passes.background, Color::new(0.2, 0.3, 0.4));
+            assert_eq!(passes.total(), Color::new(0.2, 0.3, 0.4));
+            assert_eq!(passes.lights, vec![color::black()]);
+        }
+    }
 }
@@ -0,0 +1,267 @@
+//! Ray and intersection test counts, printed by `draw_scene --stats`. Counting
+//! is off until `enable` is called, after which `snapshot` totals the counts
+//! kept by each rendering thread.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+const COUNTER_COUNT: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    PrimaryRays,
+    ShadowRays,
+    ReflectionRays,
+    RefractionRays,
+    SphereTests,
+    PlaneTests,
+    CubeTests,
+    CylinderTests,
+    ConeTests,
+    TriangleTests,
+    /// Bounding boxes tested by `Bounded` objects
+    BoundedNodesVisited,
+}
+
+impl Counter {
+    pub const ALL: [Counter; COUNTER_COUNT] = [
+        Counter::PrimaryRays,
+        Counter::ShadowRays,
+        Counter::ReflectionRays,
+        Counter::RefractionRays,
+        Counter::SphereTests,
+        Counter::PlaneTests,
+        Counter::CubeTests,
+        Counter::CylinderTests,
+        Counter::ConeTests,
+        Counter::TriangleTests,
+        Counter::BoundedNodesVisited,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::PrimaryRays => "Primary",
+            Counter::ShadowRays => "Shadow",
+            Counter::ReflectionRays => "Reflection",
+            Counter::RefractionRays => "Refraction",
+            Counter::SphereTests => "Sphere",
+            Counter::PlaneTests => "Plane",
+            Counter::CubeTests => "Cube",
+            Counter::CylinderTests => "Cylinder",
+            Counter::ConeTests => "Cone",
+            Counter::TriangleTests => "Triangle",
+            Counter::BoundedNodesVisited => "Bounded nodes visited",
+        }
+    }
+
+    fn is_ray(&self) -> bool {
+        matches!(
+            self,
+            Counter::PrimaryRays
+                | Counter::ShadowRays
+                | Counter::ReflectionRays
+                | Counter::RefractionRays
+        )
+    }
+
+    fn is_intersection_test(&self) -> bool {
+        !self.is_ray() && *self != Counter::BoundedNodesVisited
+    }
+}
+
+type Counters = [AtomicU64; COUNTER_COUNT];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Counters are per thread, as every thread counting into one set slows
+/// rendering down noticeably
+static THREAD_COUNTERS: Mutex<Vec<Arc<Counters>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static COUNTERS: Arc<Counters> = {
+        let counters = Arc::new(Counters::default());
+        THREAD_COUNTERS.lock().unwrap().push(counters.clone());
+        counters
+    };
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn count(counter: Counter) {
+    if ENABLED.load(Ordering::Relaxed) {
+        COUNTERS.with(|c| c[counter as usize].fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+/// Adds up the counts from every thread
+pub fn snapshot() -> Stats {
+    let mut counts = [0; COUNTER_COUNT];
+    for counters in THREAD_COUNTERS.lock().unwrap().iter() {
+        for (total, c) in counts.iter_mut().zip(counters.iter()) {
+            *total += c.load(Ordering::Relaxed);
+        }
+    }
+    Stats { counts }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    counts: [u64; COUNTER_COUNT],
+}
+
+impl Stats {
+    pub fn get(&self, counter: Counter) -> u64 {
+        self.counts[counter as usize]
+    }
+
+    pub fn rays(&self) -> u64 {
+        Counter::ALL
+            .iter()
+            .filter(|c| c.is_ray())
+            .map(|&c| self.get(c))
+            .sum()
+    }
+
+    /// Rays along each path from the camera, counting the primary ray and any
+    /// reflections and refractions but not shadow rays
+    pub fn average_path_depth(&self) -> f64 {
+        let primary = self.get(Counter::PrimaryRays);
+        if primary == 0 {
+            0.0
+        } else {
+            let path_rays =
+                primary + self.get(Counter::ReflectionRays) + self.get(Counter::RefractionRays);
+            path_rays as f64 / primary as f64
+        }
+    }
+
+    /// Returns the counts since `earlier`
+    pub fn since(&self, earlier: &Stats) -> Stats {
+        let mut counts = self.counts;
+        for (count, e) in counts.iter_mut().zip(earlier.counts) {
+            *count = count.saturating_sub(e);
+        }
+        Stats { counts }
+    }
+
+    /// Formats the counts for printing after a render which took `elapsed`
+    pub fn summary(&self, elapsed: Duration) -> String {
+        let mut s = String::from("Render statistics:\n");
+        let rays_per_second = self.rays() as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        s.push_str(&format!(
+            "  Rays: {} ({:.2} M/s)\n",
+            self.rays(),
+            rays_per_second / 1e6
+        ));
+        for c in Counter::ALL.iter().filter(|c| c.is_ray()) {
+            s.push_str(&format!("    {}: {}\n", c.name(), self.get(*c)));
+        }
+        s.push_str(&format!(
+            "  Average path depth: {:.3}\n",
+            self.average_path_depth()
+        ));
+        s.push_str("  Intersection tests:\n");
+        for c in Counter::ALL.iter().filter(|c| c.is_intersection_test()) {
+            s.push_str(&format!("    {}: {}\n", c.name(), self.get(*c)));
+        }
+        s.push_str(&format!(
+            "  {}: {}\n",
+            Counter::BoundedNodesVisited.name(),
+            self.get(Counter::BoundedNodesVisited)
+        ));
+        s
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use std::sync::{Mutex, MutexGuard};
+
+    static COUNTING: Mutex<()> = Mutex::new(());
+
+    /// Keeps counting enabled until dropped. Only one test can hold one at a
+    /// time, so one test can't turn counting off while another needs it.
+    pub struct Counting {
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for Counting {
+        fn drop(&mut self) {
+            super::disable();
+        }
+    }
+
+    pub fn counting() -> Counting {
+        // A failed test holding the lock leaves nothing to clean up
+        let lock = COUNTING.lock().unwrap_or_else(|e| e.into_inner());
+        super::enable();
+        Counting { _lock: lock }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_utils::counting, *};
+
+    fn stats_with(counts: &[(Counter, u64)]) -> Stats {
+        let mut stats = Stats::default();
+        for &(c, n) in counts {
+            stats.counts[c as usize] = n;
+        }
+        stats
+    }
+
+    #[test]
+    fn counts_are_added_up_across_threads() {
+        let _counting = counting();
+        let before = snapshot();
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10 {
+                        count(Counter::ConeTests);
+                    }
+                });
+            }
+        });
+
+        // Other tests may be counting at the same time
+        assert!(snapshot().since(&before).get(Counter::ConeTests) >= 40);
+    }
+
+    #[test]
+    fn average_path_depth_ignores_shadow_rays() {
+        let stats = stats_with(&[
+            (Counter::PrimaryRays, 4),
+            (Counter::ShadowRays, 100),
+            (Counter::ReflectionRays, 2),
+            (Counter::RefractionRays, 4),
+        ]);
+
+        assert_eq!(stats.rays(), 110);
+        assert_eq!(stats.average_path_depth(), 2.5);
+        assert_eq!(Stats::default().average_path_depth(), 0.0);
+    }
+
+    #[test]
+    fn summary_reports_rays_per_second() {
+        let stats = stats_with(&[(Counter::PrimaryRays, 3_000_000), (Counter::SphereTests, 7)]);
+
+        let summary = stats.summary(Duration::from_secs(2));
+
+        assert!(summary.contains("Rays: 3000000 (1.50 M/s)"));
+        assert!(summary.contains("Sphere: 7"));
+    }
+}
@@ -1,9 +1,20 @@
 use std::{fs, time::SystemTime};
 
-use crate::draw::canvas::Canvas;
+use crate::{
+    draw::{canvas::Canvas, tone_map::OutputOpts},
+    scene::{
+        aov::{Aov, AovBuffers},
+        passes::RenderPasses,
+    },
+};
 
 pub fn write_to_file(c: &Canvas, filename_prefix: &str) {
-    let ppm_data = c.ppm();
+    write_to_file_with_opts(c, filename_prefix, &OutputOpts::default())
+}
+
+/// Like `write_to_file`, but with exposure, tone mapping and sRGB encoding applied to the image
+pub fn write_to_file_with_opts(c: &Canvas, filename_prefix: &str, opts: &OutputOpts) {
+    let ppm_data = c.ppm_with_opts(opts);
     let filename = format!(
         "{}-{}.ppm",
         filename_prefix,
@@ -14,3 +25,31 @@ pub fn write_to_file(c: &Canvas, filename_prefix: &str) {
     );
     fs::write(filename, ppm_data).expect("unable to write file")
 }
+
+/// Writes one frame of an image sequence to a numbered file (`<filename_prefix>-0000.ppm` and so on)
+pub fn write_frame_to_file(c: &Canvas, filename_prefix: &str, frame: usize) {
+    let filename = format!("{}-{:04}.ppm", filename_prefix, frame);
+    fs::write(filename, c.ppm()).expect("unable to write file")
+}
+
+/// Writes each AOV pass to its own files: `<filename_prefix>-<pass>.pfm` with
+/// the raw values for compositing, and `<filename_prefix>-<pass>.ppm` with a
+/// preview for viewing
+pub fn write_aovs_to_files(aovs: &AovBuffers, filename_prefix: &str) {
+    for aov in Aov::ALL {
+        let filename = format!("{}-{}", filename_prefix, aov.name());
+        fs::write(format!("{}.pfm", filename), aovs.image(aov).pfm())
+            .expect("unable to write file");
+        fs::write(format!("{}.ppm", filename), aovs.preview(aov).ppm())
+            .expect("unable to write file");
+    }
+}
+
+/// Writes each lighting pass to `<filename_prefix>-<pass>.pfm`, keeping the
+/// full range of each pass so that they can be added back up when compositing
+pub fn write_passes_to_files(passes: &RenderPasses, filename_prefix: &str) {
+    for (name, canvas) in passes.named() {
+        fs::write(format!("{}-{}.pfm", filename_prefix, name), canvas.pfm())
+            .expect("unable to write file");
+    }
+}
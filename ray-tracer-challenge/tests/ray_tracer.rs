@@ -243,7 +243,7 @@ fn test_obj_scene() -> Scene {
     let content = include_str!("../../objs/teapot-low.obj").as_bytes();
 
     let obj = Transformed::new(
-        WavefrontObj::parse(content).to_object(),
+        WavefrontObj::parse(content).unwrap().to_object(),
         InvertibleMatrix::try_from(transformation::sequence(&[transformation::rotation_x(
             -consts::FRAC_PI_2,
         )]))
@@ -1,23 +1,152 @@
 use std::{
-    fs,
-    io::{self, BufWriter},
-    time::SystemTime,
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Cursor},
+    process::{Command, Stdio},
+    thread,
+    time::{Instant, SystemTime},
 };
 
 use ray_tracing_one_weekend::{
-    camera::{Camera, Canvas},
+    animation::CameraPath,
+    camera::{Camera, CameraParams, Canvas},
+    denoise::Denoiser,
+    distributed,
     hittable::{flat::quad::Quad, Hittable},
     material::Material,
-    output,
+    output::{self, OutputOpts},
+    preview::PreviewServer,
+    progress::CancellationToken,
+    stats,
     vec3::{Point3, Vec3},
 };
 
 #[allow(dead_code)]
 pub fn render_to_stdout<M: Material, H: Hittable<Material = M> + Sync>(world: &H, camera: &Camera) {
     let mut out = BufWriter::new(io::stdout().lock());
-    let canvas = camera.render(&world);
+    let canvas = render_with_stats_from_args(world, camera);
+
+    output::output_ppm(&canvas, &mut out).unwrap();
+}
+
+/// Like `render_to_stdout`, but with exposure and tone mapping applied to the image
+#[allow(dead_code)]
+pub fn render_to_stdout_with_opts<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+    opts: &OutputOpts,
+) {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let canvas = render_with_stats_from_args(world, camera);
+
+    output::output_ppm_with_opts(&canvas, opts, &mut out).unwrap();
+}
+
+/// Renders and denoises a scene, writing the image to stdout. Useful for quick
+/// previews with few samples per pixel.
+#[allow(dead_code)]
+pub fn render_denoised_to_stdout<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+    denoiser: &Denoiser,
+) {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let canvas = denoiser.denoise(&render_with_stats_from_args(world, camera));
+
+    output::output_ppm(&canvas, &mut out).unwrap();
+}
+
+/// Renders a scene, handling `--stats` by counting rays and intersection tests
+/// and printing a summary to stderr afterwards
+#[allow(dead_code)]
+fn render_with_stats_from_args<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+) -> Canvas {
+    if !env::args().any(|a| a == "--stats") {
+        return camera.render(world);
+    }
+
+    let start = Instant::now();
+    let (canvas, stats) = stats::measure(|| camera.render(world));
+    eprint!("{}", stats.summary(start.elapsed()));
+    canvas
+}
+
+/// Renders a scene along with its AOV passes, and writes them to an EXR file.
+/// The depth, normal and albedo passes are only written if the camera keeps
+/// first hit features.
+#[allow(dead_code)]
+pub fn render_exr_with_aovs<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+    filename_prefix: &str,
+) {
+    let canvas = camera.render(world);
+    let aovs = camera.render_aovs(world);
+
+    let mut out = Cursor::new(Vec::new());
+    output::output_exr(&canvas, Some(&aovs), &mut out).unwrap();
+    write_to_file(out.get_ref(), filename_prefix, "exr");
+}
 
+/// Handles `--debug-pixel X Y`, writing the paths traced through that pixel to
+/// stdout as JSON. Returns whether the flag was passed, in which case there's
+/// no need to render.
+#[allow(dead_code)]
+pub fn debug_pixel_from_args<M: Material, H: Hittable<Material = M>>(
+    world: &H,
+    camera: &Camera,
+) -> bool {
+    let args = env::args().collect::<Vec<_>>();
+    let Some(n) = args.iter().position(|a| a == "--debug-pixel") else {
+        return false;
+    };
+    let coord = |i: usize| -> usize {
+        args.get(n + i)
+            .and_then(|a| a.parse().ok())
+            .expect("usage: --debug-pixel X Y")
+    };
+
+    let trace = camera.debug_pixel(world, coord(1), coord(2));
+    serde_json::to_writer_pretty(BufWriter::new(io::stdout().lock()), &trace).unwrap();
+    true
+}
+
+/// Handles `--live-preview ADDR [PASSES]`, rendering PASSES passes (64 by
+/// default) of `samples_per_pixel` samples while streaming the image to a web
+/// browser at http://ADDR/, then writing it to stdout. Returns whether the flag
+/// was passed, in which case there's no need to render.
+#[allow(dead_code)]
+pub fn live_preview_from_args<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+) -> bool {
+    let args = env::args().collect::<Vec<_>>();
+    let Some(n) = args.iter().position(|a| a == "--live-preview") else {
+        return false;
+    };
+    let addr = args
+        .get(n + 1)
+        .expect("usage: --live-preview ADDR [PASSES]");
+    let passes = args.get(n + 2).and_then(|a| a.parse().ok()).unwrap_or(64);
+
+    let server = PreviewServer::start(addr, OutputOpts::default()).unwrap();
+    eprintln!("Serving preview at http://{}/", server.local_addr());
+
+    let start = Instant::now();
+    let canvas = camera.render_progressive(world, passes, &CancellationToken::new(), |canvas| {
+        server.publish(canvas);
+        eprintln!(
+            "{} samples per pixel after {:.1?}",
+            canvas.samples,
+            start.elapsed()
+        );
+    });
+
+    let mut out = BufWriter::new(io::stdout().lock());
     output::output_ppm(&canvas, &mut out).unwrap();
+    true
 }
 
 #[allow(dead_code)]
@@ -29,7 +158,8 @@ pub fn render_save_checkpoint<M: Material, H: Hittable<Material = M> + Sync>(
     let mut out = BufWriter::new(io::stdout().lock());
     let canvas = camera.render(&world);
 
-    let output_checkpoint = bincode::serialize(&canvas).unwrap();
+    let mut output_checkpoint = Vec::new();
+    distributed::write_canvas(&canvas, &mut output_checkpoint).unwrap();
     write_to_file(&output_checkpoint, checkpoint_name, "chkpt");
 
     output::output_ppm(&canvas, &mut out).unwrap();
@@ -42,17 +172,117 @@ pub fn render_from_checkpoint<M: Material, H: Hittable<Material = M> + Sync>(
     checkpoint_name: &str,
 ) {
     let checkpoint_bytes: Vec<u8> = read_from_file(checkpoint_name);
-    let checkpoint: Canvas = bincode::deserialize(&checkpoint_bytes.as_slice()).unwrap();
+    let checkpoint = distributed::read_canvas(checkpoint_bytes.as_slice()).unwrap();
 
     let mut out = BufWriter::new(io::stdout().lock());
     let new_render = camera.render_from_checkpoint(&world, &checkpoint);
 
-    let output_checkpoint = bincode::serialize(&new_render).unwrap();
+    let mut output_checkpoint = Vec::new();
+    distributed::write_canvas(&new_render, &mut output_checkpoint).unwrap();
     write_to_file(&output_checkpoint, checkpoint_name, "chkpt");
 
     output::output_ppm(&new_render, &mut out).unwrap();
 }
 
+/// Renders a scene split by sample ranges across several processes. The role of
+/// this process is chosen from the command line:
+/// - `--workers N` (the default, with N = 1) spawns N copies of this executable
+///   on the local machine, merges their canvases, and writes the image to stdout.
+/// - `--worker START END` renders only samples START..END and writes the canvas
+///   to stdout, so that it can be run on another machine and saved to a file.
+/// - `--merge FILE...` merges canvas files written by workers and writes the
+///   image to stdout.
+#[allow(dead_code)]
+pub fn render_distributed<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    camera: &Camera,
+) {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let arg_usize = |n: usize| -> usize {
+        args.get(n)
+            .and_then(|a| a.parse().ok())
+            .unwrap_or_else(|| panic!("expected a number for argument {}", n))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("--worker") => {
+            let canvas = camera.render_samples(world, arg_usize(1)..arg_usize(2));
+
+            let out = BufWriter::new(io::stdout().lock());
+            distributed::write_canvas(&canvas, out).unwrap();
+        }
+        Some("--merge") => {
+            let canvases = args[1..].iter().map(|filename| {
+                let reader = BufReader::new(File::open(filename).unwrap());
+                distributed::read_canvas(reader).unwrap()
+            });
+            let canvas = distributed::merge_all(canvases).expect("no canvases to merge");
+
+            let mut out = BufWriter::new(io::stdout().lock());
+            output::output_ppm(&canvas, &mut out).unwrap();
+        }
+        Some("--workers") | None => {
+            let workers = if args.is_empty() { 1 } else { arg_usize(1) };
+            let ranges = distributed::partition_samples(camera.params().samples_per_pixel, workers);
+
+            // Share the machine's cores between the workers rather than oversubscribing
+            let threads_per_worker = thread::available_parallelism()
+                .map(|n| (n.get() / ranges.len().max(1)).max(1))
+                .unwrap_or(1);
+
+            let exe = env::current_exe().unwrap();
+            let children = ranges
+                .iter()
+                .map(|r| {
+                    Command::new(&exe)
+                        .args(["--worker", &r.start.to_string(), &r.end.to_string()])
+                        .env("RAYON_NUM_THREADS", threads_per_worker.to_string())
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .expect("unable to spawn worker")
+                })
+                .collect::<Vec<_>>();
+
+            let canvases = children.into_iter().map(|child| {
+                let output = child.wait_with_output().unwrap();
+                assert!(output.status.success(), "worker failed");
+                distributed::read_canvas(output.stdout.as_slice()).unwrap()
+            });
+            let canvas = distributed::merge_all(canvases).expect("no samples to render");
+
+            let mut out = BufWriter::new(io::stdout().lock());
+            output::output_ppm(&canvas, &mut out).unwrap();
+        }
+        Some(other) => panic!("unrecognized argument {}", other),
+    }
+}
+
+/// Renders `frames` frames of an animation with the camera following `path`,
+/// writing each to a numbered file (`<filename_prefix>-0000.ppm` and so on).
+/// Parameters which aren't animated come from `params`; see `CameraPath::frame_params`.
+#[allow(dead_code)]
+pub fn render_sequence<M: Material, H: Hittable<Material = M> + Sync>(
+    world: &H,
+    path: &CameraPath,
+    params: &CameraParams,
+    frames: usize,
+    frames_per_second: f64,
+    filename_prefix: &str,
+) {
+    for frame in 0..frames {
+        eprintln!("Rendering frame {} of {}", frame + 1, frames);
+        let camera = Camera::new(path.frame_params(params, frame, frames_per_second));
+        let canvas = camera.render(world);
+
+        let mut out = BufWriter::new(File::create(frame_filename(filename_prefix, frame)).unwrap());
+        output::output_ppm(&canvas, &mut out).unwrap();
+    }
+}
+
+fn frame_filename(filename_prefix: &str, frame: usize) -> String {
+    format!("{}-{:04}.ppm", filename_prefix, frame)
+}
+
 fn write_to_file(data: &[u8], filename_prefix: &str, extension: &str) {
     let filename = format!(
         "{}-{}.{}",
@@ -2,6 +2,7 @@ use ray_tracing_one_weekend::{
     bvh::Bvh,
     camera::{Camera, CameraParams},
     color::Color,
+    denoise::Denoiser,
     hittable::{flat::quad::Quad, Hittable},
     material::{DiffuseLight, Lambertian, Material},
     texture::SolidColor,
@@ -11,6 +12,9 @@ use ray_tracing_one_weekend::{
 mod common;
 
 fn main() {
+    // Pass --preview for a quick, denoised render with fewer samples
+    let preview = std::env::args().any(|arg| arg == "--preview");
+
     let red = Lambertian {
         texture: SolidColor {
             albedo: Color::new(0.65, 0.05, 0.05),
@@ -95,7 +99,7 @@ fn main() {
     let camera = Camera::new(CameraParams {
         aspect_ratio: 1.0,
         image_width: 600,
-        samples_per_pixel: 200,
+        samples_per_pixel: if preview { 16 } else { 200 },
         max_depth: 50,
         background: Color::new(0.0, 0.0, 0.0),
         vfov: 40.0,
@@ -103,8 +107,23 @@ fn main() {
         lookat: Point3::new(278.0, 278.0, 0.0),
         vup: Vec3::new(0.0, 1.0, 0.0),
         defocus_angle: 0.0,
+        aux: preview,
         ..Default::default()
     });
 
-    common::render_to_stdout(&world, &camera);
+    // Pass --debug-pixel X Y to see how a pixel's color came about
+    if common::debug_pixel_from_args(&world, &camera) {
+        return;
+    }
+
+    // Pass --live-preview 127.0.0.1:8080 to watch the image converge in a browser
+    if common::live_preview_from_args(&world, &camera) {
+        return;
+    }
+
+    if preview {
+        common::render_denoised_to_stdout(&world, &camera, &Denoiser::default());
+    } else {
+        common::render_to_stdout(&world, &camera);
+    }
 }
@@ -6,7 +6,7 @@ use ray_tracing_one_weekend::{
     camera::{Camera, CameraParams},
     color::{srgb, Color},
     hittable::{flat::quad::Quad, Hittable},
-    io::wavefront_obj::WavefrontObj,
+    io::wavefront_obj::{NormalWeighting, WavefrontObj},
     material::{DiffuseLight, Lambertian, Material},
     texture::{Image, SolidColor},
     vec3::{Point3, Vec3},
@@ -44,7 +44,10 @@ fn main() {
 
     let obj_bytes = include_bytes!("../../objs/spot_triangulated.obj");
     let reader = BufReader::new(obj_bytes.as_slice());
-    let cow = WavefrontObj::parse(reader).to_object(&cow_surface as &(dyn Material + Sync));
+    let mut parsed = WavefrontObj::parse(reader);
+    // The model has no normals of its own, so smooth it
+    parsed.generate_normals(NormalWeighting::Angle, 60.0);
+    let cow = parsed.to_mesh(&cow_surface as &(dyn Material + Sync));
     let transformed_cow = cow
         .scale(200.0)
         .rotate_y(45.0)
@@ -0,0 +1,85 @@
+use std::{io::BufReader, sync::Arc};
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use ray_tracing_one_weekend::{
+    bvh::Bvh,
+    camera::{Camera, CameraParams},
+    color::Color,
+    hittable::{flat::quad::Quad, Hittable},
+    io::wavefront_obj::WavefrontObj,
+    material::{Lambertian, Material},
+    texture::SolidColor,
+    vec3::{Point3, Vec3},
+};
+
+mod common;
+
+const ROWS: usize = 25;
+const COLUMNS: usize = 40;
+
+fn main() {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+
+    let diffuse = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.53, 0.32, 0.75),
+        },
+    };
+    let ground_material = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.48, 0.83, 0.53),
+        },
+    };
+
+    // The teapot is parsed once, and every instance shares its mesh
+    let obj_bytes = include_bytes!("../../objs/teapot-low.obj");
+    let reader = BufReader::new(obj_bytes.as_slice());
+    let teapot = Arc::new(WavefrontObj::parse(reader).to_mesh(&diffuse as &(dyn Material + Sync)));
+
+    let upright = teapot.clone().rotate_x(-90.0).bounding_box();
+    let spacing = upright.x().size().max(upright.z().size()) * 1.5;
+    let instances = (0..ROWS * COLUMNS)
+        .map(|i| {
+            let (row, column) = (i / COLUMNS, i % COLUMNS);
+            let offset = Vec3::new(
+                (column as f64 - COLUMNS as f64 / 2.0) * spacing,
+                -upright.y().min,
+                -(row as f64) * spacing,
+            );
+            teapot
+                .clone()
+                .rotate_x(-90.0)
+                .rotate_y(rng.gen_range(0.0..360.0))
+                .translate(offset)
+        })
+        .collect::<Vec<_>>();
+
+    let world: Vec<Box<dyn Hittable<Material = &(dyn Material + Sync)> + Sync>> = vec![
+        Box::new(Bvh::new(instances)),
+        Box::new(Quad::new(
+            Point3::new(-1000.0, 0.0, 1000.0),
+            Vec3::new(2000.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -2000.0),
+            &ground_material as &(dyn Material + Sync),
+        )),
+    ];
+
+    let world = Bvh::new(world);
+
+    let camera = Camera::new(CameraParams {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 600,
+        samples_per_pixel: 100,
+        max_depth: 20,
+        vfov: 40.0,
+        lookfrom: Point3::new(0.0, 6.0 * spacing, 6.0 * spacing),
+        lookat: Point3::new(0.0, 0.0, -(ROWS as f64) * spacing / 3.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        defocus_angle: 0.0,
+        background: Color::new(0.7, 0.8, 1.0),
+        ..Default::default()
+    });
+
+    common::render_to_stdout(&world, &camera);
+}
@@ -0,0 +1,140 @@
+use ray_tracing_one_weekend::{
+    animation::Keyframes,
+    bvh::Bvh,
+    camera::{Camera, CameraParams},
+    color::Color,
+    hittable::{
+        animated::TransformKey,
+        flat::{quad::Quad, triangle::Triangle},
+        Hittable,
+    },
+    material::{Lambertian, Material},
+    texture::SolidColor,
+    vec3::{Point3, Vec3},
+};
+
+mod common;
+
+fn main() {
+    let ground = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.5, 0.5, 0.5),
+        },
+    };
+    let box_material = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.8, 0.3, 0.1),
+        },
+    };
+    let triangle_material = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.2, 0.4, 0.9),
+        },
+    };
+
+    let mut world: Vec<Box<dyn Hittable<Material = &(dyn Material + Sync)> + Sync>> = Vec::new();
+
+    world.push(Box::new(
+        Quad::new(
+            Point3::new(-10.0, 0.0, -10.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 20.0),
+            &ground as &(dyn Material + Sync),
+        )
+        .tagged(1),
+    ));
+
+    // A box spinning a quarter turn about its vertical axis while the shutter is open
+    let spinning_box = Bvh::new(
+        common::make_box(
+            &Point3::new(-1.0, 0.0, -1.0),
+            &Point3::new(1.0, 2.0, 1.0),
+            &box_material as &(dyn Material + Sync),
+        )
+        .to_vec(),
+    )
+    .animate(Keyframes::new(vec![
+        (
+            0.0,
+            TransformKey {
+                translation: Vec3::new(-2.5, 0.0, 0.0),
+                ..Default::default()
+            },
+        ),
+        (
+            1.0,
+            TransformKey {
+                translation: Vec3::new(-2.5, 0.0, 0.0),
+                rotation: Vec3::new(0.0, 90.0, 0.0),
+                ..Default::default()
+            },
+        ),
+    ]))
+    .tagged(2);
+    world.push(Box::new(spinning_box));
+
+    // A triangle sliding sideways and shrinking, with a pause in the middle of its motion
+    let sliding_triangle = Triangle::new(
+        Point3::new(-1.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(1.0, 2.0, 0.0),
+        &triangle_material as &(dyn Material + Sync),
+    )
+    .animate(Keyframes::new(vec![
+        (
+            0.0,
+            TransformKey {
+                translation: Vec3::new(1.5, 0.0, 0.0),
+                ..Default::default()
+            },
+        ),
+        (
+            0.4,
+            TransformKey {
+                translation: Vec3::new(2.5, 0.0, 0.0),
+                ..Default::default()
+            },
+        ),
+        (
+            0.6,
+            TransformKey {
+                translation: Vec3::new(2.5, 0.0, 0.0),
+                ..Default::default()
+            },
+        ),
+        (
+            1.0,
+            TransformKey {
+                translation: Vec3::new(3.5, 0.0, 0.0),
+                scale: Vec3::new(0.5, 0.5, 0.5),
+                ..Default::default()
+            },
+        ),
+    ]))
+    .tagged(3);
+    world.push(Box::new(sliding_triangle));
+
+    let world = Bvh::new(world);
+
+    let camera = Camera::new(CameraParams {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 20,
+        vfov: 40.0,
+        lookfrom: Point3::new(0.0, 4.0, 10.0),
+        lookat: Point3::new(0.0, 1.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        shutter_open: 0.0,
+        shutter_close: 1.0,
+        aux: true,
+        ..Default::default()
+    });
+
+    // Pass --aovs to write an EXR file with the AOV passes, including motion vectors
+    if std::env::args().any(|arg| arg == "--aovs") {
+        common::render_exr_with_aovs(&world, &camera, "motion_blur");
+    } else {
+        common::render_to_stdout(&world, &camera);
+    }
+}
@@ -9,6 +9,7 @@ use ray_tracing_one_weekend::{
         Hittable,
     },
     material::{DiffuseLight, Lambertian, Material},
+    output::{OutputOpts, ToneMap},
     perlin::Perlin,
     texture::{Noise, SolidColor},
     vec3::{Point3, Vec3},
@@ -74,5 +75,11 @@ fn main() {
         ..Default::default()
     });
 
-    common::render_to_stdout(&world.as_slice(), &camera);
+    // The lights are much brighter than white, so compress them rather than clipping
+    let output_opts = OutputOpts {
+        tone_map: ToneMap::Aces,
+        ..Default::default()
+    };
+
+    common::render_to_stdout_with_opts(&world.as_slice(), &camera, &output_opts);
 }
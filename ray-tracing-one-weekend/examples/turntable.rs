@@ -0,0 +1,87 @@
+use ray_tracing_one_weekend::{
+    animation::CameraPath,
+    camera::CameraParams,
+    color::Color,
+    hittable::sphere::{Center, Sphere},
+    material::{Dielectric, Lambertian, Material, Metal},
+    texture::{Checker, SolidColor},
+    vec3::{Point3, Vec3},
+};
+
+mod common;
+
+const FRAMES: usize = 48;
+const FRAMES_PER_SECOND: f64 = 24.0;
+
+fn main() {
+    let ground = Lambertian {
+        texture: Checker::new(
+            0.5,
+            SolidColor {
+                albedo: Color::new(0.2, 0.3, 0.1),
+            },
+            SolidColor {
+                albedo: Color::new(0.9, 0.9, 0.9),
+            },
+        ),
+    };
+    let diffuse = Lambertian {
+        texture: SolidColor {
+            albedo: Color::new(0.1, 0.2, 0.5),
+        },
+    };
+    let glass = Dielectric {
+        refraction_index: 1.5,
+    };
+    let metal = Metal {
+        albedo: Color::new(0.8, 0.6, 0.2),
+        fuzz: 0.0,
+    };
+
+    let world: Vec<Sphere<&(dyn Material + Sync)>> = vec![
+        Sphere {
+            center: Center::Stationary(Point3::new(0.0, -1000.0, 0.0)),
+            radius: 1000.0,
+            material: &ground,
+        },
+        Sphere {
+            center: Center::Stationary(Point3::new(0.0, 1.0, 0.0)),
+            radius: 1.0,
+            material: &diffuse,
+        },
+        Sphere {
+            center: Center::Stationary(Point3::new(-2.2, 1.0, 0.0)),
+            radius: 1.0,
+            material: &glass,
+        },
+        Sphere {
+            center: Center::Stationary(Point3::new(2.2, 1.0, 0.0)),
+            radius: 1.0,
+            material: &metal,
+        },
+    ];
+
+    let params = CameraParams {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 50,
+        max_depth: 20,
+        vfov: 30.0,
+        lookfrom: Point3::new(0.0, 3.0, 12.0),
+        lookat: Point3::new(0.0, 1.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        ..Default::default()
+    };
+
+    // One full revolution over the length of the sequence
+    let path = CameraPath::orbit(&params, FRAMES as f64 / FRAMES_PER_SECOND);
+
+    common::render_sequence(
+        &world.as_slice(),
+        &path,
+        &params,
+        FRAMES,
+        FRAMES_PER_SECOND,
+        "turntable",
+    );
+}
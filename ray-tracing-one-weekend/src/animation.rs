@@ -0,0 +1,254 @@
+use crate::{
+    camera::CameraParams,
+    vec3::{NormalizedVec3, Point3, Vec3},
+};
+
+/// Spacing of the keyframes generated for an orbiting camera
+const ORBIT_STEP_DEGREES: f64 = 5.0;
+
+/// Values which can be linearly interpolated. `t` is expected to be in [0, 1],
+/// where 0 gives `self` and 1 gives `other`.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + t * (other - self)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + t * (other - self)
+    }
+}
+
+/// A value which changes over time, linearly interpolated between keyframes.
+/// Before the first keyframe and after the last, the value is held constant.
+#[derive(Debug, Clone)]
+pub struct Keyframes<T> {
+    /// (time, value) pairs sorted by time
+    frames: Vec<(f64, T)>,
+}
+
+impl<T: Lerp + Clone> Keyframes<T> {
+    /// Creates an animation from (time, value) pairs. Panics if there are no keyframes.
+    pub fn new(mut frames: Vec<(f64, T)>) -> Self {
+        assert!(!frames.is_empty(), "Cannot animate without keyframes");
+        frames.sort_by(|(t1, _), (t2, _)| t1.total_cmp(t2));
+        Keyframes { frames }
+    }
+
+    /// Creates an animation that holds a single value for all time
+    pub fn constant(value: T) -> Self {
+        Keyframes::new(vec![(0.0, value)])
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let next = self.frames.partition_point(|(t, _)| *t <= time);
+        if next == 0 {
+            self.frames[0].1.clone()
+        } else if next == self.frames.len() {
+            self.frames[next - 1].1.clone()
+        } else {
+            let (t0, v0) = &self.frames[next - 1];
+            let (t1, v1) = &self.frames[next];
+            v0.lerp(v1, (time - t0) / (t1 - t0))
+        }
+    }
+
+    pub fn frames(&self) -> &[(f64, T)] {
+        &self.frames
+    }
+}
+
+/// A camera moving along keyframed paths, for rendering image sequences.
+/// Parameters which aren't animated are taken from a base `CameraParams`.
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    pub lookfrom: Keyframes<Point3>,
+    pub lookat: Keyframes<Point3>,
+    /// Vertical FOV in degrees
+    pub vfov: Keyframes<f64>,
+}
+
+impl CameraPath {
+    /// A camera held still where `params` places it
+    pub fn fixed(params: &CameraParams) -> Self {
+        CameraPath {
+            lookfrom: Keyframes::constant(params.lookfrom.clone()),
+            lookat: Keyframes::constant(params.lookat.clone()),
+            vfov: Keyframes::constant(params.vfov),
+        }
+    }
+
+    /// A camera circling `params.lookat` once over `duration`, starting from
+    /// `params.lookfrom`. The camera turns counter-clockwise about `params.vup`,
+    /// as seen from above. Useful for turntables.
+    pub fn orbit(params: &CameraParams, duration: f64) -> Self {
+        let axis = NormalizedVec3::try_from(params.vup.clone()).unwrap();
+        let offset = &params.lookfrom - &params.lookat;
+
+        let steps = (360.0 / ORBIT_STEP_DEGREES) as usize;
+        let frames = (0..=steps)
+            .map(|n| {
+                let fraction = n as f64 / steps as f64;
+                let (sin, cos) = f64::sin_cos(f64::to_radians(360.0 * fraction));
+
+                // Rodrigues' rotation formula
+                let rotated = &offset * cos
+                    + axis.cross(&offset) * sin
+                    + &*axis * (axis.dot(&offset) * (1.0 - cos));
+                (duration * fraction, &params.lookat + &rotated)
+            })
+            .collect();
+
+        CameraPath {
+            lookfrom: Keyframes::new(frames),
+            ..CameraPath::fixed(params)
+        }
+    }
+
+    /// Returns `params` with the camera placed where it is at `time`
+    pub fn params_at(&self, params: &CameraParams, time: f64) -> CameraParams {
+        CameraParams {
+            lookfrom: self.lookfrom.at(time),
+            lookat: self.lookat.at(time),
+            vfov: self.vfov.at(time),
+            ..params.clone()
+        }
+    }
+
+    /// Returns the parameters for one frame of a sequence. The frame starts at
+    /// time `frame / frames_per_second`, which is where the camera is placed, and
+    /// the shutter interval of `params` is taken as a fraction of the frame's
+    /// duration, so objects animated in scene time are motion blurred per frame.
+    pub fn frame_params(
+        &self,
+        params: &CameraParams,
+        frame: usize,
+        frames_per_second: f64,
+    ) -> CameraParams {
+        let time = frame as f64 / frames_per_second;
+        CameraParams {
+            shutter_open: time + params.shutter_open / frames_per_second,
+            shutter_close: time + params.shutter_close / frames_per_second,
+            ..self.params_at(params, time)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    fn test_keyframes() -> Keyframes<f64> {
+        Keyframes::new(vec![(1.0, 10.0), (0.0, 0.0), (2.0, 0.0)])
+    }
+
+    #[test]
+    fn keyframes_are_sorted_by_time() {
+        let times = test_keyframes()
+            .frames()
+            .iter()
+            .map(|(t, _)| *t)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn value_at_a_keyframe() {
+        assert_eq!(test_keyframes().at(1.0), 10.0);
+    }
+
+    #[test]
+    fn value_between_keyframes_is_interpolated() {
+        let k = test_keyframes();
+
+        assert_eq!(k.at(0.25), 2.5);
+        assert_eq!(k.at(1.5), 5.0);
+    }
+
+    #[test]
+    fn value_is_held_outside_keyframes() {
+        let k = test_keyframes();
+
+        assert_eq!(k.at(-1.0), 0.0);
+        assert_eq!(k.at(5.0), 0.0);
+    }
+
+    #[test]
+    fn interpolating_vectors() {
+        let k = Keyframes::new(vec![
+            (0.0, Vec3::new(0.0, 0.0, 0.0)),
+            (1.0, Vec3::new(2.0, -4.0, 8.0)),
+        ]);
+
+        assert_eq!(k.at(0.5), Vec3::new(1.0, -2.0, 4.0));
+    }
+
+    #[test]
+    fn camera_path_moves_the_camera() {
+        let params = CameraParams::default();
+        let path = CameraPath {
+            lookfrom: Keyframes::new(vec![
+                (0.0, Point3::new(0.0, 0.0, 0.0)),
+                (2.0, Point3::new(0.0, 2.0, 0.0)),
+            ]),
+            vfov: Keyframes::new(vec![(0.0, 90.0), (2.0, 30.0)]),
+            ..CameraPath::fixed(&params)
+        };
+
+        let moved = path.params_at(&params, 1.0);
+        assert_eq!(moved.lookfrom, Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(moved.lookat, params.lookat);
+        assert_eq!(moved.vfov, 60.0);
+    }
+
+    #[test]
+    fn orbiting_camera_circles_the_target() {
+        let params = CameraParams {
+            lookfrom: Point3::new(0.0, 1.0, 5.0),
+            lookat: Point3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+        let path = CameraPath::orbit(&params, 4.0);
+
+        // A quarter turn counter-clockwise about +Y takes +Z to +X
+        assert_approx_eq!(
+            &Vec3,
+            &path.lookfrom.at(1.0),
+            &Point3::new(5.0, 1.0, 0.0),
+            epsilon = 1e-9
+        );
+        assert_approx_eq!(
+            &Vec3,
+            &path.lookfrom.at(2.0),
+            &Point3::new(0.0, 1.0, -5.0),
+            epsilon = 1e-9
+        );
+        assert_approx_eq!(
+            &Vec3,
+            &path.lookfrom.at(4.0),
+            &params.lookfrom,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn frame_shutter_is_a_fraction_of_the_frame() {
+        let params = CameraParams {
+            shutter_open: 0.0,
+            shutter_close: 0.5,
+            ..Default::default()
+        };
+        let frame = CameraPath::fixed(&params).frame_params(&params, 3, 10.0);
+
+        assert_approx_eq!(f64, frame.shutter_open, 0.3);
+        assert_approx_eq!(f64, frame.shutter_close, 0.35);
+    }
+}
@@ -0,0 +1,19 @@
+//! Arbitrary output variables (AOVs) which can't be averaged over a pixel's
+//! samples: which object and material are seen through each pixel, and how it
+//! moves. See `Camera::render_aovs`. The albedo, normal and depth passes are
+//! the first hit features kept by the canvas, see `CameraParams::aux`.
+
+/// Each pass is stored in row-major order. Pixels where nothing was hit are zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovBuffers {
+    pub width: usize,
+    pub height: usize,
+    /// See `Hittable::tagged`
+    pub object_id: Vec<u32>,
+    /// See `Material::id`
+    pub material_id: Vec<u32>,
+    /// How far the surface seen through each pixel moves across the image while
+    /// the shutter is open, in pixels, with +y pointing down the image. Estimated
+    /// from how fast the surface is moving at the middle of the shutter interval.
+    pub motion: Vec<(f64, f64)>,
+}
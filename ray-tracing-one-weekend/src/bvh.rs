@@ -3,6 +3,7 @@ use crate::{
     hittable::{HitRecord, Hittable},
     interval::Interval,
     ray::Ray,
+    stats::{self, Counter},
 };
 
 /// A bounding-volume hierarchy for hittables.
@@ -19,30 +20,39 @@ enum Children<H> {
 }
 
 impl<M, H: Hittable<Material = M>> Bvh<H> {
-    pub fn new(mut hs: Vec<H>) -> Self {
+    pub fn new(hs: Vec<H>) -> Self {
+        Bvh::with_bounds(hs, &H::bounding_box)
+    }
+}
+
+impl<H> Bvh<H> {
+    /// Builds the hierarchy over children which aren't hittables themselves,
+    /// given the bounding box of each
+    pub(crate) fn with_bounds(mut hs: Vec<H>, bounds: &impl Fn(&H) -> AABB) -> Self {
         if hs.is_empty() {
             panic!("Cannot make a BVH node without hittables.")
         }
 
         let (children, bbox) = if hs.len() == 1 {
             let child = hs.swap_remove(0);
-            let b = child.bounding_box();
+            let b = bounds(&child);
             (Children::Leaf(vec![child]), b)
         } else if hs.len() == 2 {
             let left = hs.swap_remove(0);
             let right = hs.swap_remove(0);
-            let b = left.bounding_box().merge(&right.bounding_box());
+            let b = bounds(&left).merge(&bounds(&right));
             (Children::Leaf(vec![left, right]), b)
         } else {
-            let bbox = hs
-                .iter()
-                .fold(AABB::empty(), |b, h| b.merge(&h.bounding_box()));
+            let bbox = hs.iter().fold(AABB::empty(), |b, h| b.merge(&bounds(h)));
             let choice = find_longest_axis(&bbox);
-            let key_fn = match choice {
-                0 => |h: &H| h.bounding_box().x().min,
-                1 => |h: &H| h.bounding_box().y().min,
-                2 => |h: &H| h.bounding_box().z().min,
-                _ => panic!("Sorting choice should be between 0 and 2"),
+            let key_fn = |h: &H| {
+                let b = bounds(h);
+                match choice {
+                    0 => b.x().min,
+                    1 => b.y().min,
+                    2 => b.z().min,
+                    _ => panic!("Sorting choice should be between 0 and 2"),
+                }
             };
 
             hs.sort_unstable_by(|l, r| key_fn(l).total_cmp(&key_fn(r)));
@@ -51,13 +61,51 @@ impl<M, H: Hittable<Material = M>> Bvh<H> {
             let rs = hs.split_off(mid);
             let ls = hs;
 
-            let left = Bvh::new(ls);
-            let right = Bvh::new(rs);
+            let left = Bvh::with_bounds(ls, bounds);
+            let right = Bvh::with_bounds(rs, bounds);
             (Children::Inner(vec![left, right]), bbox)
         };
 
         Bvh { children, bbox }
     }
+
+    /// The box around every child, for children which aren't hittables
+    pub(crate) fn root_bounding_box(&self) -> AABB {
+        self.bbox.clone()
+    }
+
+    /// Finds the closest hit like `hit`, for children which aren't hittables
+    /// themselves. `hit_child` tests one child against the ray within an
+    /// interval.
+    pub(crate) fn hit_with(
+        &self,
+        ray: &Ray,
+        ray_t: &Interval,
+        hit_child: &impl Fn(&H, &Interval) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        stats::count(Counter::BvhNodesVisited);
+        if !self.bbox.hit(ray, ray_t) {
+            return None;
+        }
+
+        // Each child only needs to beat the closest hit so far
+        let interval = |closest_so_far: &Option<HitRecord>| Interval {
+            min: ray_t.min,
+            max: closest_so_far
+                .as_ref()
+                .map_or(ray_t.max, |closest| closest.t),
+        };
+        match &self.children {
+            Children::Leaf(c) => c.iter().fold(None, |closest_so_far, child| {
+                hit_child(child, &interval(&closest_so_far)).or(closest_so_far)
+            }),
+            Children::Inner(c) => c.iter().fold(None, |closest_so_far, child| {
+                child
+                    .hit_with(ray, &interval(&closest_so_far), hit_child)
+                    .or(closest_so_far)
+            }),
+        }
+    }
 }
 
 fn find_longest_axis(bbox: &AABB) -> u8 {
@@ -79,6 +127,7 @@ fn find_longest_axis(bbox: &AABB) -> u8 {
 impl<M, H: Hittable<Material = M>> Hittable for Bvh<H> {
     type Material = M;
     fn hit(&self, ray: &Ray, ray_t: &Interval) -> Option<(&M, HitRecord)> {
+        stats::count(Counter::BvhNodesVisited);
         if self.bbox.hit(ray, ray_t) {
             match &self.children {
                 Children::Leaf(c) => c.as_slice().hit(ray, ray_t),
@@ -1,7 +1,4 @@
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
-};
+use std::ops::{Add, AddAssign, Range};
 
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
@@ -11,15 +8,32 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    aov::AovBuffers,
     color::Color,
-    hittable::Hittable,
+    debug::{Bounce, HitTrace, PixelTrace, SampleTrace},
+    filter::Filter,
+    hittable::{Face, Hittable},
     interval::Interval,
     material::Material,
+    progress::{CancellationToken, Progress, ProgressObserver, ScanlineProgress, Tracker},
     ray::Ray,
+    stats::{self, Counter},
     utility,
     vec3::{NormalizedVec3, Point3, Vec3},
 };
 
+/// Distances along a ray at which hits count. Starts slightly above zero so
+/// that scattered rays don't hit the surface they leave from.
+const RAY_INTERVAL: Interval = Interval {
+    min: 1e-10,
+    max: f64::INFINITY,
+};
+
+/// Rows of pixels starting from the row `top`, with the weighted colors, weights
+/// and features splatted onto them, and how many pixels of its own row were
+/// finished before cancelling
+type Band = (usize, Vec<Color>, Vec<f64>, Option<Vec<Aux>>, usize);
+
 #[derive(Clone)]
 pub struct CameraParams {
     pub aspect_ratio: f64,
@@ -36,6 +50,66 @@ pub struct CameraParams {
     pub focus_dist: f64,
     pub background: Color,
     pub seed: u64,
+    /// Time at which the shutter opens. Each ray is cast at a random time
+    /// between shutter open and close.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Restricts rendering to a rectangle of pixels. Pixels outside the region
+    /// are left black, with no samples.
+    pub region: Option<Region>,
+    pub projection: Projection,
+    /// How samples are weighted into the pixels around them. Canvases can only
+    /// be merged if they were rendered with the same filter.
+    pub filter: Filter,
+    /// Whether the canvas keeps the first hit features of each pixel, which
+    /// `Denoiser` needs
+    pub aux: bool,
+}
+
+/// How camera rays are cast through the image. Only the perspective projection
+/// simulates a lens, so the defocus settings are ignored by the others.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    /// Parallel rays cast from the plane through `lookfrom`, covering a view
+    /// `height` world units tall. `vfov` is ignored.
+    Orthographic { height: f64 },
+    /// Equidistant fisheye. The angle between a ray and the view direction is
+    /// proportional to its pixel's distance from the image center, with `vfov`
+    /// degrees spanning the image height. A `vfov` of 180° gives a hemisphere.
+    /// The camera's own `vfov` is ignored.
+    Fisheye { vfov: f64 },
+    /// 360° panorama, with longitude across the image width and latitude down
+    /// its height. The image should be twice as wide as it is tall. `vfov` is ignored.
+    Equirectangular,
+}
+
+/// A rectangle of pixels, with (x, y) being the top-left corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    /// Returns the part of this region which lies inside an image of the given size
+    pub fn clamp_to(&self, image_width: usize, image_height: usize) -> Region {
+        let x = self.x.min(image_width);
+        let y = self.y.min(image_height);
+        Region {
+            x,
+            y,
+            width: self.width.min(image_width - x),
+            height: self.height.min(image_height - y),
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
 }
 
 impl Default for CameraParams {
@@ -54,6 +128,12 @@ impl Default for CameraParams {
             focus_dist: 10.0,
             background: Color::new(0.7, 0.8, 1.0),
             seed: 0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            region: None,
+            projection: Projection::Perspective,
+            filter: Filter::Box,
+            aux: false,
         }
     }
 }
@@ -66,6 +146,10 @@ pub struct Camera {
     pixel_dv: Vec3,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
+    /// Camera frame basis vectors
+    u: NormalizedVec3,
+    v: NormalizedVec3,
+    w: NormalizedVec3,
 }
 
 impl Camera {
@@ -114,13 +198,54 @@ impl Camera {
             pixel_dv,
             defocus_disk_u,
             defocus_disk_v,
+            u,
+            v,
+            w,
         }
     }
 }
 
 impl Camera {
+    pub fn params(&self) -> &CameraParams {
+        &self.params
+    }
+
     pub fn render<M: Material, H: Hittable<Material = M> + Sync>(&self, world: H) -> Canvas {
-        self._render(0, world)
+        self.render_with_progress(world, &self.scanline_progress(), &CancellationToken::new())
+    }
+
+    /**
+     * Renders a scene, telling `observer` about each pixel as it's finished.
+     * Once `cancel` is cancelled no more pixels are started, and the canvas is
+     * returned with the unfinished pixels left black.
+     */
+    pub fn render_with_progress<M: Material, H: Hittable<Material = M> + Sync>(
+        &self,
+        world: H,
+        observer: &dyn ProgressObserver,
+        cancel: &CancellationToken,
+    ) -> Canvas {
+        self._render(0..self.params.samples_per_pixel, world, observer, cancel)
+    }
+
+    /**
+     * Renders only the samples whose indices fall in the given range, ignoring
+     * `samples_per_pixel`. Each sample index has its own RNG stream, so canvases
+     * rendered from disjoint ranges (e.g. by separate processes) can be combined
+     * with `Canvas::merge`. The result is the same as checkpointing at the same
+     * range boundaries.
+     */
+    pub fn render_samples<M: Material, H: Hittable<Material = M> + Sync>(
+        &self,
+        world: H,
+        samples: Range<usize>,
+    ) -> Canvas {
+        self._render(
+            samples,
+            world,
+            &self.scanline_progress(),
+            &CancellationToken::new(),
+        )
     }
 
     /**
@@ -138,89 +263,559 @@ impl Camera {
         world: H,
         checkpoint: &Canvas,
     ) -> Canvas {
-        let new_render = self._render(checkpoint.samples, world);
-        new_render.merge(&checkpoint)
+        self.continue_render(
+            world,
+            checkpoint,
+            &self.scanline_progress(),
+            &CancellationToken::new(),
+        )
+    }
+
+    /**
+     * Renders a scene in passes of `samples_per_pixel` samples each, merging
+     * every pass into the canvas as `render_from_checkpoint` does, and calling
+     * `on_pass` with the canvas so far after each one. Stops after `passes`
+     * passes, or once `cancel` is cancelled, in which case the unfinished pass
+     * is thrown away.
+     */
+    pub fn render_progressive<M: Material, H: Hittable<Material = M> + Sync + Copy>(
+        &self,
+        world: H,
+        passes: usize,
+        cancel: &CancellationToken,
+        mut on_pass: impl FnMut(&Canvas),
+    ) -> Canvas {
+        let pixels = self.params.image_width * self.image_height;
+        let mut canvas = Canvas {
+            samples: 0,
+            width: self.params.image_width,
+            height: self.image_height,
+            data: vec![Color::new(0.0, 0.0, 0.0); pixels],
+            weights: vec![0.0; pixels],
+            aux: self.params.aux.then(|| vec![Aux::default(); pixels]),
+        };
+
+        for _ in 0..passes {
+            let next = self.continue_render(world, &canvas, &|_: &Progress| {}, cancel);
+            if cancel.is_cancelled() {
+                break;
+            }
+            canvas = next;
+            on_pass(&canvas);
+        }
+
+        canvas
+    }
+
+    fn continue_render<M: Material, H: Hittable<Material = M> + Sync>(
+        &self,
+        world: H,
+        checkpoint: &Canvas,
+        observer: &dyn ProgressObserver,
+        cancel: &CancellationToken,
+    ) -> Canvas {
+        let samples = checkpoint.samples..checkpoint.samples + self.params.samples_per_pixel;
+        let new_render = self._render(samples, world, observer, cancel);
+        new_render.merge(checkpoint)
+    }
+
+    fn scanline_progress(&self) -> ScanlineProgress {
+        ScanlineProgress {
+            image_width: self.params.image_width,
+        }
     }
 
     fn _render<M: Material, H: Hittable<Material = M> + Sync>(
         &self,
-        samples_already_rendered: usize,
+        samples: Range<usize>,
         world: H,
+        observer: &dyn ProgressObserver,
+        cancel: &CancellationToken,
     ) -> Canvas {
         let image_width = self.params.image_width;
         let image_height = self.image_height;
 
-        let indices = (0..image_height)
-            .into_par_iter()
-            .flat_map_iter(|y| (0..image_width).map(move |x| (x, y)));
+        let region = match &self.params.region {
+            Some(r) => r.clamp_to(image_width, image_height),
+            None => Region {
+                x: 0,
+                y: 0,
+                width: image_width,
+                height: image_height,
+            },
+        };
 
-        let pixel_counter = Arc::new(AtomicUsize::new(0));
+        let tracker = Tracker::new(observer, cancel, region.width * region.height);
 
-        let mut colors: Vec<((usize, usize), Color)> = indices
-            .map(|(i, j)| {
-                let mut rng = ChaCha8Rng::seed_from_u64(self.params.seed);
-                let color_sum = (0..self.params.samples_per_pixel)
-                    .map(|n| n + samples_already_rendered)
-                    .map(|sample_index| {
-                        // Use a different RNG stream per sample so that RNG is deterministic
-                        // whether starting a fresh render or continuing from a checkpoint.
-                        let stream_index = (sample_index * image_width * image_height
-                            + i * image_width
-                            + j) as u64;
-                        rng.set_stream(stream_index);
-                        let ray = self.get_ray(&mut rng, i, j);
-                        self.ray_color(&mut rng, &ray, &world, self.params.max_depth)
-                    })
-                    .fold(Color::new(0.0, 0.0, 0.0), |acc, c| acc + c);
-
-                let pixel_counter = Arc::clone(&pixel_counter);
-                let pixels_completed = pixel_counter.fetch_add(1, Ordering::Relaxed) + 1;
-                if pixels_completed % image_width == 0 {
-                    let scanlines_completed = pixels_completed / image_width;
-                    eprintln!(
-                        "Scanline-equivalents remaining: {}.",
-                        image_height - scanlines_completed
-                    );
+        // Returns a sample's offset from the center of pixel (i, j), its color and first hit
+        let sample = |rng: &mut ChaCha8Rng, i: usize, j: usize, sample_index: usize| {
+            let (offset, ray) = self.sample_ray(rng, i, j, sample_index);
+            let mut first_hit = Aux::default();
+            let color = self.ray_color(
+                rng,
+                &ray,
+                &world,
+                self.params.max_depth,
+                Some(&mut first_hit),
+            );
+            (offset, color, first_hit)
+        };
+
+        let mut data = vec![Color::new(0.0, 0.0, 0.0); image_width * image_height];
+        let mut weights = vec![0.0; image_width * image_height];
+        let mut aux = self
+            .params
+            .aux
+            .then(|| vec![Aux::default(); image_width * image_height]);
+
+        if self.params.filter == Filter::Box {
+            // Every sample has the same weight and stays in its own pixel, so just sum them
+            let indices = (region.y..region.y + region.height)
+                .into_par_iter()
+                .flat_map_iter(|y| (region.x..region.x + region.width).map(move |x| (x, y)));
+
+            let colors: Vec<((usize, usize), Color, Aux)> = indices
+                .filter(|_| !tracker.is_cancelled())
+                .map(|(i, j)| {
+                    let mut rng = ChaCha8Rng::seed_from_u64(self.params.seed);
+                    let (color_sum, aux_sum) = samples
+                        .clone()
+                        .map(|sample_index| {
+                            let (_, color, aux) = sample(&mut rng, i, j, sample_index);
+                            (color, aux)
+                        })
+                        .fold(
+                            (Color::new(0.0, 0.0, 0.0), Aux::default()),
+                            |(acc, acc_aux), (c, a)| (acc + c, acc_aux + a),
+                        );
+
+                    tracker.pixel_done();
+                    ((i, j), color_sum, aux_sum)
+                })
+                .collect();
+
+            colors.into_iter().for_each(|((x, y), c, a)| {
+                data[y * image_width + x] = c;
+                weights[y * image_width + x] = samples.len() as f64;
+                if let Some(aux) = &mut aux {
+                    aux[y * image_width + x] = a;
                 }
+            });
+        } else {
+            // Samples are splatted onto the pixels within the filter's reach. Each
+            // row of pixels is rendered into its own band of rows, and the bands
+            // are added up in order so that the result is deterministic.
+            let filter = &self.params.filter;
+            let reach = (filter.radius() + 0.5).ceil() as usize;
+            let region_right = region.x + region.width - 1;
 
-                ((i, j), color_sum)
-            })
-            .collect();
+            let bands: Vec<Band> = (region.y..region.y + region.height)
+                .into_par_iter()
+                .map(|j| {
+                    let top = j.saturating_sub(reach).max(region.y);
+                    let bottom = (j + reach).min(region.y + region.height - 1);
+                    let mut band_colors =
+                        vec![Color::new(0.0, 0.0, 0.0); (bottom - top + 1) * region.width];
+                    let mut band_weights = vec![0.0; (bottom - top + 1) * region.width];
+                    let mut band_aux = self
+                        .params
+                        .aux
+                        .then(|| vec![Aux::default(); (bottom - top + 1) * region.width]);
+
+                    let mut finished = 0;
+                    for i in region.x..region.x + region.width {
+                        if tracker.is_cancelled() {
+                            break;
+                        }
+                        let mut rng = ChaCha8Rng::seed_from_u64(self.params.seed);
+                        for sample_index in samples.clone() {
+                            let ((dx, dy), color, sample_aux) =
+                                sample(&mut rng, i, j, sample_index);
+
+                            for y in top..=bottom {
+                                for x in i.saturating_sub(reach).max(region.x)
+                                    ..=(i + reach).min(region_right)
+                                {
+                                    let weight = filter.weight(
+                                        dx - (x as f64 - i as f64),
+                                        dy - (y as f64 - j as f64),
+                                    );
+                                    if weight != 0.0 {
+                                        let n = (y - top) * region.width + (x - region.x);
+                                        band_colors[n] += weight * &color;
+                                        band_weights[n] += weight;
+                                        if let Some(band_aux) = &mut band_aux {
+                                            band_aux[n] += sample_aux.scaled(weight);
+                                        }
+                                    }
+                                }
+                            }
+                        }
 
-        // Sort pixels in row-major order
-        colors.sort_unstable_by_key(|((x, y), _)| (*y, *x));
+                        tracker.pixel_done();
+                        finished += 1;
+                    }
+
+                    (top, band_colors, band_weights, band_aux, finished)
+                })
+                .collect();
+
+            let mut unfinished = Vec::new();
+            for (j, (top, band_colors, band_weights, band_aux, finished)) in (region.y..).zip(bands)
+            {
+                let pixel =
+                    |n: usize| (top + n / region.width) * image_width + region.x + n % region.width;
+                for (n, (c, w)) in band_colors.into_iter().zip(band_weights).enumerate() {
+                    data[pixel(n)] += c;
+                    weights[pixel(n)] += w;
+                }
+                if let (Some(aux), Some(band_aux)) = (&mut aux, band_aux) {
+                    for (n, a) in band_aux.into_iter().enumerate() {
+                        aux[pixel(n)] += a;
+                    }
+                }
+                unfinished.extend(
+                    (region.x + finished..region.x + region.width).map(|x| j * image_width + x),
+                );
+            }
+
+            // Finished neighbours splat onto the pixels left out by cancelling,
+            // which would otherwise look rendered with only some of their samples
+            for pixel in unfinished {
+                data[pixel] = Color::new(0.0, 0.0, 0.0);
+                weights[pixel] = 0.0;
+                if let Some(aux) = &mut aux {
+                    aux[pixel] = Aux::default();
+                }
+            }
+        }
 
         Canvas {
-            samples: self.params.samples_per_pixel,
+            samples: samples.len(),
             width: image_width,
             height: image_height,
-            data: colors.into_iter().map(|(_, c)| c).collect(),
+            data,
+            weights,
+            aux,
         }
     }
 
-    /// Returns a randomly sampled camera ray for the pixel at location (i, j).
-    /// The ray will originate from the defocus disk.
-    fn get_ray(&self, rng: &mut impl Rng, i: usize, j: usize) -> Ray {
-        let pixel_center =
-            &self.pixel_00_location + (i as f64 * &self.pixel_du) + (j as f64 * &self.pixel_dv);
-        let pixel_sample = pixel_center + self.pixel_sample_square(rng);
+    /**
+     * Renders the ID and motion passes, from a single ray through the center of
+     * each pixel at the middle of the shutter interval. Defocus blur is ignored,
+     * so that the passes line up with the surfaces in focus. The albedo, normal
+     * and depth passes are the canvas's first hit features, see `CameraParams::aux`.
+     */
+    pub fn render_aovs<M: Material, H: Hittable<Material = M> + Sync>(
+        &self,
+        world: H,
+    ) -> AovBuffers {
+        let image_width = self.params.image_width;
+        let image_height = self.image_height;
+        let time = (self.params.shutter_open + self.params.shutter_close) / 2.0;
+
+        let pixels: Vec<_> = (0..image_height)
+            .into_par_iter()
+            .flat_map_iter(|j| (0..image_width).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                let (origin, direction) = if self.params.projection == Projection::Perspective {
+                    let pixel_center = &self.pixel_00_location
+                        + (i as f64 * &self.pixel_du)
+                        + (j as f64 * &self.pixel_dv);
+                    let direction = &pixel_center - &self.params.lookfrom;
+                    (self.params.lookfrom.clone(), direction)
+                } else {
+                    self.project(i as f64 + 0.5, j as f64 + 0.5)
+                };
+                let ray = Ray::new_at_time(origin, direction, time);
 
-        let ray_origin = if self.params.defocus_angle <= 0.0 {
-            self.params.lookfrom.clone()
-        } else {
-            self.defocus_disk_sample(rng)
+                let (mat, hit_rec) = world.hit(&ray, &RAY_INTERVAL)?;
+
+                let start = &hit_rec.p + (self.params.shutter_open - time) * &hit_rec.velocity;
+                let end = &hit_rec.p + (self.params.shutter_close - time) * &hit_rec.velocity;
+                let motion = match (self.image_position(&start), self.image_position(&end)) {
+                    (Some((x0, y0)), Some((x1, y1))) => (x1 - x0, y1 - y0),
+                    _ => (0.0, 0.0),
+                };
+
+                Some((hit_rec.object_id, mat.id(), motion))
+            })
+            .collect();
+
+        let mut aovs = AovBuffers {
+            width: image_width,
+            height: image_height,
+            object_id: Vec::with_capacity(pixels.len()),
+            material_id: Vec::with_capacity(pixels.len()),
+            motion: Vec::with_capacity(pixels.len()),
         };
-        let ray_direction = &pixel_sample - &ray_origin;
-        let ray_time = rng.gen();
-        Ray::new_at_time(ray_origin, ray_direction, ray_time)
+        for pixel in pixels {
+            let (object_id, material_id, motion) = pixel.unwrap_or((0, 0, (0.0, 0.0)));
+            aovs.object_id.push(object_id);
+            aovs.material_id.push(material_id);
+            aovs.motion.push(motion);
+        }
+        aovs
     }
 
-    /// Returns a random offset vector in the square surrounding a pixel
-    fn pixel_sample_square(&self, rng: &mut impl Rng) -> Vec3 {
+    /// Returns where a point appears on the image, in pixels from the top-left
+    /// corner. The inverse of casting a ray through the image. Returns `None`
+    /// for points the camera can't see, such as those behind a perspective camera.
+    pub fn image_position(&self, point: &Point3) -> Option<(f64, f64)> {
+        let width = self.params.image_width as f64;
+        let height = self.image_height as f64;
+        let offset = point - &self.params.lookfrom;
+        let (x, y, z) = (
+            offset.dot(&self.u),
+            offset.dot(&self.v),
+            -offset.dot(&self.w),
+        );
+
+        match self.params.projection {
+            Projection::Perspective => {
+                if z <= 0.0 {
+                    return None;
+                }
+                let h = f64::tan(utility::degrees_to_radians(self.params.vfov) / 2.0);
+                let pixels_per_unit = height / (2.0 * h);
+                Some((
+                    width / 2.0 + (x / z) * pixels_per_unit,
+                    height / 2.0 - (y / z) * pixels_per_unit,
+                ))
+            }
+            Projection::Orthographic {
+                height: view_height,
+            } => {
+                let pixels_per_unit = height / view_height;
+                Some((
+                    width / 2.0 + x * pixels_per_unit,
+                    height / 2.0 - y * pixels_per_unit,
+                ))
+            }
+            Projection::Fisheye { vfov } => {
+                let distance = offset.length();
+                if distance == 0.0 {
+                    return None;
+                }
+                let theta = f64::acos((z / distance).clamp(-1.0, 1.0));
+                let r = theta * height / utility::degrees_to_radians(vfov);
+                let sideways = f64::sqrt(x * x + y * y);
+                let (dx, dy) = if sideways > 0.0 {
+                    (r * x / sideways, r * y / sideways)
+                } else {
+                    (0.0, 0.0)
+                };
+                Some((width / 2.0 + dx, height / 2.0 - dy))
+            }
+            Projection::Equirectangular => {
+                let distance = offset.length();
+                if distance == 0.0 {
+                    return None;
+                }
+                let longitude = f64::atan2(x, z);
+                let latitude = f64::asin((y / distance).clamp(-1.0, 1.0));
+                Some((
+                    (longitude / (2.0 * std::f64::consts::PI) + 0.5) * width,
+                    (0.5 - latitude / std::f64::consts::PI) * height,
+                ))
+            }
+        }
+    }
+
+    /// Returns the camera ray for a sample of pixel (i, j), along with its
+    /// offset from the pixel's center
+    fn sample_ray(
+        &self,
+        rng: &mut ChaCha8Rng,
+        i: usize,
+        j: usize,
+        sample_index: usize,
+    ) -> ((f64, f64), Ray) {
+        // Use a different RNG stream per sample so that RNG is deterministic
+        // whether starting a fresh render or continuing from a checkpoint.
+        let image_width = self.params.image_width;
+        let stream_index =
+            (sample_index * image_width * self.image_height + i * image_width + j) as u64;
+        rng.set_stream(stream_index);
+        let offset = Self::pixel_sample_offset(rng);
+        let ray = self.get_ray(rng, i, j, offset);
+        (offset, ray)
+    }
+
+    /**
+     * Traces the same samples through pixel (i, j) as `render`, recording the
+     * path each one takes through the scene. The pixel's color is the plain
+     * average of the samples, without filtering.
+     */
+    pub fn debug_pixel<M: Material, H: Hittable<Material = M>>(
+        &self,
+        world: H,
+        i: usize,
+        j: usize,
+    ) -> PixelTrace {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.params.seed);
+        let samples = (0..self.params.samples_per_pixel)
+            .map(|sample_index| {
+                let (offset, ray) = self.sample_ray(&mut rng, i, j, sample_index);
+                let bounces = self.trace_path(&mut rng, ray, &world);
+                SampleTrace {
+                    sample_index,
+                    offset,
+                    color: self.path_color(&bounces),
+                    bounces,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let color = samples
+            .iter()
+            .fold(Color::new(0.0, 0.0, 0.0), |acc, s| acc + &s.color)
+            / samples.len() as f64;
+
+        PixelTrace {
+            x: i,
+            y: j,
+            color,
+            samples,
+        }
+    }
+
+    /// Follows a camera ray as `ray_color` does, drawing the same
+    /// random numbers, and records each bounce
+    fn trace_path<R: Rng, M: Material, H: Hittable<Material = M>>(
+        &self,
+        rng: &mut R,
+        mut r: Ray,
+        world: H,
+    ) -> Vec<Bounce> {
+        let mut bounces = Vec::new();
+        for _ in 0..self.params.max_depth {
+            let hit = world.hit(&r, &RAY_INTERVAL).map(|(mat, hit_rec)| {
+                let scatter = mat.scatter(rng, &r, &hit_rec);
+                let hit = HitTrace {
+                    t: hit_rec.t,
+                    p: hit_rec.p.clone(),
+                    normal: (*hit_rec.normal).clone(),
+                    front_face: matches!(hit_rec.face, Face::Front),
+                    uv: hit_rec.uv,
+                    object_id: hit_rec.object_id,
+                    material_id: mat.id(),
+                    emitted: mat.emitted(hit_rec.uv.0, hit_rec.uv.1, &hit_rec.p),
+                    attenuation: scatter.as_ref().map(|(attenuation, _)| attenuation.clone()),
+                };
+                (hit, scatter.map(|(_, scattered)| scattered))
+            });
+
+            let (hit, scattered) = match hit {
+                Some((hit, scattered)) => (Some(hit), scattered),
+                None => (None, None),
+            };
+            bounces.push(Bounce {
+                origin: r.origin.clone(),
+                direction: r.direction.clone(),
+                time: r.time,
+                hit,
+            });
+            match scattered {
+                Some(scattered) => r = scattered,
+                None => break,
+            }
+        }
+        bounces
+    }
+
+    /// Adds up the light along a path from `trace_path`, as `ray_color` does
+    fn path_color(&self, bounces: &[Bounce]) -> Color {
+        // A path that is still scattering when it reaches the maximum depth gathers no more light
+        bounces
+            .iter()
+            .rev()
+            .fold(Color::new(0.0, 0.0, 0.0), |acc, bounce| match &bounce.hit {
+                Some(hit) => match &hit.attenuation {
+                    Some(attenuation) => hit.emitted.clone() + attenuation * acc,
+                    None => hit.emitted.clone(),
+                },
+                None => self.params.background.clone(),
+            })
+    }
+
+    /// Returns a random offset from a pixel's center, within the square surrounding it.
+    /// Measured in pixels, with +y pointing down the image.
+    fn pixel_sample_offset(rng: &mut impl Rng) -> (f64, f64) {
         let px = -0.5 + rng.gen::<f64>();
         let py = -0.5 + rng.gen::<f64>();
+        (px, py)
+    }
+
+    /// Returns a camera ray through the pixel at location (i, j), offset from its
+    /// center by `offset`. The ray will originate from the defocus disk.
+    fn get_ray(&self, rng: &mut impl Rng, i: usize, j: usize, offset: (f64, f64)) -> Ray {
+        let (ray_origin, ray_direction) = if self.params.projection == Projection::Perspective {
+            let pixel_center =
+                &self.pixel_00_location + (i as f64 * &self.pixel_du) + (j as f64 * &self.pixel_dv);
+            let pixel_sample =
+                pixel_center + ((offset.0 * &self.pixel_du) + (offset.1 * &self.pixel_dv));
+
+            let ray_origin = if self.params.defocus_angle <= 0.0 {
+                self.params.lookfrom.clone()
+            } else {
+                self.defocus_disk_sample(rng)
+            };
+            let ray_direction = &pixel_sample - &ray_origin;
+            (ray_origin, ray_direction)
+        } else {
+            let x = i as f64 + 0.5 + offset.0;
+            let y = j as f64 + 0.5 + offset.1;
+            self.project(x, y)
+        };
+        let ray_time = self.params.shutter_open
+            + rng.gen::<f64>() * (self.params.shutter_close - self.params.shutter_open);
+        Ray::new_at_time(ray_origin, ray_direction, ray_time)
+    }
+
+    /// Returns the origin and direction of the ray through a point on the image,
+    /// in pixels from the top-left corner, for projections other than perspective
+    fn project(&self, x: f64, y: f64) -> (Point3, Vec3) {
+        let width = self.params.image_width as f64;
+        let height = self.image_height as f64;
+        let (u, v, w) = (&*self.u, &*self.v, &*self.w);
+
+        match self.params.projection {
+            Projection::Orthographic {
+                height: view_height,
+            } => {
+                let pixel_size = view_height / height;
+                let origin = &self.params.lookfrom
+                    + (x - width / 2.0) * pixel_size * u
+                    + (height / 2.0 - y) * pixel_size * v;
+                (origin, -w)
+            }
+            Projection::Fisheye { vfov } => {
+                let dx = x - width / 2.0;
+                let dy = height / 2.0 - y;
+                let r = f64::sqrt(dx * dx + dy * dy);
+                let theta = r * utility::degrees_to_radians(vfov) / height;
+                let (sin_theta, cos_theta) = f64::sin_cos(theta);
 
-        (px * &self.pixel_du) + (py * &self.pixel_dv)
+                let sideways = if r > 0.0 {
+                    (dx / r) * u + (dy / r) * v
+                } else {
+                    Vec3::zero()
+                };
+                let direction = sin_theta * sideways - cos_theta * w;
+                (self.params.lookfrom.clone(), direction)
+            }
+            Projection::Equirectangular => {
+                let longitude = (x / width - 0.5) * 2.0 * std::f64::consts::PI;
+                let latitude = (0.5 - y / height) * std::f64::consts::PI;
+                let (sin_lon, cos_lon) = f64::sin_cos(longitude);
+                let (sin_lat, cos_lat) = f64::sin_cos(latitude);
+
+                let direction = (cos_lat * sin_lon) * u + sin_lat * v - (cos_lat * cos_lon) * w;
+                (self.params.lookfrom.clone(), direction)
+            }
+            Projection::Perspective => unreachable!("perspective rays are cast in get_ray"),
+        }
     }
 
     /// Returns a random point in the camera defocus disk
@@ -229,44 +824,123 @@ impl Camera {
         &self.params.lookfrom + (px * &self.defocus_disk_u) + (py * &self.defocus_disk_v)
     }
 
+    /// Returns the color seen along a ray. For camera rays, `first_hit` is set
+    /// to the features of the first surface the ray hits.
     fn ray_color<R: Rng, M: Material, H: Hittable<Material = M>>(
         &self,
         rng: &mut R,
         r: &Ray,
         world: H,
         depth: usize,
+        first_hit: Option<&mut Aux>,
     ) -> Color {
         if depth == 0 {
-            Color::new(0.0, 0.0, 0.0)
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        stats::count(if first_hit.is_some() {
+            Counter::PrimaryRays
         } else {
-            let interval = Interval {
-                min: 1e-10,
-                max: f64::INFINITY,
-            };
-            // Not as pretty as using Option combinators, but working with closures was finicky and this just works
-            if let Some((mat, hit_rec)) = world.hit(r, &interval) {
-                let color_from_emission = mat.emitted(hit_rec.uv.0, hit_rec.uv.1, &hit_rec.p);
-                if let Some((attenuation, scattered)) = mat.scatter(rng, r, &hit_rec) {
-                    let color_from_scatter =
-                        attenuation * self.ray_color(rng, &scattered, world, depth - 1);
-                    color_from_emission + color_from_scatter
-                } else {
-                    color_from_emission
-                }
+            Counter::ScatteredRays
+        });
+        // Not as pretty as using Option combinators, but working with closures was finicky and this just works
+        if let Some((mat, hit_rec)) = world.hit(r, &RAY_INTERVAL) {
+            let color_from_emission = mat.emitted(hit_rec.uv.0, hit_rec.uv.1, &hit_rec.p);
+            let scatter = mat.scatter(rng, r, &hit_rec);
+            if let Some(first_hit) = first_hit {
+                *first_hit = Aux {
+                    albedo: scatter
+                        .as_ref()
+                        .map_or(Color::new(1.0, 1.0, 1.0), |(attenuation, _)| {
+                            attenuation.clone()
+                        }),
+                    normal: (*hit_rec.normal).clone(),
+                    depth: hit_rec.t * r.direction.length(),
+                };
+            }
+            if let Some((attenuation, scattered)) = scatter {
+                let color_from_scatter =
+                    attenuation * self.ray_color(rng, &scattered, world, depth - 1, None);
+                color_from_emission + color_from_scatter
             } else {
-                self.params.background.clone()
+                color_from_emission
             }
+        } else {
+            if let Some(first_hit) = first_hit {
+                *first_hit = Aux {
+                    albedo: Color::new(1.0, 1.0, 1.0),
+                    ..Default::default()
+                };
+            }
+            self.params.background.clone()
+        }
+    }
+}
+
+/// Features of the first surface seen through a pixel, which guide denoising.
+/// Surfaces which don't scatter light, such as lights, have a white albedo.
+/// Rays which miss everything have a white albedo, with a zero normal and depth.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Aux {
+    pub albedo: Color,
+    /// Faces the camera
+    pub normal: Vec3,
+    /// Distance from the ray origin
+    pub depth: f64,
+}
+
+impl Aux {
+    fn scaled(&self, weight: f64) -> Aux {
+        Aux {
+            albedo: weight * &self.albedo,
+            normal: weight * &self.normal,
+            depth: weight * self.depth,
         }
     }
 }
 
+impl Default for Aux {
+    fn default() -> Self {
+        Aux {
+            albedo: Color::new(0.0, 0.0, 0.0),
+            normal: Vec3::zero(),
+            depth: 0.0,
+        }
+    }
+}
+
+impl Add for Aux {
+    type Output = Aux;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Aux {
+            albedo: self.albedo + rhs.albedo,
+            normal: self.normal + rhs.normal,
+            depth: self.depth + rhs.depth,
+        }
+    }
+}
+
+impl AddAssign for Aux {
+    fn add_assign(&mut self, rhs: Self) {
+        self.albedo += rhs.albedo;
+        self.normal += rhs.normal;
+        self.depth += rhs.depth;
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Canvas {
     pub samples: usize,
     pub width: usize,
     pub height: usize,
-    /// Image pixels in row-major order
+    /// Image pixels in row-major order, as sums of weighted sample colors
     data: Vec<Color>,
+    /// Sum of the sample weights for each pixel
+    weights: Vec<f64>,
+    /// Weighted sums of first hit features for each pixel, if the camera was
+    /// asked to keep them
+    aux: Option<Vec<Aux>>,
 }
 
 impl Canvas {
@@ -282,16 +956,121 @@ impl Canvas {
             .zip(other.data.iter())
             .map(|(p1, p2)| p1 + p2)
             .collect::<Vec<_>>();
+        let new_weights = self
+            .weights
+            .iter()
+            .zip(other.weights.iter())
+            .map(|(w1, w2)| w1 + w2)
+            .collect::<Vec<_>>();
+        // Features are only kept if both canvases have them for every sample
+        let new_aux = self
+            .aux
+            .as_ref()
+            .zip(other.aux.as_ref())
+            .map(|(aux1, aux2)| {
+                aux1.iter()
+                    .zip(aux2.iter())
+                    .map(|(a1, a2)| a1.clone() + a2.clone())
+                    .collect::<Vec<_>>()
+            });
         Canvas {
             samples: total_samples,
             width: self.width,
             height: self.height,
             data: new_data,
+            weights: new_weights,
+            aux: new_aux,
         }
     }
 
+    /// Returns the weighted average color of each pixel. Pixels without any
+    /// samples are black.
     pub fn pixel_data<'a>(&'a self) -> impl Iterator<Item = Color> + 'a {
-        self.data.iter().map(|c| c / (self.samples as f64))
+        self.data.iter().zip(self.weights.iter()).map(|(c, w)| {
+            if *w == 0.0 {
+                Color::new(0.0, 0.0, 0.0)
+            } else {
+                c / *w
+            }
+        })
+    }
+
+    /// Returns the weighted average first hit features of each pixel, or `None`
+    /// if the canvas doesn't have them. Pixels without any samples have zeroed
+    /// features.
+    pub fn aux_data<'a>(&'a self) -> Option<impl Iterator<Item = Aux> + 'a> {
+        let aux = self.aux.as_ref()?;
+        Some(aux.iter().zip(self.weights.iter()).map(|(a, w)| {
+            if *w == 0.0 {
+                Aux::default()
+            } else {
+                a.scaled(1.0 / *w)
+            }
+        }))
+    }
+
+    /// Returns a copy of this canvas with its pixels replaced by `colors`, each
+    /// with a weight of one, keeping the features.
+    pub(crate) fn with_pixel_data(&self, colors: Vec<Color>) -> Canvas {
+        assert_eq!(colors.len(), self.data.len());
+        Canvas {
+            samples: self.samples,
+            width: self.width,
+            height: self.height,
+            data: colors,
+            weights: vec![1.0; self.weights.len()],
+            aux: self.aux_data().map(|aux| aux.collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod region_tests {
+    use super::*;
+
+    #[test]
+    fn clamping_a_region_inside_the_image() {
+        let r = Region {
+            x: 10,
+            y: 20,
+            width: 5,
+            height: 5,
+        };
+
+        assert_eq!(r.clamp_to(100, 100), r);
+    }
+
+    #[test]
+    fn clamping_a_region_overlapping_the_image_edge() {
+        let r = Region {
+            x: 90,
+            y: 95,
+            width: 20,
+            height: 20,
+        };
+
+        assert_eq!(
+            r.clamp_to(100, 100),
+            Region {
+                x: 90,
+                y: 95,
+                width: 10,
+                height: 5
+            }
+        );
+    }
+
+    #[test]
+    fn clamping_a_region_outside_the_image() {
+        let r = Region {
+            x: 200,
+            y: 200,
+            width: 20,
+            height: 20,
+        };
+
+        let clamped = r.clamp_to(100, 100);
+        assert_eq!((clamped.width, clamped.height), (0, 0));
     }
 }
 
@@ -299,6 +1078,13 @@ impl Canvas {
 mod canvas_tests {
     use super::*;
 
+    fn aux_with_depth(depth: f64) -> Aux {
+        Aux {
+            depth,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_merge() {
         let c1 = Canvas {
@@ -306,6 +1092,8 @@ mod canvas_tests {
             width: 100,
             height: 100,
             data: vec![Color::new(1.0, 1.0, 1.0), Color::new(1.0, 1.0, 1.0)],
+            weights: vec![10.0, 10.0],
+            aux: Some(vec![Aux::default(), Aux::default()]),
         };
 
         let c2 = Canvas {
@@ -313,6 +1101,8 @@ mod canvas_tests {
             width: 100,
             height: 100,
             data: vec![Color::new(2.0, 3.0, 4.0), Color::new(5.0, 6.0, 7.0)],
+            weights: vec![10.0, 10.0],
+            aux: Some(vec![aux_with_depth(10.0), aux_with_depth(20.0)]),
         };
 
         assert_eq!(
@@ -321,8 +1111,454 @@ mod canvas_tests {
                 samples: 20,
                 width: 100,
                 height: 100,
-                data: vec![Color::new(3.0, 4.0, 5.0), Color::new(6.0, 7.0, 8.0)]
+                data: vec![Color::new(3.0, 4.0, 5.0), Color::new(6.0, 7.0, 8.0)],
+                weights: vec![20.0, 20.0],
+                aux: Some(vec![aux_with_depth(10.0), aux_with_depth(20.0)]),
             }
         );
     }
+
+    #[test]
+    fn merging_keeps_features_only_if_both_canvases_have_them() {
+        let canvas = |aux| Canvas {
+            samples: 1,
+            width: 1,
+            height: 1,
+            data: vec![Color::new(1.0, 1.0, 1.0)],
+            weights: vec![1.0],
+            aux,
+        };
+        let with_aux = canvas(Some(vec![aux_with_depth(2.0)]));
+        let without_aux = canvas(None);
+
+        assert!(with_aux.merge(&with_aux).aux_data().is_some());
+        assert!(with_aux.merge(&without_aux).aux_data().is_none());
+        assert!(without_aux.merge(&with_aux).aux_data().is_none());
+    }
+}
+
+#[cfg(test)]
+mod projection_tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    fn camera_with(projection: Projection) -> Camera {
+        Camera::new(CameraParams {
+            aspect_ratio: 2.0,
+            image_width: 200,
+            vfov: 180.0,
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            projection,
+            ..Default::default()
+        })
+    }
+
+    fn direction_at(camera: &Camera, x: f64, y: f64) -> Vec3 {
+        camera.project(x, y).1.normalize()
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let c = camera_with(Projection::Orthographic { height: 10.0 });
+
+        let (corner_origin, corner_direction) = c.project(0.0, 0.0);
+        let (center_origin, center_direction) = c.project(100.0, 50.0);
+
+        assert_approx_eq!(&Vec3, &corner_origin, &Point3::new(-10.0, 5.0, 0.0));
+        assert_approx_eq!(&Vec3, &center_origin, &Point3::new(0.0, 0.0, 0.0));
+        assert_approx_eq!(&Vec3, &corner_direction, &Vec3::new(0.0, 0.0, -1.0));
+        assert_approx_eq!(&Vec3, &center_direction, &Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn fisheye_angle_is_proportional_to_distance_from_center() {
+        let c = camera_with(Projection::Fisheye { vfov: 180.0 });
+
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 100.0, 50.0),
+            &Vec3::new(0.0, 0.0, -1.0)
+        );
+        // Halfway up the image is at 90° from the view direction
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 100.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            epsilon = 1e-9
+        );
+        // A quarter of the image height to the right is at 45°
+        let diagonal = std::f64::consts::FRAC_1_SQRT_2;
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 125.0, 50.0),
+            &Vec3::new(diagonal, 0.0, -diagonal),
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn equirectangular_covers_the_whole_sphere() {
+        let c = camera_with(Projection::Equirectangular);
+
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 100.0, 50.0),
+            &Vec3::new(0.0, 0.0, -1.0)
+        );
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 150.0, 50.0),
+            &Vec3::new(1.0, 0.0, 0.0),
+            epsilon = 1e-9
+        );
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 0.0, 50.0),
+            &Vec3::new(0.0, 0.0, 1.0),
+            epsilon = 1e-9
+        );
+        assert_approx_eq!(
+            &Vec3,
+            &direction_at(&c, 30.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            epsilon = 1e-9
+        );
+    }
+}
+
+#[cfg(test)]
+mod aov_tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::{Lambertian, Tagged},
+        texture::SolidColor,
+    };
+
+    use super::*;
+
+    fn sphere(center: Center) -> impl Hittable<Material = Tagged<Lambertian<SolidColor>>> {
+        Sphere {
+            center,
+            radius: 1.0,
+            material: Tagged {
+                material: Lambertian {
+                    texture: SolidColor {
+                        albedo: Color::new(0.2, 0.4, 0.6),
+                    },
+                },
+                id: 4,
+            },
+        }
+        .tagged(7)
+    }
+
+    fn camera() -> Camera {
+        Camera::new(CameraParams {
+            image_width: 21,
+            vfov: 40.0,
+            lookfrom: Point3::new(0.0, 0.0, 5.0),
+            lookat: Point3::zero(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn image_position_is_inverse_of_casting_rays() {
+        for projection in [
+            Projection::Perspective,
+            Projection::Orthographic { height: 3.0 },
+            Projection::Fisheye { vfov: 120.0 },
+            Projection::Equirectangular,
+        ] {
+            let c = Camera::new(CameraParams {
+                aspect_ratio: 2.0,
+                image_width: 200,
+                vfov: 120.0,
+                lookfrom: Point3::new(1.0, 2.0, 3.0),
+                lookat: Point3::new(0.0, 0.0, -1.0),
+                projection,
+                ..Default::default()
+            });
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+            for (i, j) in [(0, 0), (100, 50), (37, 81), (199, 99)] {
+                let ray = c.get_ray(&mut rng, i, j, (0.25, -0.25));
+                let (x, y) = c.image_position(&ray.at(3.0)).unwrap();
+
+                assert_approx_eq!(f64, x, i as f64 + 0.75, epsilon = 1e-6);
+                assert_approx_eq!(f64, y, j as f64 + 0.25, epsilon = 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn points_behind_perspective_camera_are_not_on_the_image() {
+        assert_eq!(camera().image_position(&Point3::new(0.0, 0.0, 10.0)), None);
+    }
+
+    #[test]
+    fn aovs_describe_first_hit() {
+        let aovs = camera().render_aovs(sphere(Center::Stationary(Point3::zero())));
+        let center = 10 * 21 + 10;
+
+        assert_eq!(aovs.object_id[center], 7);
+        assert_eq!(aovs.material_id[center], 4);
+        assert_eq!(aovs.motion[center], (0.0, 0.0));
+    }
+
+    #[test]
+    fn aovs_where_nothing_is_hit() {
+        let aovs = camera().render_aovs(sphere(Center::Stationary(Point3::zero())));
+
+        assert_eq!(aovs.object_id[0], 0);
+        assert_eq!(aovs.material_id[0], 0);
+        assert_eq!(aovs.motion[0], (0.0, 0.0));
+    }
+
+    #[test]
+    fn first_hit_features_describe_the_surface_seen() {
+        let camera = Camera::new(CameraParams {
+            aux: true,
+            ..camera().params
+        });
+        let canvas = camera.render(sphere(Center::Stationary(Point3::zero())));
+        let aux = canvas.aux_data().unwrap().collect::<Vec<_>>();
+        let center = &aux[10 * 21 + 10];
+
+        assert_approx_eq!(f64, center.depth, 4.0, epsilon = 0.01);
+        assert_approx_eq!(
+            &Vec3,
+            &center.normal,
+            &Vec3::new(0.0, 0.0, 1.0),
+            epsilon = 0.05
+        );
+        assert_approx_eq!(&Color, &center.albedo, &Color::new(0.2, 0.4, 0.6));
+        // Nothing was hit in the corner, so only the background is seen
+        assert_eq!(aux[0].depth, 0.0);
+        assert_eq!(aux[0].normal, Vec3::zero());
+        assert_eq!(aux[0].albedo, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn motion_vectors_follow_moving_objects() {
+        let moving = Center::Moving(Point3::new(-0.1, 0.0, 0.0), Point3::new(0.1, 0.0, 0.0));
+        let c = camera();
+        let aovs = c.render_aovs(sphere(moving));
+        let center = 10 * 21 + 10;
+
+        // The front of the sphere moves 0.2 units right, at a distance of 4 from the camera
+        let (x0, _) = c.image_position(&Point3::new(-0.1, 0.0, 1.0)).unwrap();
+        let (x1, _) = c.image_position(&Point3::new(0.1, 0.0, 1.0)).unwrap();
+        assert_approx_eq!(f64, aovs.motion[center].0, x1 - x0, epsilon = 1e-9);
+        assert_approx_eq!(f64, aovs.motion[center].1, 0.0, epsilon = 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::{Lambertian, Tagged},
+        texture::SolidColor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn debugging_a_pixel_retraces_its_samples() {
+        let world = Sphere {
+            center: Center::Stationary(Point3::zero()),
+            radius: 1.0,
+            material: Tagged {
+                material: Lambertian {
+                    texture: SolidColor {
+                        albedo: Color::new(0.2, 0.4, 0.6),
+                    },
+                },
+                id: 4,
+            },
+        }
+        .tagged(7);
+        let camera = Camera::new(CameraParams {
+            image_width: 21,
+            samples_per_pixel: 8,
+            max_depth: 4,
+            vfov: 40.0,
+            lookfrom: Point3::new(0.0, 0.0, 5.0),
+            lookat: Point3::zero(),
+            ..Default::default()
+        });
+
+        let trace = camera.debug_pixel(&world, 10, 5);
+
+        assert_eq!(trace.samples.len(), 8);
+        for sample in &trace.samples {
+            let first_hit = sample.bounces[0].hit.as_ref().unwrap();
+            assert_eq!((first_hit.object_id, first_hit.material_id), (7, 4));
+            assert!(first_hit.front_face);
+            assert!(sample.bounces.len() <= 4);
+        }
+        let canvas = camera.render(&world);
+        let expected = canvas.pixel_data().nth(5 * 21 + 10).unwrap();
+        assert_eq!(trace.color, expected);
+        assert!(serde_json::to_string(&trace).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::Lambertian,
+        stats::Counter,
+        texture::SolidColor,
+    };
+
+    use super::*;
+
+    #[test]
+    fn rendering_counts_rays_and_intersection_tests() {
+        let world = Sphere {
+            center: Center::Stationary(Point3::zero()),
+            radius: 1.0,
+            material: Lambertian {
+                texture: SolidColor {
+                    albedo: Color::new(0.5, 0.5, 0.5),
+                },
+            },
+        };
+        let camera = Camera::new(CameraParams {
+            image_width: 10,
+            aspect_ratio: 1.0,
+            samples_per_pixel: 2,
+            max_depth: 3,
+            lookfrom: Point3::new(0.0, 0.0, 3.0),
+            lookat: Point3::zero(),
+            ..Default::default()
+        });
+        let (_, stats) = stats::measure(|| camera.render(&world));
+
+        // Other tests may be counting at the same time, so only check lower bounds
+        assert!(stats.get(Counter::PrimaryRays) >= 200);
+        assert!(stats.get(Counter::ScatteredRays) > 0);
+        assert!(stats.get(Counter::SphereTests) >= 200);
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::Lambertian,
+        progress::Progress,
+        texture::SolidColor,
+    };
+
+    use super::*;
+
+    fn world() -> Sphere<Lambertian<SolidColor>> {
+        Sphere {
+            center: Center::Stationary(Point3::zero()),
+            radius: 1.0,
+            material: Lambertian {
+                texture: SolidColor {
+                    albedo: Color::new(0.5, 0.5, 0.5),
+                },
+            },
+        }
+    }
+
+    fn camera(filter: Filter) -> Camera {
+        Camera::new(CameraParams {
+            image_width: 8,
+            aspect_ratio: 1.0,
+            samples_per_pixel: 2,
+            max_depth: 3,
+            lookfrom: Point3::new(0.0, 0.0, 3.0),
+            lookat: Point3::zero(),
+            background: Color::new(1.0, 1.0, 1.0),
+            filter,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn observer_is_told_about_every_pixel() {
+        for filter in [Filter::Box, Filter::Tent { radius: 1.0 }] {
+            let camera = camera(filter);
+            let updates = Mutex::new(Vec::new());
+            let observer = |p: &Progress| updates.lock().unwrap().push(p.clone());
+
+            camera.render_with_progress(world(), &observer, &CancellationToken::new());
+
+            let mut updates = updates.into_inner().unwrap();
+            updates.sort_by_key(|p| p.pixels_done);
+            assert_eq!(updates.len(), 64);
+            assert!(updates.iter().all(|p| p.pixels_total == 64));
+            assert_eq!(updates.last().unwrap().pixels_done, 64);
+        }
+    }
+
+    #[test]
+    fn progressive_passes_merge_like_checkpoints() {
+        let camera = camera(Filter::Box);
+        let mut pass_samples = Vec::new();
+
+        let canvas = camera.render_progressive(&world(), 3, &CancellationToken::new(), |c| {
+            pass_samples.push(c.samples)
+        });
+
+        let first = camera.render(world());
+        let second = camera.render_from_checkpoint(world(), &first);
+        let third = camera.render_from_checkpoint(world(), &second);
+        assert_eq!(canvas, third);
+        assert_eq!(pass_samples, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn cancelling_progressive_rendering_keeps_the_finished_passes() {
+        let camera = camera(Filter::Box);
+        let cancel = CancellationToken::new();
+
+        let canvas = camera.render_progressive(&world(), 5, &cancel, |c| {
+            if c.samples == 4 {
+                cancel.cancel();
+            }
+        });
+
+        assert_eq!(canvas.samples, 4);
+    }
+
+    #[test]
+    fn cancelling_returns_a_partial_canvas() {
+        for filter in [Filter::Box, Filter::Tent { radius: 1.0 }] {
+            let camera = camera(filter);
+            let cancel = CancellationToken::new();
+            let finished = AtomicUsize::new(0);
+            let observer = |p: &Progress| {
+                finished.fetch_max(p.pixels_done, Ordering::Relaxed);
+                if p.pixels_done == 10 {
+                    cancel.cancel();
+                }
+            };
+
+            let canvas = camera.render_with_progress(world(), &observer, &cancel);
+
+            // Pixels already started on other threads are still finished, and
+            // no others have any samples splatted onto them
+            let sampled = canvas.weights.iter().filter(|w| **w != 0.0).count();
+            assert!(sampled >= 10);
+            assert!(sampled < 64);
+            assert_eq!(sampled, finished.into_inner());
+        }
+    }
 }
@@ -0,0 +1,54 @@
+//! Records the paths traced through a pixel, for working out why it looks
+//! wrong. The records serialize to JSON.
+
+use serde::Serialize;
+
+use crate::{
+    color::Color,
+    vec3::{Point3, Vec3},
+};
+
+/// Every sample traced through a pixel by `Camera::debug_pixel`
+#[derive(Debug, Clone, Serialize)]
+pub struct PixelTrace {
+    pub x: usize,
+    pub y: usize,
+    /// The average color of the samples
+    pub color: Color,
+    pub samples: Vec<SampleTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleTrace {
+    pub sample_index: usize,
+    /// Offset of the sample from the pixel's center, in pixels
+    pub offset: (f64, f64),
+    pub color: Color,
+    /// The camera ray followed by each scattered ray, until one misses, is
+    /// absorbed or the maximum depth is reached
+    pub bounces: Vec<Bounce>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bounce {
+    pub origin: Point3,
+    pub direction: Vec3,
+    pub time: f64,
+    /// `None` where the ray misses everything and sees the background
+    pub hit: Option<HitTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HitTrace {
+    pub t: f64,
+    pub p: Point3,
+    /// Faces against the ray
+    pub normal: Vec3,
+    pub front_face: bool,
+    pub uv: (f64, f64),
+    pub object_id: u32,
+    pub material_id: u32,
+    pub emitted: Color,
+    /// `None` where the material absorbs the ray instead of scattering it
+    pub attenuation: Option<Color>,
+}
@@ -0,0 +1,248 @@
+//! Denoising for low sample count renders, using the edge-avoiding À-Trous
+//! wavelet filter from "Edge-Avoiding À-Trous Wavelet Transform for fast Global
+//! Illumination Filtering" (Dammertz et al. 2010).
+//!
+//! Colors are blurred with neighboring pixels, except across edges found in
+//! the normal and depth of the first surface seen through each pixel, or where
+//! the brightness changes by more than the noise around it explains. As in
+//! SVGF ("Spatiotemporal Variance-Guided Filtering", Schied et al. 2017), the
+//! noise is estimated from the variance of the brightness around each pixel, so
+//! that bright, sparse noise is smoothed out too. Each pass doubles the spacing
+//! between the pixels it blurs, so a few passes cover a wide area cheaply.
+//! Colors are divided by the albedo before blurring and multiplied by it
+//! afterwards, so that textures stay sharp.
+
+use rayon::prelude::*;
+
+use crate::{
+    camera::{Aux, Canvas},
+    color::Color,
+};
+
+/// Weights of the taps in the B3 spline kernel, from -2 to 2 steps away from the center
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Added to the albedo before dividing by it, to avoid dividing by zero on
+/// black surfaces
+const ALBEDO_EPSILON: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Denoiser {
+    /// Number of filter passes. Each pixel is blurred with pixels up to
+    /// `2 * (2^iterations - 1)` pixels away.
+    pub iterations: usize,
+    /// How different neighboring brightnesses can be before they're treated as
+    /// an edge, in standard deviations of the local noise
+    pub luminance_sigma: f64,
+    /// How different neighboring normals can be before they're treated as an edge
+    pub normal_sigma: f64,
+    /// How different neighboring depths can be before they're treated as an
+    /// edge, relative to the depth of the farther one
+    pub depth_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            luminance_sigma: 4.0,
+            normal_sigma: 0.3,
+            depth_sigma: 0.05,
+        }
+    }
+}
+
+impl Denoiser {
+    /// Returns a denoised copy of the canvas, with each pixel having a weight of
+    /// one. The result is for output only: merging it with other canvases or
+    /// rendering more samples on top of it will give the wrong result.
+    ///
+    /// Panics if the canvas was rendered without `CameraParams::aux`.
+    pub fn denoise(&self, canvas: &Canvas) -> Canvas {
+        let width = canvas.width;
+        let height = canvas.height;
+        let aux = canvas
+            .aux_data()
+            .expect("denoising needs a canvas rendered with CameraParams::aux")
+            .collect::<Vec<_>>();
+
+        let mut illumination = canvas
+            .pixel_data()
+            .zip(aux.iter())
+            .map(|(c, a)| demodulate(&c, &a.albedo))
+            .collect::<Vec<_>>();
+
+        let pixels = || {
+            (0..height)
+                .into_par_iter()
+                .flat_map_iter(move |y| (0..width).map(move |x| (x, y)))
+        };
+
+        for iteration in 0..self.iterations {
+            let luminance = illumination.iter().map(luminance).collect::<Vec<_>>();
+            let std_dev = pixels()
+                .map(|(x, y)| local_variance(&luminance, (width, height), (x, y)).sqrt())
+                .collect::<Vec<_>>();
+
+            illumination = pixels()
+                .map(|(x, y)| {
+                    self.filter_pixel(
+                        &illumination,
+                        &luminance,
+                        &std_dev,
+                        &aux,
+                        (width, height),
+                        (x, y),
+                        1 << iteration,
+                    )
+                })
+                .collect();
+        }
+
+        let colors = illumination
+            .iter()
+            .zip(aux.iter())
+            .map(|(c, a)| remodulate(c, &a.albedo))
+            .collect();
+        canvas.with_pixel_data(colors)
+    }
+
+    /// Returns the weighted average of the 5×5 taps around pixel (x, y), spaced
+    /// `step` pixels apart
+    #[allow(clippy::too_many_arguments)]
+    fn filter_pixel(
+        &self,
+        illumination: &[Color],
+        luminance: &[f64],
+        std_dev: &[f64],
+        aux: &[Aux],
+        (width, height): (usize, usize),
+        (x, y): (usize, usize),
+        step: usize,
+    ) -> Color {
+        let center = y * width + x;
+        // Allow for some difference even where there's no noise, so that flat
+        // areas with gentle gradients still get smoothed
+        let luminance_sigma = self.luminance_sigma * std_dev[center] + 1e-4;
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        let mut weight_sum = 0.0;
+
+        for (ky, kernel_y) in KERNEL.iter().enumerate() {
+            let Some(ty) = (y + ky * step).checked_sub(2 * step) else {
+                continue;
+            };
+            if ty >= height {
+                continue;
+            }
+            for (kx, kernel_x) in KERNEL.iter().enumerate() {
+                let Some(tx) = (x + kx * step).checked_sub(2 * step) else {
+                    continue;
+                };
+                if tx >= width {
+                    continue;
+                }
+
+                let tap = ty * width + tx;
+                let weight = kernel_x
+                    * kernel_y
+                    * f64::exp(-(luminance[center] - luminance[tap]).abs() / luminance_sigma)
+                    * edge_weight(
+                        (&aux[center].normal - &aux[tap].normal).length_squared(),
+                        self.normal_sigma,
+                    )
+                    * edge_weight(
+                        relative_difference(aux[center].depth, aux[tap].depth).powi(2),
+                        self.depth_sigma,
+                    );
+
+                sum += weight * &illumination[tap];
+                weight_sum += weight;
+            }
+        }
+
+        // The center tap always has a weight of at least KERNEL[2]², so this can't divide by zero
+        sum / weight_sum
+    }
+}
+
+/// https://en.wikipedia.org/wiki/Relative_luminance
+fn luminance(c: &Color) -> f64 {
+    0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b()
+}
+
+/// Variance of the values in the 3×3 pixels around (x, y)
+fn local_variance(values: &[f64], (width, height): (usize, usize), (x, y): (usize, usize)) -> f64 {
+    let neighbors = (y.saturating_sub(1)..=(y + 1).min(height - 1))
+        .flat_map(|ny| (x.saturating_sub(1)..=(x + 1).min(width - 1)).map(move |nx| (nx, ny)))
+        .map(|(nx, ny)| values[ny * width + nx])
+        .collect::<Vec<_>>();
+    let n = neighbors.len() as f64;
+    let mean = neighbors.iter().sum::<f64>() / n;
+    neighbors
+        .iter()
+        .map(|v| (v - mean) * (v - mean))
+        .sum::<f64>()
+        / n
+}
+
+/// Falls off from 1 towards 0 as the squared distance between two features grows past sigma²
+fn edge_weight(distance_squared: f64, sigma: f64) -> f64 {
+    f64::exp(-distance_squared / (sigma * sigma))
+}
+
+fn relative_difference(a: f64, b: f64) -> f64 {
+    let larger = a.max(b);
+    if larger > 0.0 {
+        (a - b).abs() / larger
+    } else {
+        0.0
+    }
+}
+
+fn demodulate(color: &Color, albedo: &Color) -> Color {
+    Color::new(
+        color.r() / (albedo.r() + ALBEDO_EPSILON),
+        color.g() / (albedo.g() + ALBEDO_EPSILON),
+        color.b() / (albedo.b() + ALBEDO_EPSILON),
+    )
+}
+
+fn remodulate(illumination: &Color, albedo: &Color) -> Color {
+    Color::new(
+        illumination.r() * (albedo.r() + ALBEDO_EPSILON),
+        illumination.g() * (albedo.g() + ALBEDO_EPSILON),
+        illumination.b() * (albedo.b() + ALBEDO_EPSILON),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn demodulating_and_remodulating_restores_the_color() {
+        let color = Color::new(0.2, 0.0, 3.0);
+        let albedo = Color::new(0.5, 0.0, 1.0);
+
+        assert_approx_eq!(
+            &Color,
+            &remodulate(&demodulate(&color, &albedo), &albedo),
+            &color
+        );
+    }
+
+    #[test]
+    fn relative_difference_is_symmetric() {
+        assert_eq!(relative_difference(2.0, 4.0), 0.5);
+        assert_eq!(relative_difference(4.0, 2.0), 0.5);
+        assert_eq!(relative_difference(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn identical_features_have_full_weight() {
+        assert_eq!(edge_weight(0.0, 0.1), 1.0);
+        assert!(edge_weight(0.01, 0.1) > edge_weight(0.04, 0.1));
+    }
+}
@@ -0,0 +1,97 @@
+//! Splitting a render across processes. Every sample index has its own
+//! deterministic RNG stream, so a render can be divided into disjoint sample
+//! ranges which are rendered independently (see `Camera::render_samples`)
+//! and later merged. As with checkpointing, the merged image is deterministic
+//! for a given set of ranges, but is not identical to rendering every sample
+//! in one pass.
+
+use std::{
+    io::{Read, Write},
+    ops::Range,
+};
+
+use crate::camera::Canvas;
+
+/// Splits `total` samples into at most `parts` contiguous, non-empty ranges
+/// whose sizes differ by at most one.
+pub fn partition_samples(total: usize, parts: usize) -> Vec<Range<usize>> {
+    let parts = parts.clamp(1, total.max(1));
+    let base = total / parts;
+    let remainder = total % parts;
+
+    let mut start = 0;
+    (0..parts)
+        .map(|n| {
+            let len = base + usize::from(n < remainder);
+            let range = start..start + len;
+            start += len;
+            range
+        })
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
+/// Version of the canvas format, written before every serialized canvas.
+/// Bump it whenever `Canvas`'s fields change, so that old files are rejected
+/// rather than misread. Canvases from before version 2 have no version number
+/// and no sample weights, and can't be loaded. Version 3 made the first hit
+/// features optional.
+const CANVAS_FORMAT_VERSION: u32 = 3;
+
+/// Serializes a canvas in the same format used for render checkpoints.
+pub fn write_canvas(canvas: &Canvas, mut out: impl Write) -> bincode::Result<()> {
+    bincode::serialize_into(&mut out, &CANVAS_FORMAT_VERSION)?;
+    bincode::serialize_into(out, canvas)
+}
+
+/// Reads a canvas written by `write_canvas`. Fails if it was written in another
+/// version of the format.
+pub fn read_canvas(mut input: impl Read) -> bincode::Result<Canvas> {
+    let version: u32 = bincode::deserialize_from(&mut input)?;
+    if version != CANVAS_FORMAT_VERSION {
+        return Err(Box::new(bincode::ErrorKind::Custom(format!(
+            "canvas format version {} is not supported, expected {}",
+            version, CANVAS_FORMAT_VERSION
+        ))));
+    }
+    bincode::deserialize_from(input)
+}
+
+/// Merges any number of canvases rendered from disjoint sample ranges of the
+/// same scene. Returns `None` if there is nothing to merge.
+pub fn merge_all(canvases: impl IntoIterator<Item = Canvas>) -> Option<Canvas> {
+    canvases.into_iter().reduce(|acc, c| acc.merge(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partitioning_samples_evenly() {
+        assert_eq!(partition_samples(10, 2), vec![0..5, 5..10]);
+    }
+
+    #[test]
+    fn partitioning_samples_with_remainder() {
+        assert_eq!(partition_samples(10, 3), vec![0..4, 4..7, 7..10]);
+    }
+
+    #[test]
+    fn partitioning_into_more_parts_than_samples() {
+        assert_eq!(partition_samples(2, 5), vec![0..1, 1..2]);
+    }
+
+    #[test]
+    fn partitioning_no_samples() {
+        assert!(partition_samples(0, 3).is_empty());
+    }
+
+    #[test]
+    fn canvases_from_other_format_versions_are_rejected() {
+        let mut bytes = Vec::new();
+        bincode::serialize_into(&mut bytes, &(CANVAS_FORMAT_VERSION + 1)).unwrap();
+
+        assert!(read_canvas(bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    aabb::AABB,
+    animation::{Keyframes, Lerp},
+    interval::Interval,
+    material::Material,
+    matrix::Matrix3,
+    ray::Ray,
+    vec3::{NormalizedVec3, Vec3},
+};
+
+use super::{transform, HitRecord, Hittable};
+
+/// Upper bound on how far the rotation may change between the poses sampled
+/// when bounding an animated hittable's motion.
+const MAX_ROTATION_STEP_DEGREES: f64 = 2.0;
+
+/// Time step used to estimate how fast a point on an animated hittable is moving
+const VELOCITY_TIME_STEP: f64 = 1e-4;
+
+/// The pose of an `Animated` hittable at one point in time. The hittable is
+/// scaled, then rotated about the X, Y and Z axes (in that order), then translated.
+#[derive(Debug, Clone)]
+pub struct TransformKey {
+    pub translation: Vec3,
+    /// Counter-clockwise rotation about each axis, in degrees
+    pub rotation: Vec3,
+    pub scale: Vec3,
+}
+
+impl TransformKey {
+    /// Returns the linear (rotation and scale) part of the transform and its inverse
+    fn matrices(&self) -> (Matrix3, Matrix3) {
+        let rotation = &Matrix3::rotation_z(self.rotation.z())
+            * &(&Matrix3::rotation_y(self.rotation.y()) * &Matrix3::rotation_x(self.rotation.x()));
+        let scale = Matrix3::scaling(self.scale.x(), self.scale.y(), self.scale.z());
+        let inv_scale = Matrix3::scaling(
+            1.0 / self.scale.x(),
+            1.0 / self.scale.y(),
+            1.0 / self.scale.z(),
+        );
+
+        (&rotation * &scale, &inv_scale * &rotation.transpose())
+    }
+}
+
+impl Default for TransformKey {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zero(),
+            rotation: Vec3::zero(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Lerp for TransformKey {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        TransformKey {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: self.rotation.lerp(&other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+
+/// A hittable whose transform changes over time, for motion blur. The pose at
+/// a ray's time is interpolated from keyframes, and the bounding box covers
+/// the whole motion.
+#[derive(Clone)]
+pub struct Animated<H> {
+    object: H,
+    keyframes: Keyframes<TransformKey>,
+    bbox: AABB,
+}
+
+impl<M: Material, H: Hittable<Material = M>> Animated<H> {
+    pub fn new(object: H, keyframes: Keyframes<TransformKey>) -> Self {
+        let bbox = motion_bbox(&object.bounding_box(), &keyframes);
+        Animated {
+            object,
+            keyframes,
+            bbox,
+        }
+    }
+}
+
+/// Bounds an object's box over every pose of an animation. Between keyframes
+/// the pose is sampled finely enough that rotation changes by at most
+/// `MAX_ROTATION_STEP_DEGREES`, and each sampled box is padded by the furthest
+/// a rotating corner can stray from the straight line between two samples.
+fn motion_bbox(object_bbox: &AABB, keyframes: &Keyframes<TransformKey>) -> AABB {
+    let frames = keyframes.frames();
+
+    let mut times = vec![frames[0].0];
+    let mut max_step_degrees: f64 = 0.0;
+    for pair in frames.windows(2) {
+        let ((t0, k0), (t1, k1)) = (&pair[0], &pair[1]);
+        let rotation_change = &k1.rotation - &k0.rotation;
+        let total_degrees =
+            rotation_change.x().abs() + rotation_change.y().abs() + rotation_change.z().abs();
+        let steps = (total_degrees / MAX_ROTATION_STEP_DEGREES).ceil().max(1.0) as usize;
+
+        max_step_degrees = max_step_degrees.max(total_degrees / steps as f64);
+        times.extend((1..=steps).map(|n| t0 + (t1 - t0) * n as f64 / steps as f64));
+    }
+
+    let corner_radius = [object_bbox.x(), object_bbox.y(), object_bbox.z()]
+        .iter()
+        .map(|i| i.min.abs().max(i.max.abs()).powi(2))
+        .sum::<f64>()
+        .sqrt();
+
+    times
+        .into_iter()
+        .map(|t| {
+            let key = keyframes.at(t);
+            let (linear, _) = key.matrices();
+            let max_scale = key
+                .scale
+                .x()
+                .abs()
+                .max(key.scale.y().abs())
+                .max(key.scale.z().abs());
+            let sagitta = corner_radius
+                * max_scale
+                * (1.0 - f64::cos(f64::to_radians(max_step_degrees / 2.0)));
+
+            let posed = &transform::transform_bbox(object_bbox, &linear) + &key.translation;
+            AABB::new(
+                posed.x().expand(2.0 * sagitta),
+                posed.y().expand(2.0 * sagitta),
+                posed.z().expand(2.0 * sagitta),
+            )
+        })
+        .fold(AABB::empty(), |acc, b| acc.merge(&b))
+}
+
+impl<M: Material, H: Hittable<Material = M>> Hittable for Animated<H> {
+    type Material = M;
+
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        let key = self.keyframes.at(r.time);
+        let (linear, inv_linear) = key.matrices();
+
+        // Transform incoming ray from world space to object space at the ray's time
+        let origin = &inv_linear * &(&r.origin - &key.translation);
+        let direction = &inv_linear * &r.direction;
+        let transformed_r = Ray::new_at_time(origin, direction, r.time);
+
+        self.object
+            .hit(&transformed_r, ray_t)
+            .map(|(m, mut hit_rec)| {
+                // Where the hit point is a moment later, following the animation
+                let later_key = self.keyframes.at(r.time + VELOCITY_TIME_STEP);
+                let (later_linear, _) = later_key.matrices();
+                let later_p = &later_linear * &hit_rec.p + &later_key.translation;
+
+                hit_rec.p = &linear * &hit_rec.p + &key.translation;
+                hit_rec.velocity =
+                    &linear * &hit_rec.velocity + (later_p - &hit_rec.p) / VELOCITY_TIME_STEP;
+                hit_rec.normal =
+                    NormalizedVec3::try_from(&inv_linear.transpose() * &*hit_rec.normal)
+                        .expect("Animated normal couldn't be normalized after transformation");
+
+                (m, hit_rec)
+            })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::Flat,
+        vec3::Point3,
+    };
+
+    use super::*;
+
+    fn unit_sphere() -> Sphere<Flat> {
+        Sphere {
+            center: Center::Stationary(Point3::zero()),
+            radius: 1.0,
+            material: Flat,
+        }
+    }
+
+    fn sliding_sphere() -> Animated<Sphere<Flat>> {
+        Animated::new(
+            unit_sphere(),
+            Keyframes::new(vec![
+                (0.0, TransformKey::default()),
+                (
+                    1.0,
+                    TransformKey {
+                        translation: Vec3::new(10.0, 0.0, 0.0),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        )
+    }
+
+    #[test]
+    fn hitting_an_animated_hittable_depends_on_time() {
+        let s = sliding_sphere();
+        let ray_at =
+            |time| Ray::new_at_time(Point3::new(5.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), time);
+
+        assert!(s.hit(&ray_at(0.0), &Interval::nonnegative()).is_none());
+        assert!(s.hit(&ray_at(1.0), &Interval::nonnegative()).is_none());
+
+        let hit = s.hit(&ray_at(0.5), &Interval::nonnegative()).unwrap().1;
+        assert_approx_eq!(f64, hit.t, 4.0);
+        assert_approx_eq!(&Vec3, &hit.p, &Point3::new(5.0, 0.0, 1.0));
+        assert_approx_eq!(&Vec3, &hit.normal, &Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hit_reports_velocity() {
+        let s = sliding_sphere();
+        let ray = Ray::new_at_time(Point3::new(5.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.5);
+
+        let hit = s.hit(&ray, &Interval::nonnegative()).unwrap().1;
+        assert_approx_eq!(
+            &Vec3,
+            &hit.velocity,
+            &Vec3::new(10.0, 0.0, 0.0),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn bounding_box_covers_translation() {
+        let bbox = sliding_sphere().bounding_box();
+
+        assert_approx_eq!(f64, bbox.x().min, -1.0);
+        assert_approx_eq!(f64, bbox.x().max, 11.0);
+        assert_approx_eq!(f64, bbox.y().min, -1.0);
+        assert_approx_eq!(f64, bbox.y().max, 1.0);
+    }
+
+    #[test]
+    fn bounding_box_covers_rotation() {
+        // An offset sphere swept a quarter turn around the Y-axis
+        let offset_sphere = unit_sphere().translate(Vec3::new(5.0, 0.0, 0.0));
+        let spinning = Animated::new(
+            offset_sphere,
+            Keyframes::new(vec![
+                (0.0, TransformKey::default()),
+                (
+                    1.0,
+                    TransformKey {
+                        rotation: Vec3::new(0.0, 90.0, 0.0),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        );
+        let bbox = spinning.bounding_box();
+
+        // The sphere's furthest point passes through (6 cos 45°, 0, -6 sin 45°)
+        let furthest = 6.0 * std::f64::consts::FRAC_1_SQRT_2;
+        assert!(bbox.x().max >= 6.0 && bbox.x().min <= -1.0);
+        assert!(bbox.z().min <= -6.0 && bbox.z().max >= 1.0);
+        assert!(bbox.x().contains(furthest) && bbox.z().contains(-furthest));
+    }
+
+    #[test]
+    fn scaled_and_rotated_normals() {
+        let stretched = Animated::new(
+            unit_sphere(),
+            Keyframes::constant(TransformKey {
+                rotation: Vec3::new(0.0, 0.0, 90.0),
+                scale: Vec3::new(2.0, 1.0, 1.0),
+                ..Default::default()
+            }),
+        );
+
+        // After rotation, the stretched X-axis points along Y
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = stretched.hit(&ray, &Interval::nonnegative()).unwrap().1;
+
+        assert_approx_eq!(f64, hit.t, 3.0);
+        assert_approx_eq!(&Vec3, &hit.normal, &Vec3::new(0.0, 1.0, 0.0));
+    }
+}
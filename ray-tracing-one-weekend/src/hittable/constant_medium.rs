@@ -1,4 +1,11 @@
-use crate::{aabb::AABB, interval::Interval, material::Material, ray::Ray, vec3::NormalizedVec3};
+use crate::{
+    aabb::AABB,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats::{self, Counter},
+    vec3::NormalizedVec3,
+};
 
 use super::{Face, HitRecord, Hittable};
 
@@ -25,6 +32,7 @@ impl<MOUT: Material, M: Material, H: Hittable<Material = M>> Hittable for Consta
     type Material = MOUT;
 
     fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        stats::count(Counter::ConstantMediumTests);
         self.boundary
             .hit(r, &Interval::universe())
             .and_then(|(_, rec1)| {
@@ -66,6 +74,8 @@ impl<MOUT: Material, M: Material, H: Hittable<Material = M>> Hittable for Consta
                             t,
                             uv: (0.0, 0.0),    // arbitrary
                             face: Face::Front, // arbitrary
+                            velocity: rec1.velocity.clone(),
+                            object_id: rec1.object_id,
                         };
 
                         Some((&self.phase_function, hitrecord))
@@ -4,6 +4,7 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats::{self, Counter},
     vec3::{NormalizedVec3, Point3, Vec3},
 };
 
@@ -78,12 +79,10 @@ impl<M: Material> Plane<M> {
             }
         }
     }
-}
-
-impl<M: Material> Hittable for Plane<M> {
-    type Material = M;
 
-    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+    /// Hits the whole plane, for `Quad` to restrict to its own shape. Not
+    /// counted as a plane intersection test.
+    pub(super) fn hit_plane(&self, r: &Ray, ray_t: &Interval) -> Option<(&M, HitRecord)> {
         self.hit_ab(r, ray_t).map(|pi| {
             let (oriented_normal, face) = hittable::calculate_face_normal(r, self.normal.clone());
 
@@ -93,11 +92,22 @@ impl<M: Material> Hittable for Plane<M> {
                 t: pi.t,
                 uv: (pi.alpha, pi.beta),
                 face,
+                velocity: Vec3::zero(),
+                object_id: 0,
             };
 
             (&self.material, record)
         })
     }
+}
+
+impl<M: Material> Hittable for Plane<M> {
+    type Material = M;
+
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        stats::count(Counter::PlaneTests);
+        self.hit_plane(r, ray_t)
+    }
 
     fn bounding_box(&self) -> AABB {
         AABB::universe()
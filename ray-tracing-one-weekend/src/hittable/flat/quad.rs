@@ -4,6 +4,7 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats::{self, Counter},
     vec3::{Point3, Vec3},
 };
 
@@ -35,7 +36,8 @@ impl<M: Material> Hittable for Quad<M> {
     type Material = M;
 
     fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
-        self.plane.hit(r, ray_t).filter(|hit| {
+        stats::count(Counter::QuadTests);
+        self.plane.hit_plane(r, ray_t).filter(|hit| {
             let (alpha, beta) = hit.1.uv;
             0.0 <= alpha && alpha <= 1.0 && 0.0 <= beta && beta <= 1.0
         })
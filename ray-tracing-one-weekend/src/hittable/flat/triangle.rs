@@ -4,14 +4,17 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats::{self, Counter},
     vec3::{NormalizedVec3, Point3, Vec3},
 };
 
-use super::plane::Plane;
-
 #[derive(Clone)]
 pub struct Triangle<M> {
-    plane: Plane<M>,
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: NormalizedVec3,
+    material: M,
     bbox: AABB,
     normals: Option<[Vec3; 3]>,
     texture_coords: Option<[(f64, f64); 3]>,
@@ -42,11 +45,15 @@ impl<M> Triangle<M> {
 
         let u = &p2 - &p1;
         let v = &p3 - &p1;
-
-        let plane = Plane::new(p1, u, v, material);
+        let normal = NormalizedVec3::try_from(u.cross(&v))
+            .expect("Failed to find normal because u and v were parallel");
 
         Self {
-            plane,
+            q: p1,
+            u,
+            v,
+            normal,
+            material,
             bbox,
             normals,
             texture_coords,
@@ -58,43 +65,96 @@ impl<M: Material> Hittable for Triangle<M> {
     type Material = M;
 
     fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
-        self.plane
-            .hit(r, ray_t)
-            .filter(|hit| {
-                let (alpha, beta) = hit.1.uv;
-                0.0 <= alpha && 0.0 <= beta && alpha + beta <= 1.0
-            })
-            .map(|mut hit| {
-                let (u, v) = hit.1.uv;
-                let frac2 = u;
-                let frac3 = v;
-                let frac1 = 1.0 - u - v;
-
-                match &self.normals {
-                    Some([v1, v2, v3]) => {
-                        let normal =
-                            NormalizedVec3::try_from(v2 * frac2 + v3 * frac3 + v1 * frac1).unwrap();
-                        let (oriented_normal, face) = hittable::calculate_face_normal(&r, normal);
-                        hit.1.normal = oriented_normal;
-                        hit.1.face = face;
-                    }
-                    None => (),
-                };
-
-                match &self.texture_coords {
-                    Some([t1, t2, t3]) => {
-                        let new_u = t1.0 * frac1 + t2.0 * frac2 + t3.0 * frac3;
-                        let new_v = t1.1 * frac1 + t2.1 * frac2 + t3.1 * frac3;
-                        hit.1.uv = (new_u, new_v)
-                    }
-                    None => (),
-                };
-
-                hit
-            })
+        let (t, alpha, beta) = hit_uv(r, ray_t, &self.q, &self.u, &self.v)?;
+        let normal = match &self.normals {
+            Some([v1, v2, v3]) => interpolate_normal([v1, v2, v3], alpha, beta),
+            None => self.normal.clone(),
+        };
+        let texture_coords = self.texture_coords.as_ref().map(|ts| ts.each_ref());
+
+        Some((
+            &self.material,
+            hit_record(r, t, (alpha, beta), normal, texture_coords),
+        ))
     }
 
     fn bounding_box(&self) -> AABB {
         self.bbox.clone()
     }
 }
+
+/// Where a ray hits the triangle with corner q and sides u and v, as the
+/// distance along the ray and the weights of the corners at the ends of u and
+/// v. `Mesh` shares this with `Triangle`, so that the two hit identically.
+pub(crate) fn hit_uv(
+    r: &Ray,
+    ray_t: &Interval,
+    q: &Point3,
+    u: &Vec3,
+    v: &Vec3,
+) -> Option<(f64, f64, f64)> {
+    stats::count(Counter::TriangleTests);
+    let dir_cross_v = r.direction.cross(v);
+    let det = u.dot(&dir_cross_v);
+
+    // No hits if the ray is parallel to the triangle
+    if det.abs() < 1e-8 {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let q_to_origin = &r.origin - q;
+    let alpha = f * q_to_origin.dot(&dir_cross_v);
+    if !(0.0..=1.0).contains(&alpha) {
+        return None;
+    }
+
+    let origin_cross_u = q_to_origin.cross(u);
+    let beta = f * r.direction.dot(&origin_cross_u);
+    if beta < 0.0 || alpha + beta > 1.0 {
+        return None;
+    }
+
+    let t = f * v.dot(&origin_cross_u);
+    ray_t.contains(t).then_some((t, alpha, beta))
+}
+
+/// Blends the normals at a triangle's corners, weighting the second and third
+/// by alpha and beta
+pub(crate) fn interpolate_normal(
+    [v1, v2, v3]: [&Vec3; 3],
+    alpha: f64,
+    beta: f64,
+) -> NormalizedVec3 {
+    NormalizedVec3::try_from(v2 * alpha + v3 * beta + v1 * (1.0 - alpha - beta)).unwrap()
+}
+
+/// The record of a hit found by `hit_uv`. Its uv are the texture coordinates
+/// interpolated from the corners if there are any, or alpha and beta if not.
+pub(crate) fn hit_record(
+    r: &Ray,
+    t: f64,
+    (alpha, beta): (f64, f64),
+    outward_normal: NormalizedVec3,
+    texture_coords: Option<[&(f64, f64); 3]>,
+) -> HitRecord {
+    let frac1 = 1.0 - alpha - beta;
+    let uv = match texture_coords {
+        Some([t1, t2, t3]) => (
+            t1.0 * frac1 + t2.0 * alpha + t3.0 * beta,
+            t1.1 * frac1 + t2.1 * alpha + t3.1 * beta,
+        ),
+        None => (alpha, beta),
+    };
+    let (normal, face) = hittable::calculate_face_normal(r, outward_normal);
+
+    HitRecord {
+        p: r.at(t),
+        normal,
+        t,
+        uv,
+        face,
+        velocity: Vec3::zero(),
+        object_id: 0,
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{
+    aabb::AABB,
+    bvh::Bvh,
+    hittable::{flat::triangle, HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    vec3::{NormalizedVec3, Point3, Vec3},
+};
+
+/// A triangle mesh whose faces index into shared lists of vertices, texture
+/// coordinates and normals, rather than each owning copies like `Triangle`.
+/// Faces are kept in their own BVH, so a mesh can be hit as a single object.
+/// One material covers the whole mesh.
+#[derive(Clone)]
+pub struct Mesh<M> {
+    vertices: Vec<Point3>,
+    texture_coords: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    /// `None` if every face was dropped
+    faces: Option<Bvh<MeshFace>>,
+    face_count: usize,
+    material: M,
+}
+
+/// The indices of a triangle's corners into a mesh's lists. Texture
+/// coordinates and normals are interpolated the same way as in `Triangle`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshFace {
+    pub vertices: [u32; 3],
+    pub texture_coords: Option<[u32; 3]>,
+    pub normals: Option<[u32; 3]>,
+}
+
+/// A face referring to a vertex, texture coordinate or normal which isn't in
+/// the mesh's lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFace(pub MeshFace);
+
+impl Display for InvalidFace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mesh face {:?} refers to data outside the mesh", self.0)
+    }
+}
+
+impl Error for InvalidFace {}
+
+impl<M> Mesh<M> {
+    /// Creates a mesh, dropping faces with no area
+    pub fn new(
+        vertices: Vec<Point3>,
+        texture_coords: Vec<(f64, f64)>,
+        normals: Vec<Vec3>,
+        faces: Vec<MeshFace>,
+        material: M,
+    ) -> Result<Self, InvalidFace> {
+        let in_range = |indices: &[u32; 3], len: usize| indices.iter().all(|&i| (i as usize) < len);
+        if let Some(face) = faces.iter().find(|face| {
+            !(in_range(&face.vertices, vertices.len())
+                && face
+                    .texture_coords
+                    .as_ref()
+                    .is_none_or(|t| in_range(t, texture_coords.len()))
+                && face
+                    .normals
+                    .as_ref()
+                    .is_none_or(|n| in_range(n, normals.len())))
+        }) {
+            return Err(InvalidFace(face.clone()));
+        }
+
+        let faces = faces
+            .into_iter()
+            .filter(|face| {
+                let [p1, p2, p3] = face.vertices.map(|i| &vertices[i as usize]);
+                (p2 - p1).cross(&(p3 - p1)).length_squared() > 0.0
+            })
+            .collect::<Vec<_>>();
+        let face_count = faces.len();
+        let faces = (!faces.is_empty()).then(|| {
+            Bvh::with_bounds(faces, &|face: &MeshFace| {
+                AABB::from_points(&face.vertices.map(|i| &vertices[i as usize]))
+            })
+        });
+
+        Ok(Mesh {
+            vertices,
+            texture_coords,
+            normals,
+            faces,
+            face_count,
+            material,
+        })
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.face_count
+    }
+
+    /// Hits one face with the same code as `Triangle`, so meshes and triangles
+    /// shade identically
+    fn hit_face(&self, face: &MeshFace, r: &Ray, ray_t: &Interval) -> Option<HitRecord> {
+        let [p1, p2, p3] = face.vertices.map(|i| &self.vertices[i as usize]);
+        let (u, v) = (p2 - p1, p3 - p1);
+        let (t, alpha, beta) = triangle::hit_uv(r, ray_t, p1, &u, &v)?;
+
+        let normal = match face.normals {
+            Some(ns) => {
+                triangle::interpolate_normal(ns.map(|i| &self.normals[i as usize]), alpha, beta)
+            }
+            None => NormalizedVec3::try_from(u.cross(&v)).unwrap(),
+        };
+        let texture_coords = face
+            .texture_coords
+            .map(|ts| ts.map(|i| &self.texture_coords[i as usize]));
+
+        Some(triangle::hit_record(
+            r,
+            t,
+            (alpha, beta),
+            normal,
+            texture_coords,
+        ))
+    }
+}
+
+impl<M: Material> Hittable for Mesh<M> {
+    type Material = M;
+
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        let rec = self
+            .faces
+            .as_ref()?
+            .hit_with(r, ray_t, &|face, ray_t| self.hit_face(face, r, ray_t))?;
+        Some((&self.material, rec))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.faces
+            .as_ref()
+            .map_or(AABB::empty(), |faces| faces.root_bounding_box())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{bvh::Bvh, hittable::flat::triangle::Triangle, material::Flat};
+
+    use super::*;
+
+    /// A grid of quads in the XY plane, split into triangles
+    fn grid(size: u32) -> (Vec<Point3>, Vec<MeshFace>) {
+        let mut vertices = Vec::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                vertices.push(Point3::new(x as f64, y as f64, 0.0));
+            }
+        }
+        let index = |x: u32, y: u32| y * (size + 1) + x;
+        let mut faces = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let face = |vertices| MeshFace {
+                    vertices,
+                    texture_coords: None,
+                    normals: None,
+                };
+                faces.push(face([index(x, y), index(x + 1, y), index(x + 1, y + 1)]));
+                faces.push(face([index(x, y), index(x + 1, y + 1), index(x, y + 1)]));
+            }
+        }
+        (vertices, faces)
+    }
+
+    #[test]
+    fn mesh_hits_match_separate_triangles() {
+        let (vertices, faces) = grid(4);
+        let triangles = faces
+            .iter()
+            .map(|f| {
+                let points = f.vertices.map(|i| vertices[i as usize].clone());
+                Triangle::from_model(points, None, None, Flat)
+            })
+            .collect::<Vec<_>>();
+        let mesh = Mesh::new(vertices, Vec::new(), Vec::new(), faces, Flat).unwrap();
+
+        for (x, y) in [(0.3, 0.1), (1.9, 3.2), (2.5, 2.5), (3.99, 0.01), (4.5, 1.0)] {
+            let ray = Ray::new(Point3::new(x, y, -2.0), Vec3::new(0.1, 0.0, 1.0));
+            let from_mesh = mesh.hit(&ray, &Interval::nonnegative()).map(|(_, rec)| rec);
+            let from_triangles = triangles
+                .as_slice()
+                .hit(&ray, &Interval::nonnegative())
+                .map(|(_, rec)| rec);
+
+            match (from_mesh, from_triangles) {
+                (Some(m), Some(t)) => {
+                    assert_eq!(m.p, t.p);
+                    assert_eq!(*m.normal, *t.normal);
+                    assert_eq!(m.uv, t.uv);
+                    assert_eq!(m.face, t.face);
+                }
+                (None, None) => (),
+                _ => panic!("mesh and triangles disagree on a hit at ({}, {})", x, y),
+            }
+        }
+    }
+
+    #[test]
+    fn instances_share_one_mesh() {
+        let (vertices, faces) = grid(2);
+        let mesh = Arc::new(Mesh::new(vertices, Vec::new(), Vec::new(), faces, Flat).unwrap());
+        let instances = (0..10)
+            .map(|i| mesh.clone().translate(Vec3::new(0.0, 0.0, i as f64)))
+            .collect::<Vec<_>>();
+        let world = Bvh::new(instances);
+        assert_eq!(Arc::strong_count(&mesh), 11);
+
+        let ray = Ray::new(Point3::new(0.5, 0.5, 20.0), Vec3::new(0.0, 0.0, -1.0));
+        let (_, rec) = world.hit(&ray, &Interval::nonnegative()).unwrap();
+
+        assert_eq!(rec.p, Point3::new(0.5, 0.5, 9.0));
+    }
+
+    #[test]
+    fn faces_with_no_area_are_dropped() {
+        let (vertices, mut faces) = grid(1);
+        faces.push(MeshFace {
+            vertices: [0, 1, 1],
+            texture_coords: None,
+            normals: None,
+        });
+
+        let mesh = Mesh::new(vertices, Vec::new(), Vec::new(), faces, Flat).unwrap();
+
+        assert_eq!(mesh.face_count(), 2);
+    }
+
+    #[test]
+    fn faces_must_refer_to_existing_vertices() {
+        let (vertices, _) = grid(1);
+        let face = MeshFace {
+            vertices: [0, 1, 9],
+            texture_coords: None,
+            normals: None,
+        };
+
+        let result = Mesh::new(vertices, Vec::new(), Vec::new(), vec![face.clone()], Flat);
+
+        assert_eq!(result.err(), Some(InvalidFace(face)));
+    }
+}
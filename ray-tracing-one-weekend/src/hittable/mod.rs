@@ -1,14 +1,22 @@
+pub mod animated;
 pub mod constant_medium;
 pub mod flat;
+pub mod mesh;
 pub mod sphere;
+pub mod tagged;
 pub mod transform;
 pub mod translate;
 
+use std::sync::Arc;
+
+use animated::{Animated, TransformKey};
+use tagged::Tagged;
 use transform::Transform;
 use translate::Translate;
 
 use crate::{
     aabb::AABB,
+    animation::Keyframes,
     interval::Interval,
     material::Material,
     ray::Ray,
@@ -27,6 +35,10 @@ pub struct HitRecord {
     pub t: f64,
     pub uv: (f64, f64),
     pub face: Face,
+    /// How fast the surface is moving at the hit point, in world units per unit of time
+    pub velocity: Vec3,
+    /// Set by the nearest enclosing `Tagged` hittable, or 0 if there isn't one
+    pub object_id: u32,
 }
 
 pub fn calculate_face_normal(r: &Ray, outward_normal: NormalizedVec3) -> (NormalizedVec3, Face) {
@@ -83,6 +95,23 @@ pub trait Hittable {
     {
         Transform::scale(self, scale)
     }
+
+    /// Moves the hittable over time between keyframed poses, for motion blur
+    fn animate(self, keyframes: Keyframes<TransformKey>) -> Animated<Self>
+    where
+        Self: Sized,
+        Self::Material: Material,
+    {
+        Animated::new(self, keyframes)
+    }
+
+    /// Gives the hittable an ID, which is reported in the object ID pass
+    fn tagged(self, id: u32) -> Tagged<Self>
+    where
+        Self: Sized,
+    {
+        Tagged { object: self, id }
+    }
 }
 
 impl<M, H: Hittable<Material = M>> Hittable for [H] {
@@ -146,6 +175,19 @@ impl<M, H: Hittable<Material = M> + ?Sized> Hittable for Box<H> {
     }
 }
 
+/// Lets many hittables, such as `Transform` instances, share one object
+impl<M, H: Hittable<Material = M> + ?Sized> Hittable for Arc<H> {
+    type Material = M;
+
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        (**self).hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        (**self).bounding_box()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::vec3::Vec3;
@@ -4,6 +4,7 @@ use crate::{
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats::{self, Counter},
     vec3::{NormalizedVec3, Point3, Vec3},
 };
 
@@ -27,11 +28,19 @@ impl<M> Sphere<M> {
             Center::Moving(p1, p2) => p1 + time * (p2 - p1),
         }
     }
+
+    fn velocity(&self) -> Vec3 {
+        match &self.center {
+            Center::Stationary(_) => Vec3::zero(),
+            Center::Moving(p1, p2) => p2 - p1,
+        }
+    }
 }
 
 impl<M: Material> Hittable for Sphere<M> {
     type Material = M;
     fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&M, HitRecord)> {
+        stats::count(Counter::SphereTests);
         let center = self.center(r.time);
         let oc = &r.origin - &center;
         let a = r.direction.length_squared();
@@ -68,6 +77,8 @@ impl<M: Material> Hittable for Sphere<M> {
                         t,
                         face,
                         uv: get_sphere_uv(&outward_normal),
+                        velocity: self.velocity(),
+                        object_id: 0,
                     },
                 )
             })
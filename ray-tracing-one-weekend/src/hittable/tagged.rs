@@ -0,0 +1,84 @@
+use crate::{aabb::AABB, interval::Interval, ray::Ray};
+
+use super::{HitRecord, Hittable};
+
+/// A hittable with an ID, for telling objects apart in the object ID pass.
+/// Tagging a group of hittables gives all of them the same ID, unless they
+/// have their own.
+#[derive(Clone)]
+pub struct Tagged<H> {
+    pub object: H,
+    /// Should be non-zero, as 0 means an object has no ID
+    pub id: u32,
+}
+
+impl<M, H: Hittable<Material = M>> Hittable for Tagged<H> {
+    type Material = M;
+
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> Option<(&Self::Material, HitRecord)> {
+        self.object.hit(r, ray_t).map(|(m, mut hit_rec)| {
+            if hit_rec.object_id == 0 {
+                hit_rec.object_id = self.id;
+            }
+            (m, hit_rec)
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.object.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hittable::sphere::{Center, Sphere},
+        material::Flat,
+        vec3::{Point3, Vec3},
+    };
+
+    use super::*;
+
+    fn sphere_at(z: f64) -> Sphere<Flat> {
+        Sphere {
+            center: Center::Stationary(Point3::new(0.0, 0.0, z)),
+            radius: 1.0,
+            material: Flat,
+        }
+    }
+
+    fn object_id_seen<H: Hittable>(world: &H) -> Option<u32> {
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let interval = Interval {
+            min: 0.0,
+            max: f64::INFINITY,
+        };
+        world.hit(&ray, &interval).map(|(_, rec)| rec.object_id)
+    }
+
+    #[test]
+    fn untagged_objects_have_no_id() {
+        assert_eq!(object_id_seen(&sphere_at(-5.0)), Some(0));
+    }
+
+    #[test]
+    fn tagged_object_reports_its_id() {
+        let world = [sphere_at(-5.0).tagged(1), sphere_at(-10.0).tagged(2)];
+
+        assert_eq!(object_id_seen(&world), Some(1));
+    }
+
+    #[test]
+    fn tagging_a_group_tags_its_members() {
+        let world = [sphere_at(-5.0), sphere_at(-10.0)].tagged(3);
+
+        assert_eq!(object_id_seen(&world), Some(3));
+    }
+
+    #[test]
+    fn innermost_tag_wins() {
+        let world = [sphere_at(-5.0).tagged(1)].tagged(2);
+
+        assert_eq!(object_id_seen(&world), Some(1));
+    }
+}
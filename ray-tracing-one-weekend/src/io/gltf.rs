@@ -0,0 +1,499 @@
+use std::path::Path;
+
+use ::gltf::{
+    camera::Projection as GltfProjection,
+    image::{Data as ImageData, Format},
+    khr_lights_punctual::Kind,
+    material::AlphaMode,
+    mesh::Mode,
+    Document,
+};
+use image::Rgb32FImage;
+
+use crate::{
+    bvh::Bvh,
+    camera::{CameraParams, Projection},
+    color::{srgb, Color},
+    hittable::{flat::triangle::Triangle, transform::Transform, Hittable},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    matrix::Matrix3,
+    texture::{Image, SolidColor, Texture},
+    vec3::{Point3, Vec3},
+};
+
+/// Part of an imported scene, with materials borrowed from the `GltfScene`
+pub type SceneObject<'a> = Box<dyn Hittable<Material = &'a (dyn Material + Sync)> + Sync + 'a>;
+
+/// A glTF 2.0 asset converted into this renderer's types. Only the default
+/// scene (or the first scene, if there's no default) is imported.
+pub struct GltfScene {
+    materials: Vec<Box<dyn Material + Sync>>,
+    default_material: Box<dyn Material + Sync>,
+    /// The triangles of each glTF mesh, by mesh index
+    meshes: Vec<Vec<MeshTri>>,
+    roots: Vec<SceneNode>,
+    /// One for each node with a camera, positioned by the node's transform
+    pub cameras: Vec<CameraParams>,
+    /// One for each node with a `KHR_lights_punctual` light
+    pub lights: Vec<PunctualLight>,
+}
+
+/// A light from the `KHR_lights_punctual` extension. This renderer only has
+/// emissive surfaces, so these are left to the caller to stand in for, e.g.
+/// with a small sphere of `DiffuseLight`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PunctualLight {
+    pub kind: LightKind,
+    pub position: Point3,
+    /// The direction the light points in, for directional and spot lights
+    pub direction: Vec3,
+    /// The light's color scaled by its intensity
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    /// Cone angles are in radians
+    Spot {
+        inner_cone_angle: f64,
+        outer_cone_angle: f64,
+    },
+}
+
+struct MeshTri {
+    points: [Point3; 3],
+    texture_coords: Option<[(f64, f64); 3]>,
+    normals: Option<[Vec3; 3]>,
+    material: Option<usize>,
+}
+
+/// A node's local transform, which scales, then rotates, then translates
+struct SceneNode {
+    translation: Vec3,
+    /// Unit quaternion, as `[x, y, z, w]`
+    rotation: [f64; 4],
+    scale: Vec3,
+    mesh: Option<usize>,
+    children: Vec<SceneNode>,
+}
+
+/// Column-major, as in glTF
+type Matrix4 = [[f64; 4]; 4];
+
+impl GltfScene {
+    /// Imports a `.gltf` or `.glb` file, loading any external buffers and
+    /// images relative to it
+    pub fn import(path: impl AsRef<Path>) -> ::gltf::Result<Self> {
+        let (document, buffers, images) = ::gltf::import(path)?;
+        Ok(Self::new(&document, &buffers, &images))
+    }
+
+    /// Imports a `.glb` file, or a `.gltf` file whose buffers and images are
+    /// all embedded as data URIs
+    pub fn from_slice(data: &[u8]) -> ::gltf::Result<Self> {
+        let (document, buffers, images) = ::gltf::import_slice(data)?;
+        Ok(Self::new(&document, &buffers, &images))
+    }
+
+    fn new(document: &Document, buffers: &[::gltf::buffer::Data], images: &[ImageData]) -> Self {
+        let materials = document
+            .materials()
+            .map(|m| convert_material(&m, images))
+            .collect();
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    .filter(|p| p.mode() == Mode::Triangles)
+                    .flat_map(|p| read_triangles(&p, buffers))
+                    .collect()
+            })
+            .collect();
+
+        let mut scene = GltfScene {
+            materials,
+            default_material: Box::new(Lambertian {
+                texture: SolidColor {
+                    albedo: Color::new(0.8, 0.8, 0.8),
+                },
+            }),
+            meshes,
+            roots: Vec::new(),
+            cameras: Vec::new(),
+            lights: Vec::new(),
+        };
+
+        if let Some(s) = document.default_scene().or(document.scenes().next()) {
+            for node in s.nodes() {
+                scene.add_cameras_and_lights(&node, &IDENTITY);
+                let root = read_node(&node);
+                scene.roots.push(root);
+            }
+        }
+
+        scene
+    }
+
+    /// Builds the scene's meshes, with each node's transform wrapped around
+    /// its mesh and children. Returns `None` if there aren't any triangles.
+    pub fn to_object(&self) -> Option<SceneObject<'_>> {
+        let roots = self
+            .roots
+            .iter()
+            .filter_map(|n| self.build_node(n))
+            .collect::<Vec<_>>();
+        combine(roots)
+    }
+
+    fn build_node(&self, node: &SceneNode) -> Option<SceneObject<'_>> {
+        let mut parts = node
+            .children
+            .iter()
+            .filter_map(|n| self.build_node(n))
+            .collect::<Vec<_>>();
+        if let Some(tris) = node.mesh.and_then(|i| self.meshes.get(i)) {
+            if !tris.is_empty() {
+                let triangles = tris.iter().map(|t| self.to_triangle(t)).collect();
+                parts.push(Box::new(Bvh::new(triangles)));
+            }
+        }
+
+        let mut object = combine(parts)?;
+        if node.rotation != [0.0, 0.0, 0.0, 1.0] || node.scale != Vec3::new(1.0, 1.0, 1.0) {
+            let rotation = Matrix3::rotation_quaternion(node.rotation);
+            object = Box::new(Transform::rotate_scale(object, rotation, &node.scale));
+        }
+        if node.translation != Vec3::zero() {
+            object = Box::new(object.translate(node.translation.clone()));
+        }
+        Some(object)
+    }
+
+    fn to_triangle(&self, t: &MeshTri) -> Triangle<&(dyn Material + Sync)> {
+        let material = t
+            .material
+            .and_then(|i| self.materials.get(i))
+            .unwrap_or(&self.default_material);
+        Triangle::from_model(
+            t.points.clone(),
+            t.texture_coords,
+            t.normals.clone(),
+            &**material,
+        )
+    }
+
+    fn add_cameras_and_lights(&mut self, node: &::gltf::Node, parent: &Matrix4) {
+        let world = multiply(parent, &node.transform().matrix().map(|c| c.map(f64::from)));
+        let position = transform_point(&world, &Point3::zero());
+        // Cameras and lights look down their local -Z axis
+        let forward = transform_direction(&world, &Vec3::new(0.0, 0.0, -1.0));
+
+        if let Some(camera) = node.camera() {
+            let up = transform_direction(&world, &Vec3::new(0.0, 1.0, 0.0));
+            let (aspect_ratio, vfov, projection) = match camera.projection() {
+                GltfProjection::Perspective(p) => (
+                    p.aspect_ratio().map(f64::from),
+                    f64::from(p.yfov()).to_degrees(),
+                    Projection::Perspective,
+                ),
+                GltfProjection::Orthographic(o) => (
+                    Some(f64::from(o.xmag() / o.ymag())),
+                    CameraParams::default().vfov,
+                    Projection::Orthographic {
+                        height: 2.0 * f64::from(o.ymag()),
+                    },
+                ),
+            };
+            let defaults = CameraParams::default();
+            self.cameras.push(CameraParams {
+                aspect_ratio: aspect_ratio.unwrap_or(defaults.aspect_ratio),
+                vfov,
+                lookat: &position + &forward,
+                lookfrom: position.clone(),
+                vup: up,
+                projection,
+                ..defaults
+            });
+        }
+
+        if let Some(light) = node.light() {
+            let kind = match light.kind() {
+                Kind::Directional => LightKind::Directional,
+                Kind::Point => LightKind::Point,
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => LightKind::Spot {
+                    inner_cone_angle: f64::from(inner_cone_angle),
+                    outer_cone_angle: f64::from(outer_cone_angle),
+                },
+            };
+            let [r, g, b] = light.color().map(f64::from);
+            self.lights.push(PunctualLight {
+                kind,
+                position,
+                direction: forward,
+                color: f64::from(light.intensity()) * Color::new(r, g, b),
+            });
+        }
+
+        for child in node.children() {
+            self.add_cameras_and_lights(&child, &world);
+        }
+    }
+}
+
+fn read_node(node: &::gltf::Node) -> SceneNode {
+    let (translation, rotation, scale) = node.transform().decomposed();
+    let [tx, ty, tz] = translation.map(f64::from);
+    let [sx, sy, sz] = scale.map(f64::from);
+    SceneNode {
+        translation: Vec3::new(tx, ty, tz),
+        rotation: rotation.map(f64::from),
+        scale: Vec3::new(sx, sy, sz),
+        mesh: node.mesh().map(|m| m.index()),
+        children: node.children().map(|c| read_node(&c)).collect(),
+    }
+}
+
+fn combine(mut parts: Vec<SceneObject<'_>>) -> Option<SceneObject<'_>> {
+    match parts.len() {
+        0 => None,
+        1 => parts.pop(),
+        _ => Some(Box::new(Bvh::new(parts))),
+    }
+}
+
+fn read_triangles(primitive: &::gltf::Primitive, buffers: &[::gltf::buffer::Data]) -> Vec<MeshTri> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d.0[..]));
+
+    let Some(positions) = reader.read_positions() else {
+        return Vec::new();
+    };
+    let positions = positions
+        .map(|[x, y, z]| Point3::new(x.into(), y.into(), z.into()))
+        .collect::<Vec<_>>();
+    let normals = reader.read_normals().map(|ns| {
+        ns.map(|[x, y, z]| Vec3::new(x.into(), y.into(), z.into()))
+            .collect::<Vec<_>>()
+    });
+    // glTF puts the origin of texture space at the top left, rather than the bottom left
+    let texture_coords = reader.read_tex_coords(0).map(|tcs| {
+        tcs.into_f32()
+            .map(|[u, v]| (f64::from(u), 1.0 - f64::from(v)))
+            .collect::<Vec<_>>()
+    });
+    let indices = match reader.read_indices() {
+        Some(is) => is.into_u32().map(|i| i as usize).collect::<Vec<_>>(),
+        None => (0..positions.len()).collect(),
+    };
+
+    indices
+        .chunks_exact(3)
+        .filter(|is| is.iter().all(|&i| i < positions.len()))
+        .map(|is| {
+            let corners = [is[0], is[1], is[2]];
+            MeshTri {
+                points: corners.map(|i| positions[i].clone()),
+                texture_coords: texture_coords.as_ref().map(|tcs| corners.map(|i| tcs[i])),
+                normals: normals.as_ref().map(|ns| corners.map(|i| ns[i].clone())),
+                material: primitive.material().index(),
+            }
+        })
+        .collect()
+}
+
+/// Picks the closest material for a metallic-roughness material:
+/// - Anything with a non-black emission is a `DiffuseLight`
+/// - Anything transmissive or blended with an alpha below one is a `Dielectric`
+/// - Anything at least half metallic is a `Metal`, with roughness as fuzz
+/// - Everything else is `Lambertian`, using the base color texture if there
+///   is one
+fn convert_material(material: &::gltf::Material, images: &[ImageData]) -> Box<dyn Material + Sync> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor().map(f64::from);
+    let base_color = Color::new(r, g, b);
+
+    let [er, eg, eb] = material.emissive_factor().map(f64::from);
+    let strength = material.emissive_strength().map_or(1.0, f64::from);
+    if er > 0.0 || eg > 0.0 || eb > 0.0 {
+        return Box::new(DiffuseLight {
+            texture: SolidColor {
+                albedo: strength * Color::new(er, eg, eb),
+            },
+        });
+    }
+
+    let transmissive = material
+        .transmission()
+        .is_some_and(|t| t.transmission_factor() > 0.0);
+    if transmissive || (material.alpha_mode() == AlphaMode::Blend && alpha < 1.0) {
+        return Box::new(Dielectric {
+            refraction_index: material.ior().map_or(1.5, f64::from),
+        });
+    }
+
+    if pbr.metallic_factor() >= 0.5 {
+        return Box::new(Metal {
+            albedo: base_color,
+            fuzz: f64::from(pbr.roughness_factor()).clamp(0.0, 1.0),
+        });
+    }
+
+    let image = pbr
+        .base_color_texture()
+        .and_then(|info| images.get(info.texture().source().index()))
+        .and_then(to_linear_image);
+    let texture: Box<dyn Texture> = match image {
+        Some(image) => Box::new(Image { image }),
+        None => Box::new(SolidColor { albedo: base_color }),
+    };
+    Box::new(Lambertian { texture })
+}
+
+/// Converts 8-bit sRGB image data to linear. Other formats aren't supported.
+fn to_linear_image(data: &ImageData) -> Option<Rgb32FImage> {
+    let channels = match data.format {
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        _ => return None,
+    };
+    let pixels = data
+        .pixels
+        .chunks_exact(channels)
+        .flat_map(|p| [p[0], p[1], p[2]].map(|u| srgb::srgb_to_linear(f64::from(u) / 255.0) as f32))
+        .collect();
+    Rgb32FImage::from_raw(data.width, data.height, pixels)
+}
+
+const IDENTITY: Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut out = [[0.0; 4]; 4];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col][row] = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    out
+}
+
+fn transform_point(m: &Matrix4, p: &Point3) -> Point3 {
+    &transform_direction(m, p) + &Point3::new(m[3][0], m[3][1], m[3][2])
+}
+
+fn transform_direction(m: &Matrix4, v: &Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * v.x() + m[1][0] * v.y() + m[2][0] * v.z(),
+        m[0][1] * v.x() + m[1][1] * v.y() + m[2][1] * v.z(),
+        m[0][2] * v.x() + m[1][2] * v.y() + m[2][2] * v.z(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{interval::Interval, ray::Ray};
+
+    use super::*;
+
+    /// One triangle in the XY plane, placed twice: once by a node moved 5
+    /// along Z, and once by its child, which is scaled 2x and moved another 1
+    /// along Z. Also has a camera and a point light.
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2, 3] }],
+        "nodes": [
+            { "mesh": 0, "translation": [0, 0, 5], "children": [1] },
+            { "mesh": 0, "translation": [0, 0, 1], "scale": [2, 2, 2] },
+            { "camera": 0, "translation": [0, 1, 10] },
+            {
+                "extensions": { "KHR_lights_punctual": { "light": 0 } },
+                "translation": [0, 4, 0]
+            }
+        ],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{
+            "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0 }
+        }],
+        "cameras": [{
+            "type": "perspective",
+            "perspective": { "yfov": 0.7853981633974483, "aspectRatio": 1.5, "znear": 0.1 }
+        }],
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "point", "color": [1, 0.5, 0.5], "intensity": 10 }]
+            }
+        },
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn importing_meshes_with_node_transforms() {
+        let scene = GltfScene::from_slice(SCENE.as_bytes()).unwrap();
+        let object = scene.to_object().unwrap();
+
+        let hit_at = |x, y| {
+            let ray = Ray::new(Point3::new(x, y, -1.0), Vec3::new(0.0, 0.0, 1.0));
+            object
+                .hit(
+                    &ray,
+                    &Interval {
+                        min: 0.0,
+                        max: f64::INFINITY,
+                    },
+                )
+                .map(|(_, rec)| rec.p)
+        };
+
+        // The parent's triangle is in front of the child's
+        let near = hit_at(0.25, 0.25).unwrap();
+        assert_approx_eq!(f64, near.z(), 5.0);
+        // Only the scaled child covers this point
+        let far = hit_at(1.0, 0.5).unwrap();
+        assert_approx_eq!(f64, far.z(), 6.0);
+        assert!(hit_at(2.5, 0.25).is_none());
+    }
+
+    #[test]
+    fn importing_cameras_and_lights() {
+        let scene = GltfScene::from_slice(SCENE.as_bytes()).unwrap();
+
+        assert_eq!(scene.cameras.len(), 1);
+        let camera = &scene.cameras[0];
+        assert_eq!(camera.lookfrom, Point3::new(0.0, 1.0, 10.0));
+        assert_eq!(camera.lookat, Point3::new(0.0, 1.0, 9.0));
+        assert_eq!(camera.vup, Vec3::new(0.0, 1.0, 0.0));
+        assert_approx_eq!(f64, camera.vfov, 45.0, epsilon = 1e-5);
+        assert_eq!(camera.aspect_ratio, 1.5);
+
+        assert_eq!(
+            scene.lights,
+            [PunctualLight {
+                kind: LightKind::Point,
+                position: Point3::new(0.0, 4.0, 0.0),
+                direction: Vec3::new(0.0, 0.0, -1.0),
+                color: Color::new(10.0, 5.0, 5.0),
+            }]
+        );
+    }
+}
@@ -1 +1,6 @@
+pub mod gltf;
+pub mod mtl;
+pub mod ply;
+pub mod stl;
+pub mod subdivision;
 pub mod wavefront_obj;
@@ -0,0 +1,258 @@
+use std::{collections::HashMap, io::BufRead, path::Path};
+
+use image::ImageResult;
+
+use crate::{
+    color::Color,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    texture::{Image, SolidColor, Texture},
+};
+
+/// A material from an MTL file, with only the properties the file sets
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MtlMaterial {
+    /// `Kd`
+    pub diffuse: Option<Color>,
+    /// `Ks`
+    pub specular: Option<Color>,
+    /// `Ns`
+    pub shininess: Option<f64>,
+    /// `Ni`
+    pub refractive_index: Option<f64>,
+    /// `d`, or one minus `Tr`. One is opaque.
+    pub dissolve: Option<f64>,
+    /// `Ke`
+    pub emission: Option<Color>,
+    /// The file named by `map_Kd`
+    pub diffuse_map: Option<String>,
+}
+
+impl MtlMaterial {
+    /// Picks the closest material:
+    /// - Anything with a non-black emission is a `DiffuseLight`
+    /// - Anything partly dissolved is a `Dielectric`
+    /// - Anything with a specular color but no diffuse color or map is a
+    ///   `Metal`, getting blurrier as the shininess drops from 1000
+    /// - Everything else is `Lambertian`, using the diffuse map if there is
+    ///   one
+    ///
+    /// Diffuse maps are loaded relative to `dir`.
+    pub fn to_material(&self, dir: &Path) -> ImageResult<Box<dyn Material + Sync>> {
+        let is_black = |c: &Color| c.r() <= 0.0 && c.g() <= 0.0 && c.b() <= 0.0;
+
+        if let Some(emission) = self.emission.as_ref().filter(|e| !is_black(e)) {
+            return Ok(Box::new(DiffuseLight {
+                texture: SolidColor {
+                    albedo: emission.clone(),
+                },
+            }));
+        }
+
+        if self.dissolve.is_some_and(|d| d < 1.0) {
+            return Ok(Box::new(Dielectric {
+                refraction_index: self.refractive_index.unwrap_or(1.5),
+            }));
+        }
+
+        let has_diffuse =
+            self.diffuse_map.is_some() || self.diffuse.as_ref().is_some_and(|d| !is_black(d));
+        if let Some(specular) = self
+            .specular
+            .as_ref()
+            .filter(|s| !has_diffuse && !is_black(s))
+        {
+            let shininess = self.shininess.unwrap_or(0.0);
+            return Ok(Box::new(Metal {
+                albedo: specular.clone(),
+                fuzz: (1.0 - shininess / 1000.0).clamp(0.0, 1.0),
+            }));
+        }
+
+        let texture: Box<dyn Texture> = match &self.diffuse_map {
+            Some(file) => Box::new(Image::open(dir.join(file))?),
+            None => Box::new(SolidColor {
+                albedo: self.diffuse.clone().unwrap_or(Color::new(0.8, 0.8, 0.8)),
+            }),
+        };
+        Ok(Box::new(Lambertian { texture }))
+    }
+}
+
+/// The materials defined in one or more MTL files, by name
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MtlLibrary {
+    ignored: u32,
+    materials: HashMap<String, MtlMaterial>,
+}
+
+impl MtlLibrary {
+    /// Parses an MTL file. Properties other than those in `MtlMaterial`, or
+    /// that can't be parsed, are counted and skipped.
+    pub fn parse(reader: impl BufRead) -> Self {
+        let lines_iter = reader.lines().map(|l| l.unwrap());
+
+        let mut library = MtlLibrary::default();
+        let mut current: Option<(String, MtlMaterial)> = None;
+        for line in lines_iter {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (head, tail) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let trimmed = tail.trim();
+            if head == "newmtl" && !trimmed.is_empty() {
+                if let Some((name, material)) = current.take() {
+                    library.materials.insert(name, material);
+                }
+                current = Some((String::from(trimmed), MtlMaterial::default()));
+                continue;
+            }
+
+            // Properties before the first `newmtl` don't belong to anything
+            let status = current.as_mut().and_then(|(_, material)| match head {
+                "Kd" => parse_color(trimmed).map(|c| material.diffuse = Some(c)),
+                "Ks" => parse_color(trimmed).map(|c| material.specular = Some(c)),
+                "Ke" => parse_color(trimmed).map(|c| material.emission = Some(c)),
+                "Ns" => parse_number(trimmed).map(|n| material.shininess = Some(n)),
+                "Ni" => parse_number(trimmed).map(|n| material.refractive_index = Some(n)),
+                "d" => parse_number(trimmed).map(|n| material.dissolve = Some(n)),
+                "Tr" => parse_number(trimmed).map(|n| material.dissolve = Some(1.0 - n)),
+                // Options such as `-s 1 1 1` come before the file name
+                "map_Kd" => trimmed
+                    .split_whitespace()
+                    .last()
+                    .map(|file| material.diffuse_map = Some(String::from(file))),
+                _ => None,
+            });
+
+            match status {
+                Some(_) => (),
+                None => library.ignored += 1,
+            }
+        }
+
+        if let Some((name, material)) = current {
+            library.materials.insert(name, material);
+        }
+        library
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MtlMaterial> {
+        self.materials.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    /// Adds the materials from another library, replacing any with the same names
+    pub fn extend(&mut self, other: MtlLibrary) {
+        self.ignored += other.ignored;
+        self.materials.extend(other.materials);
+    }
+
+    /// Converts every material with `MtlMaterial::to_material`, loading
+    /// diffuse maps relative to `dir`
+    pub fn to_materials(
+        &self,
+        dir: &Path,
+    ) -> ImageResult<HashMap<String, Box<dyn Material + Sync>>> {
+        self.materials
+            .iter()
+            .map(|(name, m)| Ok((name.clone(), m.to_material(dir)?)))
+            .collect()
+    }
+}
+
+fn parse_numbers(tail: &str) -> Option<Vec<f64>> {
+    tail.split_whitespace()
+        .map(|s| s.parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()
+}
+
+fn parse_number(tail: &str) -> Option<f64> {
+    parse_numbers(tail).and_then(|ns| if ns.len() == 1 { Some(ns[0]) } else { None })
+}
+
+/// Parses an RGB color. A single value is used for all three channels.
+fn parse_color(tail: &str) -> Option<Color> {
+    parse_numbers(tail).and_then(|ns| match ns.len() {
+        1 => Some(Color::new(ns[0], ns[0], ns[0])),
+        3 => Some(Color::new(ns[0], ns[1], ns[2])),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_materials() {
+        let data = "
+# Three materials
+newmtl Red
+Ka 0 0 0
+Kd 0.8 0.1 0.1
+Ks 0.5
+Ns 50
+illum 2
+
+newmtl Glass
+Kd 1 1 1
+Ni 1.5
+d 0.25
+Ks 1 2
+
+newmtl Lamp
+Ke 4 4 3
+map_Kd -s 2 2 1 textures/lamp.png
+";
+
+        let library = MtlLibrary::parse(data.as_bytes());
+
+        let mut names = library.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["Glass", "Lamp", "Red"]);
+        assert_eq!(library.ignored, 3);
+        assert_eq!(
+            library.get("Red"),
+            Some(&MtlMaterial {
+                diffuse: Some(Color::new(0.8, 0.1, 0.1)),
+                specular: Some(Color::new(0.5, 0.5, 0.5)),
+                shininess: Some(50.0),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            library.get("Glass"),
+            Some(&MtlMaterial {
+                diffuse: Some(Color::new(1.0, 1.0, 1.0)),
+                refractive_index: Some(1.5),
+                dissolve: Some(0.25),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            library.get("Lamp").unwrap().diffuse_map.as_deref(),
+            Some("textures/lamp.png")
+        );
+    }
+
+    #[test]
+    fn emissive_materials_are_lights() {
+        let lamp = MtlMaterial {
+            diffuse: Some(Color::new(0.5, 0.5, 0.5)),
+            emission: Some(Color::new(4.0, 4.0, 3.0)),
+            ..Default::default()
+        };
+
+        let material = lamp.to_material(Path::new(".")).unwrap();
+
+        assert_eq!(
+            material.emitted(0.0, 0.0, &Color::new(0.0, 0.0, 0.0)),
+            Color::new(4.0, 4.0, 3.0)
+        );
+    }
+}
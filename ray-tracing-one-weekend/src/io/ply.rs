@@ -0,0 +1,191 @@
+use std::io::BufRead;
+
+use mesh_formats::ply;
+pub use mesh_formats::ply::PlyError;
+
+use crate::{
+    bvh::Bvh,
+    color::Color,
+    hittable::{
+        flat::triangle::Triangle,
+        mesh::{Mesh, MeshFace},
+    },
+    material::{Lambertian, Material},
+    texture::VertexColors,
+    vec3::Vec3,
+};
+
+/// A triangle mesh read from a PLY file, in ASCII or binary form
+pub struct Ply(ply::Ply);
+
+impl Ply {
+    /// Parses a PLY file, reading vertex normals and colors when every
+    /// vertex has them
+    pub fn parse(reader: impl BufRead) -> Result<Self, PlyError> {
+        ply::Ply::parse(reader).map(Ply)
+    }
+
+    /// Builds the mesh, fan-triangulating faces with more than three
+    /// vertices and dropping faces with no area. Vertex normals make the
+    /// triangles smooth. Vertex colors are ignored.
+    pub fn to_object<M: Material + ?Sized>(self, material: &M) -> Bvh<Triangle<&M>> {
+        let triangles = self
+            .0
+            .triangles()
+            .map(|corners| {
+                let points = corners.map(|i| vec3(self.0.vertices[i]));
+                Triangle::from_model(points, None, self.corner_normals(&corners), material)
+            })
+            .collect::<Vec<_>>();
+        Bvh::new(triangles)
+    }
+
+    /// Like `to_object`, but gives each triangle its own diffuse material,
+    /// blending between the colors of its vertices. Integer colors are taken
+    /// to be sRGB, and vertices without colors are white.
+    pub fn to_object_with_vertex_colors(self) -> Bvh<Triangle<Lambertian<VertexColors>>> {
+        let Ply(ply) = &self;
+        let triangles = ply
+            .triangles()
+            .map(|corners| {
+                let points = corners.map(|i| vec3(ply.vertices[i]));
+                let colors = corners.map(|i| match &ply.colors {
+                    Some(colors) => {
+                        let [r, g, b] = colors[i];
+                        let color = Color::new(r, g, b);
+                        if ply.colors_are_srgb {
+                            color.srgb_to_linear()
+                        } else {
+                            color
+                        }
+                    }
+                    None => Color::new(1.0, 1.0, 1.0),
+                });
+                let material = Lambertian {
+                    texture: VertexColors { colors },
+                };
+                Triangle::from_model(points, None, self.corner_normals(&corners), material)
+            })
+            .collect::<Vec<_>>();
+        Bvh::new(triangles)
+    }
+
+    /// Like `to_object`, but as a `Mesh` whose triangles share the vertex
+    /// data, to save memory for big scans
+    pub fn to_mesh<M>(self, material: M) -> Mesh<M> {
+        let Ply(ply) = self;
+        let faces = ply
+            .triangles()
+            .map(|corners| {
+                let vertices = corners.map(|i| i as u32);
+                MeshFace {
+                    vertices,
+                    texture_coords: None,
+                    normals: ply.normals.as_ref().map(|_| vertices),
+                }
+            })
+            .collect();
+        Mesh::new(
+            ply.vertices.into_iter().map(vec3).collect(),
+            Vec::new(),
+            ply.normals
+                .unwrap_or_default()
+                .into_iter()
+                .map(vec3)
+                .collect(),
+            faces,
+            material,
+        )
+        .expect("PLY faces are checked when parsing")
+    }
+
+    fn corner_normals(&self, corners: &[usize; 3]) -> Option<[Vec3; 3]> {
+        self.0
+            .normals
+            .as_ref()
+            .map(|normals| corners.map(|i| vec3(normals[i])))
+    }
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        color::srgb, hittable::Hittable, interval::Interval, ray::Ray, texture::Texture,
+        vec3::Point3,
+    };
+
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment A unit square split in two
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3
+";
+
+    fn color_at(
+        object: &impl Hittable<Material = Lambertian<VertexColors>>,
+        x: f64,
+        y: f64,
+    ) -> Color {
+        let ray = Ray::new(Point3::new(x, y, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let (material, rec) = object
+            .hit(
+                &ray,
+                &Interval {
+                    min: 0.0,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        material.texture.value(rec.uv.0, rec.uv.1, &rec.p)
+    }
+
+    #[test]
+    fn vertex_colors_are_blended_across_faces() {
+        let object = Ply::parse(ASCII.as_bytes())
+            .unwrap()
+            .to_object_with_vertex_colors();
+
+        for (x, y, blue) in [(0.5, 0.1, 0.1), (0.75, 0.25, 0.25), (0.25, 0.75, 0.75)] {
+            let color = color_at(&object, x, y);
+            assert_approx_eq!(f64, color.r(), 1.0 - blue);
+            assert_approx_eq!(f64, color.g(), 0.0);
+            assert_approx_eq!(f64, color.b(), blue);
+        }
+    }
+
+    #[test]
+    fn integer_vertex_colors_are_decoded_from_srgb() {
+        let data = ASCII
+            .replace(" 255 0 0", " 128 0 0")
+            .replace(" 0 0 255", " 128 0 0");
+        let object = Ply::parse(data.as_bytes())
+            .unwrap()
+            .to_object_with_vertex_colors();
+
+        let color = color_at(&object, 0.5, 0.5);
+
+        assert_approx_eq!(f64, color.r(), srgb::srgb_to_linear(128.0 / 255.0));
+        assert!(color.r() < 0.25);
+    }
+}
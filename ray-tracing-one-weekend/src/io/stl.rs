@@ -0,0 +1,86 @@
+use std::io::Read;
+
+use mesh_formats::stl;
+pub use mesh_formats::stl::StlError;
+
+use crate::{bvh::Bvh, hittable::flat::triangle::Triangle, material::Material, vec3::Point3};
+
+/// A triangle mesh read from an STL file, in ASCII or binary form
+pub struct Stl(stl::Stl);
+
+impl Stl {
+    /// Parses an STL file. The stored facet normals are ignored in favor of
+    /// the ones implied by the vertex order.
+    pub fn parse(reader: impl Read) -> Result<Self, StlError> {
+        stl::Stl::parse(reader).map(Stl)
+    }
+
+    /// Builds the mesh, dropping triangles with no area
+    pub fn to_object<M: Material + ?Sized>(self, material: &M) -> Bvh<Triangle<&M>> {
+        let triangles = self
+            .0
+            .triangles()
+            .map(|facet| {
+                let points = facet.map(|[x, y, z]| Point3::new(x, y, z));
+                Triangle::from_model(points, None, None, material)
+            })
+            .collect::<Vec<_>>();
+        Bvh::new(triangles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        color::Color, hittable::Hittable, interval::Interval, material::Lambertian, ray::Ray,
+        texture::SolidColor, vec3::Vec3,
+    };
+
+    use super::*;
+
+    const ASCII: &str = "solid square
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    #[test]
+    fn facets_share_the_given_material() {
+        let material = Lambertian {
+            texture: SolidColor {
+                albedo: Color::new(0.5, 0.5, 0.5),
+            },
+        };
+        let object = Stl::parse(ASCII.as_bytes()).unwrap().to_object(&material);
+
+        for (x, y) in [(0.75, 0.25), (0.25, 0.75)] {
+            let ray = Ray::new(Point3::new(x, y, -1.0), Vec3::new(0.0, 0.0, 1.0));
+            let (hit, rec) = object
+                .hit(
+                    &ray,
+                    &Interval {
+                        min: 0.0,
+                        max: f64::INFINITY,
+                    },
+                )
+                .unwrap();
+
+            assert!(std::ptr::eq(*hit, &material));
+            assert_approx_eq!(f64, rec.t, 1.0);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Options for `WavefrontObj::parse_subdivided`. The subdivision itself is
+//! shared with the other ray tracer, in `mesh_formats::subdivision`.
+
+use mesh_formats::subdivision;
+pub use mesh_formats::subdivision::Scheme;
+
+/// How to subdivide a mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Subdivision {
+    pub scheme: Scheme,
+    /// How many times to subdivide. Each level multiplies the face count by
+    /// about four.
+    pub levels: u32,
+    /// Edges where the faces meet at more than this angle, in degrees like
+    /// the crease angle of `generate_normals`, stay sharp, as do the edges
+    /// along the mesh's boundary. If `None`, only the boundary is kept sharp.
+    pub crease_angle: Option<f64>,
+}
+
+impl From<&Subdivision> for subdivision::Subdivision {
+    fn from(s: &Subdivision) -> Self {
+        subdivision::Subdivision {
+            scheme: s.scheme,
+            levels: s.levels,
+            crease_angle: s.crease_angle.map(f64::to_radians),
+        }
+    }
+}
@@ -1,26 +1,54 @@
 use std::{collections::HashMap, io::BufRead};
 
+use mesh_formats::subdivision::{Polygon, PolygonMesh};
+
 use crate::{
     bvh::Bvh,
-    hittable::flat::triangle::Triangle,
+    hittable::{
+        flat::triangle::Triangle,
+        mesh::{Mesh, MeshFace},
+    },
+    io::subdivision::Subdivision,
     material::Material,
     vec3::{Point3, Vec3},
 };
 
+/// A group of faces, named by a `g` record. Faces before the first of those
+/// are in the default group.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-enum ObjGroup {
+pub enum ObjGroup {
     Default,
     Named(String),
 }
 
+impl ObjGroup {
+    pub fn named(name: &str) -> Self {
+        ObjGroup::Named(String::from(name))
+    }
+}
+
+/// How much each face counts towards the normals generated at its corners
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalWeighting {
+    /// By the angle between the face's edges at the corner, so that
+    /// splitting a face into more triangles doesn't change the result
+    Angle,
+    /// By the face's area, so that big faces dominate
+    Area,
+}
+
 pub struct WavefrontObj {
     ignored: u32,
-    groups: HashMap<ObjGroup, Vec<Tri>>,
+    groups: HashMap<ObjGroup, Vec<MeshFace>>,
+    /// The `usemtl` material for each triangle in `groups`
+    group_materials: HashMap<ObjGroup, Vec<Option<String>>>,
+    material_libraries: Vec<String>,
     vertices: Vec<Point3>,
     normals: Vec<Vec3>,
     texture_coords: Vec<(f64, f64)>,
 }
 
+/// A face with its vertex data looked up
 #[derive(Debug, PartialEq)]
 struct Tri {
     points: [Point3; 3],
@@ -30,18 +58,35 @@ struct Tri {
 
 impl WavefrontObj {
     pub fn parse(reader: impl BufRead) -> Self {
+        Self::parse_with(reader, None)
+    }
+
+    /// Like `parse`, but subdivides the faces first, to round off coarse
+    /// models. Subdivided faces lose their normals; `generate_normals` gives
+    /// them smooth ones again.
+    pub fn parse_subdivided(reader: impl BufRead, subdivision: &Subdivision) -> Self {
+        Self::parse_with(reader, Some(subdivision))
+    }
+
+    fn parse_with(reader: impl BufRead, subdivision: Option<&Subdivision>) -> Self {
         let lines_iter = reader.lines().map(|l| l.unwrap());
 
         let mut obj = WavefrontObj {
             ignored: 0,
             groups: HashMap::new(),
+            group_materials: HashMap::new(),
+            material_libraries: Vec::new(),
             vertices: Vec::new(),
             normals: Vec::new(),
             texture_coords: Vec::new(),
         };
 
         let mut current_group_name = ObjGroup::Default;
-        let mut current_group_val = Vec::<Tri>::new();
+        let mut current_group_val = Vec::<MeshFace>::new();
+        let mut current_group_materials = Vec::<Option<String>>::new();
+        let mut current_material = None;
+        // Faces to subdivide, with their groups and materials
+        let mut polygons = Vec::<(ObjGroup, Option<String>, Vec<FaceVertex>)>::new();
         for line in lines_iter {
             let split = line.split_once(' ');
             let status: Option<()> = split.and_then(|(head, tail)| {
@@ -54,13 +99,25 @@ impl WavefrontObj {
                     "f" => {
                         let res =
                             parse_face(trimmed, &obj.vertices, &obj.texture_coords, &obj.normals);
-                        res.map(|mut ts| current_group_val.append(&mut ts))
+                        res.map(|face| {
+                            if subdivision.is_some() {
+                                let group = current_group_name.clone();
+                                polygons.push((group, current_material.clone(), face));
+                            } else {
+                                let mut ts = triangulate(&face);
+                                current_group_materials
+                                    .extend(ts.iter().map(|_| current_material.clone()));
+                                current_group_val.append(&mut ts)
+                            }
+                        })
                     }
                     "g" => {
-                        let group_vals = std::mem::take(&mut current_group_val);
-                        obj.groups.insert(current_group_name.clone(), group_vals);
+                        obj.add_to_group(
+                            &current_group_name,
+                            &mut current_group_val,
+                            &mut current_group_materials,
+                        );
                         current_group_name = ObjGroup::Named(String::from(trimmed));
-                        current_group_val.clear();
                         Some(())
                     }
                     "vn" => {
@@ -71,6 +128,15 @@ impl WavefrontObj {
                         let res = parse_texture_coord(trimmed);
                         res.map(|tc| obj.texture_coords.push(tc))
                     }
+                    "usemtl" if !trimmed.is_empty() => {
+                        current_material = Some(String::from(trimmed));
+                        Some(())
+                    }
+                    "mtllib" if !trimmed.is_empty() => {
+                        obj.material_libraries
+                            .extend(trimmed.split_whitespace().map(String::from));
+                        Some(())
+                    }
                     _ => None,
                 }
             });
@@ -80,27 +146,339 @@ impl WavefrontObj {
                 None => obj.ignored += 1,
             }
         }
-        obj.groups.insert(current_group_name, current_group_val);
+        obj.add_to_group(
+            &current_group_name,
+            &mut current_group_val,
+            &mut current_group_materials,
+        );
+        if let Some(subdivision) = subdivision {
+            obj.add_subdivided(polygons, subdivision);
+        }
 
         obj
     }
 
+    /// Subdivides the faces `parse_subdivided` set aside, then triangulates
+    /// them into their groups like any other face. The new vertices and
+    /// texture coordinates replace the parsed ones.
+    fn add_subdivided(
+        &mut self,
+        polygons: Vec<(ObjGroup, Option<String>, Vec<FaceVertex>)>,
+        subdivision: &Subdivision,
+    ) {
+        let mut labels = Vec::with_capacity(polygons.len());
+        let faces = polygons
+            .into_iter()
+            .enumerate()
+            .map(|(tag, (group, material, face))| {
+                labels.push((group, material));
+                Polygon {
+                    vertices: face.iter().map(|&(v, _, _)| v as usize).collect(),
+                    texture_coordinates: face
+                        .iter()
+                        .map(|&(_, t, _)| t.map(|t| t as usize))
+                        .collect(),
+                    tag,
+                }
+            })
+            .collect();
+        let mesh = PolygonMesh {
+            positions: self
+                .vertices
+                .iter()
+                .map(|p| [p.x(), p.y(), p.z()])
+                .collect(),
+            texture_coordinates: std::mem::take(&mut self.texture_coords),
+            faces,
+        }
+        .subdivide(&subdivision.into());
+
+        self.vertices = mesh
+            .positions
+            .into_iter()
+            .map(|[x, y, z]| Point3::new(x, y, z))
+            .collect();
+        self.texture_coords = mesh.texture_coordinates;
+        for polygon in mesh.faces {
+            let (group, material) = &labels[polygon.tag];
+            let corners = match &polygon.texture_coordinates {
+                Some(ts) => polygon
+                    .vertices
+                    .iter()
+                    .zip(ts)
+                    .map(|(&v, &t)| (v as u32, Some(t as u32), None))
+                    .collect::<Vec<_>>(),
+                None => polygon
+                    .vertices
+                    .iter()
+                    .map(|&v| (v as u32, None, None))
+                    .collect(),
+            };
+            let mut triangles = triangulate(&corners);
+            let mut materials = vec![material.clone(); triangles.len()];
+            self.add_to_group(group, &mut triangles, &mut materials);
+        }
+    }
+
+    /// Moves triangles into a group, appending them if a group of the same
+    /// name came earlier in the file
+    fn add_to_group(
+        &mut self,
+        group: &ObjGroup,
+        triangles: &mut Vec<MeshFace>,
+        materials: &mut Vec<Option<String>>,
+    ) {
+        self.groups
+            .entry(group.clone())
+            .or_default()
+            .append(triangles);
+        self.group_materials
+            .entry(group.clone())
+            .or_default()
+            .append(materials);
+    }
+
+    /// The groups which have faces and haven't been taken yet
+    pub fn groups(&self) -> impl Iterator<Item = &ObjGroup> {
+        self.groups
+            .iter()
+            .filter(|(_, tris)| !tris.is_empty())
+            .map(|(group, _)| group)
+    }
+
+    /// Removes a group, returning its faces as their own object. Returns
+    /// `None` if there's no such group, or if it was already taken.
+    pub fn take_group<'a, M: Material + ?Sized>(
+        &mut self,
+        group: &ObjGroup,
+        material: &'a M,
+    ) -> Option<Bvh<Triangle<&'a M>>> {
+        self.take_tris(group).map(|tris| {
+            let triangles = tris
+                .into_iter()
+                .map(|(f, _)| self.resolve(&f).into_triangle(None, material))
+                .collect::<Vec<_>>();
+            Bvh::new(triangles)
+        })
+    }
+
+    /// Like `take_group`, but gives each face its material from `materials`,
+    /// as `to_object_with_materials` does
+    pub fn take_group_with_materials<'a, M: Material + ?Sized>(
+        &mut self,
+        group: &ObjGroup,
+        materials: &'a HashMap<String, Box<M>>,
+        default: &'a M,
+    ) -> Option<Bvh<Triangle<&'a M>>> {
+        self.take_tris(group).map(|tris| {
+            let triangles = tris
+                .into_iter()
+                .map(|(f, name)| {
+                    self.resolve(&f)
+                        .into_triangle(name.and_then(|n| materials.get(&n)).map(|b| &**b), default)
+                })
+                .collect::<Vec<_>>();
+            Bvh::new(triangles)
+        })
+    }
+
+    /// Removes a group's triangles, paired with their `usemtl` material names
+    fn take_tris(&mut self, group: &ObjGroup) -> Option<Vec<(MeshFace, Option<String>)>> {
+        let tris = self.groups.remove(group)?;
+        let names = self.group_materials.remove(group).unwrap_or_default();
+        // Groups are made empty by `g` records with no faces after them
+        (!tris.is_empty()).then(|| tris.into_iter().zip(names).collect())
+    }
+
+    /// Builds one object out of every group that hasn't been taken
     pub fn to_object<'a, M: Material + ?Sized>(self, material: &'a M) -> Bvh<Triangle<&'a M>> {
         let all_triangles = self
             .groups
-            .into_values()
+            .values()
             .flatten()
-            .map(|t| {
-                let Tri {
-                    points,
-                    texture_coords,
-                    normals,
-                } = t;
-                Triangle::from_model(points, texture_coords, normals, material)
+            .map(|f| self.resolve(f).into_triangle(None, material))
+            .collect::<Vec<_>>();
+        Bvh::new(all_triangles)
+    }
+
+    /// Builds one mesh out of every group that hasn't been taken. Its faces
+    /// share the file's vertex data instead of copying it into each triangle,
+    /// which saves a lot of memory for big models. `usemtl` records are
+    /// ignored, since a mesh has only one material.
+    pub fn to_mesh<M>(self, material: M) -> Mesh<M> {
+        let faces = self.groups.into_values().flatten().collect();
+        Mesh::new(
+            self.vertices,
+            self.texture_coords,
+            self.normals,
+            faces,
+            material,
+        )
+        .expect("OBJ faces are checked when parsing")
+    }
+
+    /// Like `to_object`, but gives each triangle the material named by the
+    /// `usemtl` record before its face. Faces without a material, or whose
+    /// material isn't in `materials`, get `default`.
+    pub fn to_object_with_materials<'a, M: Material + ?Sized>(
+        mut self,
+        materials: &'a HashMap<String, Box<M>>,
+        default: &'a M,
+    ) -> Bvh<Triangle<&'a M>> {
+        let groups = std::mem::take(&mut self.groups);
+        let mut group_materials = std::mem::take(&mut self.group_materials);
+        let all_triangles = groups
+            .into_iter()
+            .flat_map(|(group, faces)| {
+                let names = group_materials.remove(&group).unwrap_or_default();
+                faces.into_iter().zip(names)
+            })
+            .map(|(f, name)| {
+                self.resolve(&f)
+                    .into_triangle(name.and_then(|n| materials.get(&n)).map(|b| &**b), default)
             })
             .collect::<Vec<_>>();
         Bvh::new(all_triangles)
     }
+
+    /// The files named by `mtllib` records, in order
+    pub fn material_libraries(&self) -> &[String] {
+        &self.material_libraries
+    }
+
+    /// Gives smooth normals to the faces which don't have any, by averaging
+    /// the normals of the faces around each vertex. Faces whose normals are
+    /// more than `crease_angle` degrees apart aren't averaged together, so
+    /// that hard edges stay sharp.
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: f64) {
+        let group_names = self.groups.keys().cloned().collect::<Vec<_>>();
+        let corners = group_names
+            .iter()
+            .flat_map(|g| self.groups[g].iter().map(|f| f.vertices))
+            .collect::<Vec<_>>();
+        let normals = corner_normals(
+            &self.vertices,
+            &corners,
+            weighting,
+            crease_angle.to_radians().cos(),
+        );
+
+        // Corners which end up with the same normal share it
+        let mut indices = HashMap::new();
+        let mut normals = normals.into_iter();
+        for g in &group_names {
+            for face in self.groups.get_mut(g).unwrap() {
+                let face_normals = normals.next().unwrap();
+                if face.normals.is_some() {
+                    continue;
+                }
+                let mut face_indices = [0; 3];
+                for (i, normal) in face_normals.into_iter().enumerate() {
+                    let bits = [normal.x(), normal.y(), normal.z()].map(f64::to_bits);
+                    face_indices[i] =
+                        *indices.entry((face.vertices[i], bits)).or_insert_with(|| {
+                            self.normals.push(normal);
+                            self.normals.len() as u32 - 1
+                        });
+                }
+                face.normals = Some(face_indices);
+            }
+        }
+    }
+
+    /// Looks up the data a face's indices refer to
+    fn resolve(&self, face: &MeshFace) -> Tri {
+        Tri {
+            points: face.vertices.map(|i| self.vertices[i as usize].clone()),
+            texture_coords: face
+                .texture_coords
+                .map(|ts| ts.map(|i| self.texture_coords[i as usize])),
+            normals: face
+                .normals
+                .map(|ns| ns.map(|i| self.normals[i as usize].clone())),
+        }
+    }
+}
+
+impl Tri {
+    fn into_triangle<'a, M: Material + ?Sized>(
+        self,
+        material: Option<&'a M>,
+        default: &'a M,
+    ) -> Triangle<&'a M> {
+        let Tri {
+            points,
+            texture_coords,
+            normals,
+        } = self;
+        Triangle::from_model(points, texture_coords, normals, material.unwrap_or(default))
+    }
+}
+
+/// Finds the normal at each corner of each face, averaging the weighted
+/// normals of the faces sharing that vertex whose normals are within the
+/// crease angle, given by its cosine, of the face's own
+fn corner_normals(
+    vertices: &[Point3],
+    faces: &[[u32; 3]],
+    weighting: NormalWeighting,
+    cos_crease_angle: f64,
+) -> Vec<[Vec3; 3]> {
+    let face_normals = faces
+        .iter()
+        .map(|f| {
+            let [p1, p2, p3] = f.map(|i| &vertices[i as usize]);
+            (p2 - p1).cross(&(p3 - p1))
+        })
+        .collect::<Vec<_>>();
+
+    let mut faces_at_vertex = vec![Vec::new(); vertices.len()];
+    for (i, f) in faces.iter().enumerate() {
+        for &v in f {
+            faces_at_vertex[v as usize].push(i);
+        }
+    }
+
+    // The weighted normal each face adds at each of its corners
+    let contributions = faces
+        .iter()
+        .zip(&face_normals)
+        .map(|(f, n)| {
+            [0, 1, 2].map(|c| match weighting {
+                NormalWeighting::Area => n.clone(),
+                NormalWeighting::Angle => {
+                    let p = &vertices[f[c] as usize];
+                    let e1 = &vertices[f[(c + 1) % 3] as usize] - p;
+                    let e2 = &vertices[f[(c + 2) % 3] as usize] - p;
+                    let cos = e1.dot(&e2) / (e1.length() * e2.length());
+                    n.normalize() * cos.clamp(-1.0, 1.0).acos()
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    faces
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let normal = face_normals[i].normalize();
+            [0, 1, 2].map(|c| {
+                let sum = faces_at_vertex[f[c] as usize]
+                    .iter()
+                    .filter(|&&j| face_normals[j].normalize().dot(&normal) >= cos_crease_angle)
+                    .map(|&j| {
+                        let corner = faces[j].iter().position(|&v| v == f[c]).unwrap();
+                        &contributions[j][corner]
+                    })
+                    .fold(Vec3::zero(), |sum, n| sum + n);
+                if sum.near_zero() {
+                    normal.clone()
+                } else {
+                    sum.normalize()
+                }
+            })
+        })
+        .collect()
 }
 
 fn parse_texture_coord(tail: &str) -> Option<(f64, f64)> {
@@ -151,7 +529,7 @@ fn parse_face(
     read_vertices: &[Point3],
     read_texcoords: &[(f64, f64)],
     read_normals: &[Vec3],
-) -> Option<Vec<Tri>> {
+) -> Option<Vec<FaceVertex>> {
     let tokens = tail.split_whitespace();
 
     let indices: Option<Vec<(usize, Option<usize>, Option<usize>)>> = tokens
@@ -180,59 +558,60 @@ fn parse_face(
         })
         .collect();
 
-    let mapped_indices = indices.map(|ns| {
+    // Convert to indices from 0, checking that they refer to data read so far
+    let in_range = |i: usize, len: usize| {
+        if (1..=len).contains(&i) {
+            Some(i as u32 - 1)
+        } else {
+            None
+        }
+    };
+    let optional_in_range = |i: Option<usize>, len: usize| match i {
+        Some(i) => in_range(i, len).map(Some),
+        None => Some(None),
+    };
+    let mapped_indices = indices.and_then(|ns| {
         ns.iter()
-            .map(|(vi, ti, ni)| {
-                (
-                    &read_vertices[vi - 1],
-                    ti.map(|t| &read_texcoords[t - 1]),
-                    ni.map(|n| &read_normals[n - 1]),
-                )
+            .map(|&(vi, ti, ni)| {
+                Some((
+                    in_range(vi, read_vertices.len())?,
+                    optional_in_range(ti, read_texcoords.len())?,
+                    optional_in_range(ni, read_normals.len())?,
+                ))
             })
-            .collect::<Vec<_>>()
+            .collect::<Option<Vec<_>>>()
     });
 
-    mapped_indices.and_then(|vertices| {
-        if vertices.len() >= 3 {
-            let triangulated = fan_triangulate(&vertices);
-            Some(
-                triangulated
-                    .into_iter()
-                    .map(|verts| {
-                        let points = [verts[0].0, verts[1].0, verts[2].0].map(|p| p.clone());
-                        let texture_coords = verts[0].1.and_then(|first| {
-                            verts[1].1.and_then(|second| {
-                                verts[2]
-                                    .1
-                                    .map(|third| [first, second, third].map(|n| n.clone()))
-                            })
-                        });
-                        let normals = verts[0].2.and_then(|first| {
-                            verts[1].2.and_then(|second| {
-                                verts[2]
-                                    .2
-                                    .map(|third| [first, second, third].map(|n| n.clone()))
-                            })
-                        });
+    mapped_indices.filter(|vertices| vertices.len() >= 3)
+}
 
-                        Tri {
-                            points,
-                            texture_coords,
-                            normals,
-                        }
-                    })
-                    .collect::<Vec<_>>(),
-            )
-        } else {
-            None
-        }
-    })
+/// Splits a face into triangles. Texture coordinates and normals are only
+/// kept if every corner has them.
+fn triangulate(face: &[FaceVertex]) -> Vec<MeshFace> {
+    fan_triangulate(face)
+        .into_iter()
+        .map(|verts| {
+            let [first, second, third] = verts;
+            let texture_coords = first
+                .1
+                .and_then(|t1| second.1.and_then(|t2| third.1.map(|t3| [t1, t2, t3])));
+            let normals = first
+                .2
+                .and_then(|n1| second.2.and_then(|n2| third.2.map(|n3| [n1, n2, n3])));
+
+            MeshFace {
+                vertices: [first.0, second.0, third.0],
+                texture_coords,
+                normals,
+            }
+        })
+        .collect()
 }
 
-fn fan_triangulate<'a>(
-    vertices: &[(&'a Point3, Option<&'a (f64, f64)>, Option<&'a Vec3>)],
-) -> Vec<[(&'a Point3, Option<&'a (f64, f64)>, Option<&'a Vec3>); 3]> {
-    let mut triangles = Vec::<[(&'a Point3, Option<&'a (f64, f64)>, Option<&'a Vec3>); 3]>::new();
+type FaceVertex = (u32, Option<u32>, Option<u32>);
+
+fn fan_triangulate(vertices: &[FaceVertex]) -> Vec<[FaceVertex; 3]> {
+    let mut triangles = Vec::<[FaceVertex; 3]>::new();
 
     for i in 2..vertices.len() {
         triangles.push([vertices[0], vertices[i - 1], vertices[i]])
@@ -243,6 +622,12 @@ fn fan_triangulate<'a>(
 
 #[cfg(test)]
 mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        hittable::Hittable, interval::Interval, io::subdivision::Scheme, material::Flat, ray::Ray,
+    };
+
     use super::*;
 
     #[test]
@@ -267,8 +652,8 @@ f 1/2/3 2/3/1 3/1/2
         let parsed = WavefrontObj::parse(data.as_bytes());
 
         let g = parsed.groups.get(&ObjGroup::Default).unwrap();
-        let t1 = &g[0];
-        let t2 = &g[1];
+        let t1 = &parsed.resolve(&g[0]);
+        let t2 = &parsed.resolve(&g[1]);
 
         assert_eq!(
             t1,
@@ -304,4 +689,220 @@ f 1/2/3 2/3/1 3/1/2
             }
         );
     }
+
+    #[test]
+    fn material_records() {
+        let data = "mtllib first.mtl second.mtl
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+usemtl Red
+f 1 2 3 4
+g Other
+f 1 2 3
+";
+
+        let parsed = WavefrontObj::parse(data.as_bytes());
+
+        assert_eq!(parsed.ignored, 0);
+        assert_eq!(parsed.material_libraries(), ["first.mtl", "second.mtl"]);
+        assert_eq!(
+            parsed.group_materials.get(&ObjGroup::Default).unwrap(),
+            &[None, Some(String::from("Red")), Some(String::from("Red"))]
+        );
+        assert_eq!(
+            parsed
+                .group_materials
+                .get(&ObjGroup::Named(String::from("Other")))
+                .unwrap(),
+            &[Some(String::from("Red"))]
+        );
+    }
+
+    #[test]
+    fn taking_groups_as_their_own_objects() {
+        let data = "
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+g Body
+f 1 2 3
+g Wheels
+f 1 3 4
+";
+        let mut parsed = WavefrontObj::parse(data.as_bytes());
+
+        let mut groups = parsed.groups().cloned().collect::<Vec<_>>();
+        groups.sort_by_key(|g| format!("{:?}", g));
+        assert_eq!(groups, [ObjGroup::named("Body"), ObjGroup::named("Wheels")]);
+
+        let wheels = parsed.take_group(&ObjGroup::named("Wheels"), &Flat);
+        assert!(wheels.is_some());
+        assert!(parsed
+            .take_group(&ObjGroup::named("Wheels"), &Flat)
+            .is_none());
+        assert!(parsed.take_group(&ObjGroup::Default, &Flat).is_none());
+    }
+
+    #[test]
+    fn faces_under_a_repeated_group_name_join_the_earlier_group() {
+        let data = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+g Body
+f 1 2 3
+g Wheels
+usemtl Rubber
+f 1 3 4
+g Body
+f 1 2 4
+";
+        let parsed = WavefrontObj::parse(data.as_bytes());
+
+        let body = ObjGroup::named("Body");
+        assert_eq!(parsed.groups.get(&body).unwrap().len(), 2);
+        assert_eq!(
+            parsed.group_materials.get(&body).unwrap(),
+            &[None, Some(String::from("Rubber"))]
+        );
+    }
+
+    #[test]
+    fn subdivision_crease_angles_are_in_degrees() {
+        let data = "
+v -1 -1 -1
+v 1 -1 -1
+v -1 1 -1
+v 1 1 -1
+v -1 -1 1
+v 1 -1 1
+v -1 1 1
+v 1 1 1
+f 1 3 4 2
+f 5 6 8 7
+f 1 2 6 5
+f 3 7 8 4
+f 1 5 7 3
+f 2 4 8 6
+";
+        let corner_after_subdividing = |crease_angle| {
+            let subdivision = Subdivision {
+                scheme: Scheme::CatmullClark,
+                levels: 1,
+                crease_angle: Some(crease_angle),
+            };
+            let parsed = WavefrontObj::parse_subdivided(data.as_bytes(), &subdivision);
+            parsed.vertices[7].clone()
+        };
+
+        // The cube's faces meet at 90 degrees, so its edges are creases
+        // under 80 degrees but smoothed over under 100
+        assert_eq!(corner_after_subdividing(80.0), Point3::new(1.0, 1.0, 1.0));
+        assert!(corner_after_subdividing(100.0).length() < 1.0);
+    }
+
+    #[test]
+    fn subdividing_drops_faces_which_repeat_a_vertex() {
+        let data = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 5 5 5
+f 1 2 3
+f 4 1 1
+";
+        let subdivision = Subdivision {
+            scheme: Scheme::Loop,
+            levels: 1,
+            crease_angle: None,
+        };
+
+        let parsed = WavefrontObj::parse_subdivided(data.as_bytes(), &subdivision);
+
+        assert_eq!(parsed.groups[&ObjGroup::Default].len(), 4);
+    }
+
+    #[test]
+    fn meshes_share_vertex_data() {
+        let data = "
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3 4
+f 1 2 9
+";
+        let parsed = WavefrontObj::parse(data.as_bytes());
+        // The blank lines and the face referring to a missing vertex
+        assert_eq!(parsed.ignored, 3);
+
+        let mesh = parsed.to_mesh(Flat);
+
+        assert_eq!(mesh.face_count(), 2);
+        let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let (_, rec) = mesh.hit(&ray, &Interval::nonnegative()).unwrap();
+        assert_eq!(rec.p, Point3::new(0.5, 0.5, 0.0));
+    }
+
+    /// Two faces folded 90 degrees along the X axis, the second twice the
+    /// area of the first, plus a separate face with its own normals
+    const FOLD: &str = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 2
+v 1 1 0
+v 2 1 0
+v 1 2 0
+vn 1 0 0
+
+f 1 2 3
+f 1 4 2
+f 5//1 6//1 7//1
+";
+
+    fn generated_normals(weighting: NormalWeighting, crease_angle: f64) -> Vec<[Vec3; 3]> {
+        let mut parsed = WavefrontObj::parse(FOLD.as_bytes());
+        parsed.generate_normals(weighting, crease_angle);
+        let faces = &parsed.groups[&ObjGroup::Default];
+        faces
+            .iter()
+            .map(|f| parsed.resolve(f).normals.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn generated_normals_keep_edges_sharper_than_the_crease_angle() {
+        let normals = generated_normals(NormalWeighting::Angle, 80.0);
+
+        assert_eq!(normals[0], [0, 1, 2].map(|_| Vec3::new(0.0, 0.0, 1.0)));
+        assert_eq!(normals[1], [0, 1, 2].map(|_| Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(normals[2], [0, 1, 2].map(|_| Vec3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn generated_normals_are_weighted_by_angle_or_area() {
+        let by_angle = generated_normals(NormalWeighting::Angle, 100.0);
+        let by_area = generated_normals(NormalWeighting::Area, 100.0);
+
+        // Both faces have a right angle at the first vertex
+        assert_approx_eq!(
+            f64,
+            by_angle[0][0].dot(&Vec3::new(0.0, 1.0, 0.0)),
+            by_angle[0][0].dot(&Vec3::new(0.0, 0.0, 1.0))
+        );
+        // But the second face has twice the area
+        assert_approx_eq!(
+            f64,
+            by_area[1][0].dot(&Vec3::new(0.0, 1.0, 0.0)),
+            2.0 * by_area[1][0].dot(&Vec3::new(0.0, 0.0, 1.0))
+        );
+        assert_eq!(by_angle[2], by_area[2]);
+    }
 }
@@ -1,7 +1,13 @@
 pub mod aabb;
+pub mod animation;
+pub mod aov;
 pub mod bvh;
 pub mod camera;
 pub mod color;
+pub mod debug;
+pub mod denoise;
+pub mod distributed;
+pub mod filter;
 pub mod hittable;
 pub mod interval;
 pub mod io;
@@ -9,7 +15,10 @@ pub mod material;
 pub mod matrix;
 pub mod output;
 pub mod perlin;
+pub mod preview;
+pub mod progress;
 pub mod ray;
+pub mod stats;
 pub mod texture;
 pub mod utility;
 pub mod vec3;
@@ -17,6 +17,11 @@ pub trait Material {
     ) -> Option<(Color, Ray)>;
 
     fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// Reported in the material ID pass. 0 means the material has no ID.
+    fn id(&self) -> u32 {
+        0
+    }
 }
 
 impl<T: Material + ?Sized> Material for &T {
@@ -32,6 +37,10 @@ impl<T: Material + ?Sized> Material for &T {
     fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
         (**self).emitted(u, v, p)
     }
+
+    fn id(&self) -> u32 {
+        (**self).id()
+    }
 }
 
 impl<T: Material + ?Sized> Material for Box<T> {
@@ -47,6 +56,10 @@ impl<T: Material + ?Sized> Material for Box<T> {
     fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
         (**self).emitted(u, v, p)
     }
+
+    fn id(&self) -> u32 {
+        (**self).id()
+    }
 }
 
 pub struct Flat;
@@ -217,3 +230,29 @@ impl<T: Texture> Material for Isotropic<T> {
         Color::new(0.0, 0.0, 0.0)
     }
 }
+
+/// A material with an ID, for telling materials apart in the material ID pass
+pub struct Tagged<M> {
+    pub material: M,
+    /// Should be non-zero, as 0 means a material has no ID
+    pub id: u32,
+}
+
+impl<M: Material> Material for Tagged<M> {
+    fn scatter(
+        &self,
+        rng: &mut dyn RngCore,
+        ray: &Ray,
+        hitrecord: &HitRecord,
+    ) -> Option<(Color, Ray)> {
+        self.material.scatter(rng, ray, hitrecord)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.material.emitted(u, v, p)
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
@@ -6,6 +6,66 @@ use crate::vec3::Vec3;
 pub struct Matrix3(pub [[f64; 3]; 3]);
 
 impl Matrix3 {
+    pub fn identity() -> Self {
+        Matrix3::scaling(1.0, 1.0, 1.0)
+    }
+
+    /// Counter-clockwise rotation about the X-axis by the given number of degrees
+    pub fn rotation_x(degrees: f64) -> Self {
+        let (sin_theta, cos_theta) = f64::sin_cos(f64::to_radians(degrees));
+        Matrix3([
+            [1.0, 0.0, 0.0],
+            [0.0, cos_theta, -sin_theta],
+            [0.0, sin_theta, cos_theta],
+        ])
+    }
+
+    /// Counter-clockwise rotation about the Y-axis by the given number of degrees
+    pub fn rotation_y(degrees: f64) -> Self {
+        let (sin_theta, cos_theta) = f64::sin_cos(f64::to_radians(degrees));
+        Matrix3([
+            [cos_theta, 0.0, sin_theta],
+            [0.0, 1.0, 0.0],
+            [-sin_theta, 0.0, cos_theta],
+        ])
+    }
+
+    /// Counter-clockwise rotation about the Z-axis by the given number of degrees
+    pub fn rotation_z(degrees: f64) -> Self {
+        let (sin_theta, cos_theta) = f64::sin_cos(f64::to_radians(degrees));
+        Matrix3([
+            [cos_theta, -sin_theta, 0.0],
+            [sin_theta, cos_theta, 0.0],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotation by a unit quaternion, given as `[x, y, z, w]`
+    pub fn rotation_quaternion(q: [f64; 4]) -> Self {
+        let [x, y, z, w] = q;
+        Matrix3([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ])
+    }
+
+    pub fn scaling(x: f64, y: f64, z: f64) -> Self {
+        Matrix3([[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z]])
+    }
+
     pub fn transpose(&self) -> Self {
         let mut out = [[0.0; 3]; 3];
 
@@ -61,6 +121,8 @@ impl Mul<&Vec3> for &Matrix3 {
 
 #[cfg(test)]
 mod tests {
+    use float_cmp::assert_approx_eq;
+
     use super::*;
 
     #[test]
@@ -84,10 +146,54 @@ mod tests {
         assert_eq!(&a * &b, Vec3::new(2.0, 7.0, 2.0));
     }
 
+    #[test]
+    fn rotation_matrices_are_counter_clockwise() {
+        let v = Vec3::new(0.0, 1.0, 0.0);
+        assert_approx_eq!(
+            &Vec3,
+            &(&Matrix3::rotation_x(90.0) * &v),
+            &Vec3::new(0.0, 0.0, 1.0)
+        );
+
+        let v = Vec3::new(0.0, 0.0, 1.0);
+        assert_approx_eq!(
+            &Vec3,
+            &(&Matrix3::rotation_y(90.0) * &v),
+            &Vec3::new(1.0, 0.0, 0.0)
+        );
+
+        let v = Vec3::new(1.0, 0.0, 0.0);
+        assert_approx_eq!(
+            &Vec3,
+            &(&Matrix3::rotation_z(90.0) * &v),
+            &Vec3::new(0.0, 1.0, 0.0)
+        );
+    }
+
     #[test]
     fn transpose_matrix() {
         let a = Matrix3([[1.0; 3], [2.0; 3], [3.0; 3]]);
 
         assert_eq!(a.transpose(), Matrix3([[1.0, 2.0, 3.0]; 3]));
     }
+
+    #[test]
+    fn quaternion_rotation_matches_axis_rotation() {
+        let half_angle = f64::to_radians(30.0);
+        let q = [0.0, half_angle.sin(), 0.0, half_angle.cos()];
+
+        let from_quaternion = Matrix3::rotation_quaternion(q);
+        let about_y = Matrix3::rotation_y(60.0);
+
+        for n in 0..3 {
+            for m in 0..3 {
+                assert_approx_eq!(
+                    f64,
+                    from_quaternion.0[n][m],
+                    about_y.0[n][m],
+                    epsilon = 1e-12
+                );
+            }
+        }
+    }
 }
@@ -1,14 +1,311 @@
-use std::io::{Result, Write};
+use std::io::{Result, Seek, Write};
 
-use crate::camera::Canvas;
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    WritableImage,
+};
+
+use image::{codecs::jpeg::JpegEncoder, ExtendedColorType, ImageEncoder};
+
+use crate::{aov::AovBuffers, camera::Canvas, color::Color};
+
+/// How colors above 1.0 are brought into range before `write_ppm` and the
+/// JPEG writer encode them as sRGB. Bright emitters such as `DiffuseLight`
+/// blow out under the default.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ToneMap {
+    /// Leaves colors as rendered, for `Color::to_u8` to clamp
+    #[default]
+    Clamp,
+    Reinhard,
+    /// Narkowicz's approximation of the ACES filmic curve
+    Aces,
+    /// Hable's Uncharted 2 curve, scaled so that `white` maps to 1.0
+    Filmic {
+        white: f64,
+    },
+}
+
+impl ToneMap {
+    pub fn apply(&self, color: &Color) -> Color {
+        if *self == ToneMap::Clamp {
+            return color.clone();
+        }
+        Color::new(
+            self.curve(color.r()),
+            self.curve(color.g()),
+            self.curve(color.b()),
+        )
+    }
+
+    fn curve(&self, c: f64) -> f64 {
+        fn hable(x: f64) -> f64 {
+            let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+            (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+        }
+
+        let c = c.max(0.0);
+        let mapped = match *self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => c / (1.0 + c),
+            ToneMap::Aces => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+            ToneMap::Filmic { white } => hable(c) / hable(white),
+        };
+        mapped.min(1.0)
+    }
+}
+
+/// Settings for turning a rendered canvas into an image. These are applied
+/// when writing the image, so the canvas keeps its full range for checkpoints
+/// and merging.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutputOpts {
+    /// Brightness adjustment in stops; each stop doubles the brightness
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+}
+
+impl OutputOpts {
+    /// Applies exposure and tone mapping to a linear color. The result is
+    /// still linear, and needs sRGB encoding before display.
+    pub fn apply(&self, color: &Color) -> Color {
+        self.tone_map.apply(&(color * 2f64.powf(self.exposure)))
+    }
+}
 
 pub fn output_ppm(canvas: &Canvas, out: &mut impl Write) -> Result<()> {
+    output_ppm_with_opts(canvas, &OutputOpts::default(), out)
+}
+
+pub fn output_ppm_with_opts(
+    canvas: &Canvas,
+    opts: &OutputOpts,
+    out: &mut impl Write,
+) -> Result<()> {
     writeln!(out, "P3")?;
     writeln!(out, "{} {}", canvas.width, canvas.height)?;
     writeln!(out, "255")?;
 
     canvas
         .pixel_data()
-        .map(|c| c.write_ppm(out))
+        .map(|c| opts.apply(&c).write_ppm(out))
         .collect::<Result<()>>()
 }
+
+/// Writes the canvas as a JPEG image, for quick previews. Colors are adjusted
+/// by `opts` and encoded as sRGB, as for PPM output.
+pub fn output_jpeg(
+    canvas: &Canvas,
+    opts: &OutputOpts,
+    quality: u8,
+    out: &mut impl Write,
+) -> image::ImageResult<()> {
+    let bytes = canvas
+        .pixel_data()
+        .flat_map(|c| {
+            let (r, g, b) = opts.apply(&c).linear_to_srgb().to_u8();
+            [r, g, b]
+        })
+        .collect::<Vec<_>>();
+    JpegEncoder::new_with_quality(out, quality).write_image(
+        &bytes,
+        canvas.width as u32,
+        canvas.height as u32,
+        ExtendedColorType::Rgb8,
+    )
+}
+
+/// Writes the canvas as an OpenEXR image, along with its first hit features and
+/// any AOV passes. Colors are written linearly with their full range, leaving
+/// exposure and tone mapping to the software reading the image. Channels are
+/// named as follows:
+/// - Color: `R`, `G`, `B`
+/// - Depth, normal and albedo, if the canvas has them: `Z`, `N.X`, `N.Y`,
+///   `N.Z`, `albedo.R`, `albedo.G`, `albedo.B`
+/// - Object and material IDs: `objectId`, `materialId`
+/// - Motion vectors: `motion.X`, `motion.Y`
+pub fn output_exr(
+    canvas: &Canvas,
+    aovs: Option<&AovBuffers>,
+    out: impl Write + Seek,
+) -> exr::error::UnitResult {
+    let colors = canvas.pixel_data().collect::<Vec<_>>();
+    let float_channel = |name: &str, values: Vec<f64>| {
+        AnyChannel::new(
+            name,
+            FlatSamples::F32(values.into_iter().map(|v| v as f32).collect()),
+        )
+    };
+    let color_channel = |name: &str, colors: &[Color], n: usize| {
+        float_channel(name, colors.iter().map(|c| c[n]).collect())
+    };
+
+    let mut channels = vec![
+        color_channel("R", &colors, 0),
+        color_channel("G", &colors, 1),
+        color_channel("B", &colors, 2),
+    ];
+
+    if let Some(aux) = canvas.aux_data() {
+        let (depth, (normal, albedo)): (Vec<_>, (Vec<_>, Vec<_>)) =
+            aux.map(|a| (a.depth, (a.normal, a.albedo))).unzip();
+        channels.extend([
+            float_channel("Z", depth),
+            color_channel("N.X", &normal, 0),
+            color_channel("N.Y", &normal, 1),
+            color_channel("N.Z", &normal, 2),
+            color_channel("albedo.R", &albedo, 0),
+            color_channel("albedo.G", &albedo, 1),
+            color_channel("albedo.B", &albedo, 2),
+        ]);
+    }
+
+    if let Some(aovs) = aovs {
+        assert_eq!((aovs.width, aovs.height), (canvas.width, canvas.height));
+
+        channels.extend([
+            AnyChannel::new("objectId", FlatSamples::U32(aovs.object_id.clone())),
+            AnyChannel::new("materialId", FlatSamples::U32(aovs.material_id.clone())),
+            float_channel("motion.X", aovs.motion.iter().map(|m| m.0).collect()),
+            float_channel("motion.Y", aovs.motion.iter().map(|m| m.1).collect()),
+        ]);
+    }
+
+    let layer = Layer::new(
+        (canvas.width, canvas.height),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+    Image::from_layer(layer).write().to_buffered(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use exr::prelude::{ReadChannels, ReadLayers};
+    use float_cmp::assert_approx_eq;
+
+    use crate::{
+        camera::{Camera, CameraParams},
+        hittable::sphere::Sphere,
+        material::Flat,
+    };
+
+    use super::*;
+
+    #[test]
+    fn exposure_is_measured_in_stops() {
+        let opts = OutputOpts {
+            exposure: -2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            opts.apply(&Color::new(4.0, 2.0, 1.0)),
+            Color::new(1.0, 0.5, 0.25)
+        );
+    }
+
+    #[test]
+    fn exr_output_includes_aov_channels() {
+        let camera = Camera::new(CameraParams {
+            image_width: 3,
+            samples_per_pixel: 1,
+            aux: true,
+            ..Default::default()
+        });
+        let empty_world = Vec::<Sphere<Flat>>::new();
+        let canvas = camera.render(empty_world.as_slice());
+        let aovs = camera.render_aovs(empty_world.as_slice());
+
+        let mut out = Cursor::new(Vec::new());
+        output_exr(&canvas, Some(&aovs), &mut out).unwrap();
+        out.set_position(0);
+        let image = exr::prelude::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(out)
+            .unwrap();
+
+        let channels = &image.layer_data.channel_data.list;
+        let names = channels
+            .iter()
+            .map(|c| c.name.to_string())
+            .collect::<Vec<_>>();
+        for name in [
+            "R", "G", "B", "Z", "N.X", "albedo.G", "objectId", "motion.Y",
+        ] {
+            assert!(
+                names.contains(&name.to_string()),
+                "missing channel {}",
+                name
+            );
+        }
+
+        // Nothing was hit, so every pixel shows the background with no depth
+        let channel = |name: &str| {
+            let c = channels
+                .iter()
+                .find(|c| c.name.to_string() == name)
+                .unwrap();
+            c.sample_data.values_as_f32().collect::<Vec<_>>()
+        };
+        assert_eq!(channel("B"), vec![1.0; 9]);
+        assert_eq!(channel("Z"), vec![0.0; 9]);
+    }
+
+    #[test]
+    fn tone_mapping_keeps_bright_emitters_from_blowing_out() {
+        let camera = Camera::new(CameraParams {
+            image_width: 1,
+            samples_per_pixel: 1,
+            ..Default::default()
+        });
+        let canvas = camera
+            .render(Vec::<Sphere<Flat>>::new().as_slice())
+            .with_pixel_data(vec![Color::new(2.0, 4.0, 16.0)]);
+        let ppm = |tone_map| {
+            let mut out = Vec::new();
+            output_ppm_with_opts(
+                &canvas,
+                &OutputOpts {
+                    tone_map,
+                    exposure: 0.0,
+                },
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .last()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(ppm(ToneMap::Clamp), "255 255 255");
+        for tone_map in [
+            ToneMap::Reinhard,
+            ToneMap::Aces,
+            ToneMap::Filmic { white: 11.2 },
+        ] {
+            let pixel = ppm(tone_map)
+                .split(' ')
+                .map(|c| c.parse::<u8>().unwrap())
+                .collect::<Vec<_>>();
+            assert!(pixel[0] < pixel[1] && pixel[1] <= pixel[2], "{:?}", pixel);
+        }
+    }
+
+    #[test]
+    fn filmic_maps_white_point_to_white() {
+        let mapped = ToneMap::Filmic { white: 11.2 }.apply(&Color::new(11.2, 11.2, 11.2));
+
+        assert_approx_eq!(&Color, &mapped, &Color::new(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,257 @@
+//! A small HTTP server for watching a render converge in a web browser, with
+//! no GUI dependencies. It serves:
+//! - `/`: a page showing the stream
+//! - `/stream`: an MJPEG stream, with a new frame each time one is published
+//! - `/image.jpg`: the latest frame
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use crate::{
+    camera::Canvas,
+    output::{self, OutputOpts},
+};
+
+const JPEG_QUALITY: u8 = 90;
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Render preview</title></head>
+<body style="margin: 0; background: #202020; display: flex; justify-content: center; align-items: center; height: 100vh">
+<img src="/stream" style="max-width: 100%; max-height: 100%">
+</body>
+</html>
+"#;
+
+#[derive(Default)]
+struct Frame {
+    /// Counts up from zero, which means nothing has been published yet
+    version: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct Latest {
+    frame: Mutex<Frame>,
+    published: Condvar,
+}
+
+impl Latest {
+    /// Waits for a frame newer than `version`
+    fn wait_for_newer(&self, version: u64) -> (u64, Arc<Vec<u8>>) {
+        let frame = self
+            .published
+            .wait_while(self.frame.lock().unwrap(), |f| f.version <= version)
+            .unwrap();
+        (frame.version, frame.jpeg.clone())
+    }
+}
+
+/// Serves the latest published canvas to any number of viewers. The server
+/// runs on background threads until the process exits.
+pub struct PreviewServer {
+    addr: SocketAddr,
+    latest: Arc<Latest>,
+    opts: OutputOpts,
+}
+
+impl PreviewServer {
+    /// Starts listening on `addr`, such as `"127.0.0.1:8080"`. Port 0 picks a
+    /// free port; see `local_addr`. Frames are published with exposure and
+    /// tone mapping from `opts`.
+    pub fn start(addr: impl ToSocketAddrs, opts: OutputOpts) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let latest = Arc::new(Latest::default());
+
+        let server_latest = latest.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let latest = server_latest.clone();
+                thread::spawn(move || {
+                    // Viewers disconnecting part way through is expected
+                    let _ = handle_connection(stream, &latest);
+                });
+            }
+        });
+
+        Ok(PreviewServer { addr, latest, opts })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Encodes the canvas and sends it to every viewer
+    pub fn publish(&self, canvas: &Canvas) {
+        let mut jpeg = Vec::new();
+        output::output_jpeg(canvas, &self.opts, JPEG_QUALITY, &mut jpeg).unwrap();
+
+        let mut frame = self.latest.frame.lock().unwrap();
+        frame.version += 1;
+        frame.jpeg = Arc::new(jpeg);
+        self.latest.published.notify_all();
+    }
+}
+
+fn handle_connection(stream: TcpStream, latest: &Latest) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers aren't needed, but are read so the client sees a clean close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let mut out = stream;
+    match path {
+        "/" => respond(&mut out, "200 OK", "text/html", PAGE.as_bytes()),
+        "/image.jpg" => {
+            let frame = latest.frame.lock().unwrap();
+            if frame.version == 0 {
+                respond(
+                    &mut out,
+                    "503 Service Unavailable",
+                    "text/plain",
+                    b"No image yet",
+                )
+            } else {
+                let jpeg = frame.jpeg.clone();
+                drop(frame);
+                respond(&mut out, "200 OK", "image/jpeg", &jpeg)
+            }
+        }
+        "/stream" => stream_frames(&mut out, latest),
+        _ => respond(&mut out, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+fn respond(out: &mut impl Write, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+/// Sends each frame as it's published, until the viewer goes away
+fn stream_frames(out: &mut impl Write, latest: &Latest) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut version = 0;
+    loop {
+        let (newer, jpeg) = latest.wait_for_newer(version);
+        version = newer;
+        write!(
+            out,
+            "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            jpeg.len()
+        )?;
+        out.write_all(&jpeg)?;
+        out.write_all(b"\r\n")?;
+        out.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, time::Duration};
+
+    use crate::{
+        camera::{Camera, CameraParams},
+        hittable::sphere::{Center, Sphere},
+        material::Flat,
+        vec3::Point3,
+    };
+
+    use super::*;
+
+    fn get(addr: SocketAddr, path: &str) -> Vec<u8> {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        response
+    }
+
+    fn body(response: &[u8]) -> &[u8] {
+        let start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        &response[start..]
+    }
+
+    fn canvas() -> Canvas {
+        let camera = Camera::new(CameraParams {
+            image_width: 8,
+            aspect_ratio: 2.0,
+            samples_per_pixel: 1,
+            ..Default::default()
+        });
+        let world = Sphere {
+            center: Center::Stationary(Point3::new(0.0, 0.0, -1.0)),
+            radius: 0.5,
+            material: Flat,
+        };
+        camera.render(&world)
+    }
+
+    #[test]
+    fn serving_the_latest_frame() {
+        let server = PreviewServer::start("127.0.0.1:0", OutputOpts::default()).unwrap();
+        let addr = server.local_addr();
+
+        assert!(get(addr, "/image.jpg").starts_with(b"HTTP/1.1 503"));
+
+        server.publish(&canvas());
+
+        let response = get(addr, "/image.jpg");
+        assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+        let image = image::load_from_memory(body(&response)).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+
+        assert!(get(addr, "/").starts_with(b"HTTP/1.1 200 OK"));
+        assert!(get(addr, "/nothing").starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn streaming_frames_as_they_are_published() {
+        let server = PreviewServer::start("127.0.0.1:0", OutputOpts::default()).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(stream, "GET /stream HTTP/1.1\r\n\r\n").unwrap();
+        // Fail rather than hang if a frame never arrives
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+
+        let canvas = canvas();
+        let mut received = Vec::new();
+        let mut buffer = [0; 4096];
+        for _ in 0..2 {
+            server.publish(&canvas);
+            // Wait for the frame to arrive before publishing the next one
+            let frames = received.windows(7).filter(|w| w == b"--frame").count();
+            while received.windows(7).filter(|w| w == b"--frame").count() == frames {
+                let n = stream.read(&mut buffer).unwrap();
+                assert_ne!(n, 0, "the server closed the stream");
+                received.extend_from_slice(&buffer[..n]);
+            }
+        }
+
+        assert!(received.starts_with(b"HTTP/1.1 200 OK"));
+        assert!(received
+            .windows(25)
+            .any(|w| w == b"multipart/x-mixed-replace"));
+    }
+}
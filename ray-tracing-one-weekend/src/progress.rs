@@ -0,0 +1,163 @@
+//! Progress reporting and cancellation for long renders.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// How far a render has got
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub pixels_done: usize,
+    pub pixels_total: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Fraction of the pixels done, in [0, 1]
+    pub fn fraction(&self) -> f64 {
+        if self.pixels_total == 0 {
+            1.0
+        } else {
+            self.pixels_done as f64 / self.pixels_total as f64
+        }
+    }
+
+    /// Estimates the time left, assuming the remaining pixels take as long as
+    /// those done so far. `None` until the first pixel is done.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.pixels_done == 0 {
+            None
+        } else {
+            let remaining = self.pixels_total.saturating_sub(self.pixels_done);
+            Some(
+                self.elapsed
+                    .mul_f64(remaining as f64 / self.pixels_done as f64),
+            )
+        }
+    }
+}
+
+/// Told about every pixel as it's finished. Renders run on many threads, so
+/// observers are called concurrently and should be quick.
+pub trait ProgressObserver: Sync {
+    fn update(&self, progress: &Progress);
+}
+
+impl<F: Fn(&Progress) + Sync> ProgressObserver for F {
+    fn update(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Prints how many rows of pixels are left to stderr, whenever another
+/// image-width's worth of pixels is done
+pub struct ScanlineProgress {
+    pub image_width: usize,
+}
+
+impl ProgressObserver for ScanlineProgress {
+    fn update(&self, progress: &Progress) {
+        if progress.pixels_done.is_multiple_of(self.image_width) {
+            eprintln!(
+                "Scanline-equivalents remaining: {}.",
+                (progress.pixels_total - progress.pixels_done) / self.image_width
+            );
+        }
+    }
+}
+
+/// Stops a render early. Clones share the same flag, so one can be handed to
+/// the render while another is kept to cancel it from elsewhere.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts finished pixels for an observer, and checks for cancellation
+pub(crate) struct Tracker<'a> {
+    observer: &'a dyn ProgressObserver,
+    cancel: &'a CancellationToken,
+    pixels_total: usize,
+    pixels_done: AtomicUsize,
+    start: Instant,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn new(
+        observer: &'a dyn ProgressObserver,
+        cancel: &'a CancellationToken,
+        pixels_total: usize,
+    ) -> Self {
+        Tracker {
+            observer,
+            cancel,
+            pixels_total,
+            pixels_done: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub(crate) fn pixel_done(&self) {
+        let pixels_done = self.pixels_done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.observer.update(&Progress {
+            pixels_done,
+            pixels_total: self.pixels_total,
+            elapsed: self.start.elapsed(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_scales_the_elapsed_time_by_the_pixels_left() {
+        let progress = Progress {
+            pixels_done: 25,
+            pixels_total: 100,
+            elapsed: Duration::from_secs(10),
+        };
+
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            Progress {
+                pixels_done: 0,
+                ..progress
+            }
+            .eta(),
+            None
+        );
+    }
+
+    #[test]
+    fn clones_of_a_token_share_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+    }
+}
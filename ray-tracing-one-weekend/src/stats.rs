@@ -0,0 +1,213 @@
+//! Counts of the rays cast and intersection tests made while rendering, for
+//! profiling. Only work done inside `measure` is counted; elsewhere `count`
+//! is a single atomic load.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+const COUNTER_COUNT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Rays from the camera
+    PrimaryRays,
+    /// Rays scattered from surfaces and volumes
+    ScatteredRays,
+    SphereTests,
+    PlaneTests,
+    QuadTests,
+    TriangleTests,
+    ConstantMediumTests,
+    /// Bounding boxes tested while traversing a `Bvh`
+    BvhNodesVisited,
+}
+
+impl Counter {
+    pub const ALL: [Counter; COUNTER_COUNT] = [
+        Counter::PrimaryRays,
+        Counter::ScatteredRays,
+        Counter::SphereTests,
+        Counter::PlaneTests,
+        Counter::QuadTests,
+        Counter::TriangleTests,
+        Counter::ConstantMediumTests,
+        Counter::BvhNodesVisited,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::PrimaryRays => "Primary",
+            Counter::ScatteredRays => "Scattered",
+            Counter::SphereTests => "Sphere",
+            Counter::PlaneTests => "Plane",
+            Counter::QuadTests => "Quad",
+            Counter::TriangleTests => "Triangle",
+            Counter::ConstantMediumTests => "Constant medium",
+            Counter::BvhNodesVisited => "BVH nodes visited",
+        }
+    }
+
+    fn is_ray(&self) -> bool {
+        matches!(self, Counter::PrimaryRays | Counter::ScatteredRays)
+    }
+
+    fn is_intersection_test(&self) -> bool {
+        !self.is_ray() && *self != Counter::BvhNodesVisited
+    }
+}
+
+type Counts = [AtomicU64; COUNTER_COUNT];
+
+/// Number of `measure` calls in progress
+static MEASURING: AtomicUsize = AtomicUsize::new(0);
+/// The counts of every thread which has counted anything. Rayon's workers each
+/// count into their own, so they never write to the same atomics.
+static ALL_COUNTS: Mutex<Vec<Arc<Counts>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static THIS_THREAD: Arc<Counts> = {
+        let counts = Arc::new(Counts::default());
+        ALL_COUNTS.lock().unwrap().push(counts.clone());
+        counts
+    };
+}
+
+#[inline]
+pub fn count(counter: Counter) {
+    if MEASURING.load(Ordering::Relaxed) > 0 {
+        THIS_THREAD.with(|c| c[counter as usize].fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+/// Runs `f`, returning its result along with what was counted while it ran.
+/// Anything rendered on other threads at the same time is counted too.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Stats) {
+    // Stops counting even if `f` panics
+    struct Measuring;
+    impl Drop for Measuring {
+        fn drop(&mut self) {
+            MEASURING.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    MEASURING.fetch_add(1, Ordering::Relaxed);
+    let _measuring = Measuring;
+    let before = totals();
+    let result = f();
+    (result, totals().since(&before))
+}
+
+fn totals() -> Stats {
+    let mut counts = [0; COUNTER_COUNT];
+    for thread in ALL_COUNTS.lock().unwrap().iter() {
+        for (total, c) in counts.iter_mut().zip(thread.iter()) {
+            *total += c.load(Ordering::Relaxed);
+        }
+    }
+    Stats { counts }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    counts: [u64; COUNTER_COUNT],
+}
+
+impl Stats {
+    pub fn get(&self, counter: Counter) -> u64 {
+        self.counts[counter as usize]
+    }
+
+    pub fn rays(&self) -> u64 {
+        Counter::ALL
+            .iter()
+            .filter(|c| c.is_ray())
+            .map(|&c| self.get(c))
+            .sum()
+    }
+
+    /// Primary and scattered rays per primary ray
+    pub fn average_path_depth(&self) -> f64 {
+        let primary = self.get(Counter::PrimaryRays);
+        if primary == 0 {
+            0.0
+        } else {
+            self.rays() as f64 / primary as f64
+        }
+    }
+
+    fn since(&self, earlier: &Stats) -> Stats {
+        let mut counts = self.counts;
+        for (count, e) in counts.iter_mut().zip(earlier.counts) {
+            *count = count.saturating_sub(e);
+        }
+        Stats { counts }
+    }
+
+    /// Formats the counts for printing after a render which took `elapsed`
+    pub fn summary(&self, elapsed: Duration) -> String {
+        let mut s = String::from("Render statistics:\n");
+        let rays_per_second = self.rays() as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        s.push_str(&format!(
+            "  Rays: {} ({:.2} M/s)\n",
+            self.rays(),
+            rays_per_second / 1e6
+        ));
+        for c in Counter::ALL.iter().filter(|c| c.is_ray()) {
+            s.push_str(&format!("    {}: {}\n", c.name(), self.get(*c)));
+        }
+        s.push_str(&format!(
+            "  Average path depth: {:.3}\n",
+            self.average_path_depth()
+        ));
+        s.push_str("  Intersection tests:\n");
+        for c in Counter::ALL.iter().filter(|c| c.is_intersection_test()) {
+            s.push_str(&format!("    {}: {}\n", c.name(), self.get(*c)));
+        }
+        s.push_str(&format!(
+            "  {}: {}\n",
+            Counter::BvhNodesVisited.name(),
+            self.get(Counter::BvhNodesVisited)
+        ));
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measuring_counts_work_on_every_thread() {
+        let ((), stats) = measure(|| {
+            std::thread::scope(|s| {
+                for _ in 0..4 {
+                    s.spawn(|| {
+                        for _ in 0..10 {
+                            count(Counter::ConstantMediumTests);
+                        }
+                    });
+                }
+            })
+        });
+
+        // Other tests may be measuring at the same time
+        assert!(stats.get(Counter::ConstantMediumTests) >= 40);
+    }
+
+    #[test]
+    fn summary_reports_rays_per_second_and_path_depth() {
+        let mut stats = Stats::default();
+        stats.counts[Counter::PrimaryRays as usize] = 1_000_000;
+        stats.counts[Counter::ScatteredRays as usize] = 2_000_000;
+
+        let summary = stats.summary(Duration::from_secs(2));
+
+        assert!(summary.contains("Rays: 3000000 (1.50 M/s)"));
+        assert!(summary.contains("Average path depth: 3.000"));
+    }
+}
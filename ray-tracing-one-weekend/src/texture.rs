@@ -1,6 +1,12 @@
-use image::Rgb32FImage;
+use std::path::Path;
 
-use crate::{color::Color, perlin::Perlin, vec3::Point3};
+use image::{ImageReader, ImageResult, Rgb32FImage};
+
+use crate::{
+    color::{srgb, Color},
+    perlin::Perlin,
+    vec3::Point3,
+};
 
 pub trait Texture: Sync + Send {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
@@ -12,6 +18,12 @@ impl<T: Texture> Texture for &T {
     }
 }
 
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        (**self).value(u, v, p)
+    }
+}
+
 pub struct SolidColor {
     pub albedo: Color,
 }
@@ -22,6 +34,20 @@ impl Texture for SolidColor {
     }
 }
 
+/// Blends between a color at each corner of a triangle. Only meaningful on
+/// triangles without texture coordinates, whose (u, v) are the barycentric
+/// weights of the second and third corners.
+pub struct VertexColors {
+    pub colors: [Color; 3],
+}
+
+impl Texture for VertexColors {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let [c1, c2, c3] = &self.colors;
+        (1.0 - u - v) * c1 + u * c2 + v * c3
+    }
+}
+
 pub struct Checker<A, B> {
     pub even: A,
     pub odd: B,
@@ -59,6 +85,18 @@ pub struct Image {
     pub image: Rgb32FImage,
 }
 
+impl Image {
+    /// Loads an image file, converting its colors from sRGB to linear
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        let mut image = ImageReader::open(path)?.decode()?.into_rgb32f();
+        // image crate isn't color-space aware: https://github.com/image-rs/image/issues/2031#issuecomment-1764804355
+        image.pixels_mut().for_each(|p| {
+            p.0 = p.0.map(|u| srgb::srgb_to_linear(u as f64) as f32);
+        });
+        Ok(Image { image })
+    }
+}
+
 impl Texture for Image {
     fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
         assert!(
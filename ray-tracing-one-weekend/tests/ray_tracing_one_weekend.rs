@@ -1,6 +1,9 @@
 use ray_tracing_one_weekend::{
-    camera::{Camera, CameraParams},
+    camera::{Camera, CameraParams, Canvas, Region},
     color::Color,
+    denoise::Denoiser,
+    distributed,
+    filter::Filter,
     hittable::{
         sphere::{Center, Sphere},
         Hittable,
@@ -69,6 +72,7 @@ fn test_scene() -> (impl Hittable<Material = impl Material> + Sync, CameraParams
         focus_dist: 3.4,
         background: Color::new(0.7, 0.8, 1.0),
         seed: 0,
+        ..Default::default()
     };
 
     (world, params)
@@ -160,3 +164,162 @@ fn checkpointing_and_no_checkpointing_not_equivalent() {
 
     assert!(lq_data != hq_data);
 }
+
+#[test]
+fn merging_sample_ranges_equivalent_to_checkpointing() {
+    let (world, mut params) = test_scene();
+    // Increase test speed
+    params.samples_per_pixel = 5;
+    params.image_width = 100;
+
+    let camera = Camera::new(params);
+
+    let first = camera.render(&world);
+    let checkpointed = camera.render_from_checkpoint(&world, &first);
+
+    let ranges = distributed::partition_samples(10, 2);
+    let canvases = ranges
+        .into_iter()
+        .map(|r| {
+            // Round-trip through the worker output format
+            let mut buf = Vec::new();
+            distributed::write_canvas(&camera.render_samples(&world, r), &mut buf).unwrap();
+            distributed::read_canvas(buf.as_slice()).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let merged = distributed::merge_all(canvases).unwrap();
+
+    assert_eq!(merged.samples, 10);
+    assert!(
+        merged.pixel_data().collect::<Vec<_>>() == checkpointed.pixel_data().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn rendering_a_region() {
+    let (world, mut params) = test_scene();
+    // Increase test speed
+    params.samples_per_pixel = 5;
+    params.image_width = 100;
+
+    let full = Camera::new(params.clone()).render(&world);
+
+    let region = Region {
+        x: 10,
+        y: 20,
+        width: 30,
+        height: 15,
+    };
+    params.region = Some(region.clone());
+    let cropped = Camera::new(params).render(&world);
+
+    assert_eq!((cropped.width, cropped.height), (full.width, full.height));
+
+    let black = Color::new(0.0, 0.0, 0.0);
+    full.pixel_data()
+        .zip(cropped.pixel_data())
+        .enumerate()
+        .for_each(|(n, (f, c))| {
+            let (x, y) = (n % full.width, n / full.width);
+            if region.contains(x, y) {
+                assert_eq!(f, c, "pixel ({}, {}) inside region should match", x, y);
+            } else {
+                assert_eq!(
+                    c, black,
+                    "pixel ({}, {}) outside region should be empty",
+                    x, y
+                );
+            }
+        });
+}
+
+#[test]
+fn merging_filtered_sample_ranges_equivalent_to_checkpointing() {
+    let (world, mut params) = test_scene();
+    // Increase test speed
+    params.samples_per_pixel = 5;
+    params.image_width = 100;
+    params.filter = Filter::MitchellNetravali {
+        radius: 2.0,
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+
+    let camera = Camera::new(params);
+
+    let first = camera.render(&world);
+    let checkpointed = camera.render_from_checkpoint(&world, &first);
+
+    let merged = distributed::merge_all(
+        distributed::partition_samples(10, 2)
+            .into_iter()
+            .map(|r| camera.render_samples(&world, r)),
+    )
+    .unwrap();
+
+    assert!(
+        merged.pixel_data().collect::<Vec<_>>() == checkpointed.pixel_data().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn filtering_preserves_flat_color() {
+    let (_, mut params) = test_scene();
+    // Increase test speed
+    params.samples_per_pixel = 3;
+    params.image_width = 40;
+    params.filter = Filter::Gaussian {
+        radius: 1.5,
+        alpha: 2.0,
+    };
+
+    let empty_world = Vec::<Sphere<Lambertian<SolidColor>>>::new();
+    let canvas = Camera::new(params.clone()).render(empty_world.as_slice());
+
+    // Weights are normalized, so the background comes through unchanged, even at the edges
+    canvas.pixel_data().for_each(|c| {
+        assert!((0..3).all(|n| (c[n] - params.background[n]).abs() < 1e-12));
+    });
+}
+
+#[test]
+fn denoising_brings_render_closer_to_converged_image() {
+    let (world, mut params) = test_scene();
+    // Increase test speed
+    params.image_width = 48;
+    params.defocus_angle = 0.0;
+    params.aux = true;
+
+    let mean_squared_error = |a: &Canvas, b: &Canvas| {
+        a.pixel_data()
+            .zip(b.pixel_data())
+            .map(|(c1, c2)| (c1 - c2).length_squared())
+            .sum::<f64>()
+            / (a.width * a.height) as f64
+    };
+
+    params.samples_per_pixel = 200;
+    let converged = Camera::new(params.clone()).render(&world);
+    params.samples_per_pixel = 4;
+    let noisy = Camera::new(params).render(&world);
+    let denoised = Denoiser::default().denoise(&noisy);
+
+    assert!(mean_squared_error(&denoised, &converged) < mean_squared_error(&noisy, &converged));
+}
+
+#[test]
+fn denoising_preserves_flat_color() {
+    let (_, mut params) = test_scene();
+    // Increase test speed
+    params.samples_per_pixel = 1;
+    params.image_width = 40;
+    params.aux = true;
+
+    let empty_world = Vec::<Sphere<Lambertian<SolidColor>>>::new();
+    let canvas = Camera::new(params.clone()).render(empty_world.as_slice());
+    let denoised = Denoiser::default().denoise(&canvas);
+
+    denoised.pixel_data().for_each(|c| {
+        assert!((0..3).all(|n| (c[n] - params.background[n]).abs() < 1e-12));
+    });
+}